@@ -12,6 +12,8 @@ use super::consts::*;
 use crate::error::Result;
 use rim::{
     components::Component,
+    journal::Journal,
+    rollback::Snapshot,
     setter,
     toolset_manifest::ToolsetManifest,
     update::UpdateCheckBlocker,
@@ -20,6 +22,7 @@ use rim::{
 };
 use serde::Serialize;
 use tauri::{App, Window};
+use url::Url;
 
 #[allow(clippy::type_complexity)]
 static THREAD_POOL: LazyLock<Mutex<Vec<JoinHandle<anyhow::Result<()>>>>> =
@@ -102,6 +105,7 @@ pub(crate) fn install_toolkit_in_new_thread(
     components_list: Vec<Component>,
     install_dir: PathBuf,
     manifest: ToolsetManifest,
+    manifest_url: Option<Url>,
     is_update: bool,
 ) {
     UpdateCheckBlocker::block();
@@ -120,14 +124,21 @@ pub(crate) fn install_toolkit_in_new_thread(
 
         // TODO: Use continuous progress
         let mut config = InstallConfiguration::new(&install_dir, &manifest)?
-            .with_progress_indicator(Some(progress));
+            .with_progress_indicator(Some(progress))
+            .with_manifest_url(manifest_url);
         if let Some(rustup_dist_server) = get_cli().rustup_dist_server.as_deref() {
             config = config.with_rustup_dist_server(rustup_dist_server.parse()?);
         }
         if is_update {
             config.update(components_list)?;
-        } else {
-            config.install(components_list)?;
+        } else if let Err(e) = config.install(components_list) {
+            // revert the changes that have been made by this failed installation
+            if let Some(journal) = Journal::load_unfinished(&install_dir)? {
+                if let Err(rollback_err) = journal.rollback() {
+                    log::error!("{rollback_err}");
+                }
+            }
+            return Err(e);
         }
 
         // 安装完成后，发送安装完成事件
@@ -172,6 +183,75 @@ pub(crate) fn uninstall_toolkit_in_new_thread(window: tauri::Window, remove_self
         .push(handle);
 }
 
+pub(crate) fn repair_toolkit_in_new_thread(window: tauri::Window) {
+    UpdateCheckBlocker::block();
+
+    let handle = thread::spawn(move || -> anyhow::Result<()> {
+        // FIXME: this is needed to make sure the other thread could recieve the first couple messages
+        // we sent in this thread. But it feels very wrong, there has to be better way.
+        thread::sleep(Duration::from_millis(500));
+
+        window.emit(BLOCK_EXIT_EVENT, true)?;
+
+        let pos_cb =
+            |pos: f32| -> anyhow::Result<()> { Ok(window.emit(PROGRESS_UPDATE_EVENT, pos)?) };
+        let progress = Progress::new(&pos_cb);
+
+        let manifest = ToolsetManifest::load_from_install_dir()?;
+        let config = InstallConfiguration::new(rim::get_installed_dir(), &manifest)?
+            .with_progress_indicator(Some(progress));
+        let plan = config.detect_broken()?;
+        if plan.is_empty() {
+            log::info!("{}", t!("nothing_to_repair"));
+            window.emit(PROGRESS_UPDATE_EVENT, 100.0)?;
+        } else {
+            config.repair(&plan)?;
+        }
+
+        window.emit(ON_COMPLETE_EVENT, ())?;
+        window.emit(BLOCK_EXIT_EVENT, false)?;
+        Ok(())
+    });
+
+    THREAD_POOL
+        .lock()
+        .expect("failed pushing repair thread handle into thread pool")
+        .push(handle);
+}
+
+pub(crate) fn rollback_toolkit_in_new_thread(window: tauri::Window) {
+    UpdateCheckBlocker::block();
+
+    let handle = thread::spawn(move || -> anyhow::Result<()> {
+        // FIXME: this is needed to make sure the other thread could recieve the first couple messages
+        // we sent in this thread. But it feels very wrong, there has to be better way.
+        thread::sleep(Duration::from_millis(500));
+
+        window.emit(BLOCK_EXIT_EVENT, true)?;
+
+        let pos_cb =
+            |pos: f32| -> anyhow::Result<()> { Ok(window.emit(PROGRESS_UPDATE_EVENT, pos)?) };
+        let progress = Progress::new(&pos_cb);
+
+        let install_dir = rim::get_installed_dir();
+        let snapshot = Snapshot::load(install_dir)?
+            .ok_or_else(|| anyhow::anyhow!("{}", t!("no_previous_toolkit")))?;
+        let plan = snapshot.rollback_plan(install_dir)?;
+        InstallConfiguration::for_snapshot(install_dir, &snapshot)?
+            .with_progress_indicator(Some(progress))
+            .rollback(&plan)?;
+
+        window.emit(ON_COMPLETE_EVENT, ())?;
+        window.emit(BLOCK_EXIT_EVENT, false)?;
+        Ok(())
+    });
+
+    THREAD_POOL
+        .lock()
+        .expect("failed pushing rollback thread handle into thread pool")
+        .push(handle);
+}
+
 #[derive(serde::Serialize)]
 pub struct Language {
     pub id: String,
@@ -283,6 +363,14 @@ pub(crate) fn setup_main_window(manager: &mut App, log_receiver: Receiver<String
         log::error!("unable to apply window effects: {e}");
     }
 
+    // show the progress of each download, including the ones of unknown length
+    let win = window.clone();
+    utils::set_progress_listener(move |progress| {
+        if let Err(e) = win.emit(DOWNLOAD_PROGRESS_EVENT, progress) {
+            log::error!("unexpected error occurred while emiting tauri event: {e}");
+        }
+    });
+
     spawn_gui_update_thread(window, log_receiver);
     Ok(())
 }
@@ -1,6 +1,7 @@
 // Events
 pub(crate) const MESSAGE_UPDATE_EVENT: &str = "update-message";
 pub(crate) const PROGRESS_UPDATE_EVENT: &str = "update-progress";
+pub(crate) const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
 pub(crate) const ON_COMPLETE_EVENT: &str = "on-complete";
 pub(crate) const ON_FAILED_EVENT: &str = "on-failed";
 pub(crate) const BLOCK_EXIT_EVENT: &str = "toggle-exit-blocker";
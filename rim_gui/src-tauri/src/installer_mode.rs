@@ -119,6 +119,8 @@ fn install_toolchain(window: tauri::Window, components_list: Vec<Component>, ins
         components_list,
         install_dir,
         cached_manifest().to_owned(),
+        // the manifest bundled in the installer is used
+        None,
         false,
     );
 }
@@ -41,6 +41,8 @@ fn main() -> Result<()> {
                     args.execute()?;
                     return Ok(());
                 }
+                // manage the installation specified by `--root`, or the default one
+                rim::installations::select_installed_dir(args.root())?;
             }
             if let Err(e) = handle_autostart() {
                 // log the error but do NOT abort the program
@@ -11,9 +11,10 @@ use crate::{
     notification::{self, Notification, NotificationAction},
 };
 use anyhow::Context;
-use rim::configuration::{Configuration, UpdateTarget, DEFAULT_UPDATE_CHECK_DURATION};
+use rim::configuration::{Configuration, Pins, UpdateTarget, DEFAULT_UPDATE_CHECK_DURATION};
 use rim::{
     components::Component,
+    rollback::Snapshot,
     toolkit::{self, Toolkit},
     toolset_manifest::{get_toolset_manifest, ToolsetManifest},
     update::{self, UpdateCheckBlocker, UpdateOpt},
@@ -25,12 +26,13 @@ use tauri::{
 };
 use url::Url;
 
-static SELECTED_TOOLSET: Mutex<Option<ToolsetManifest>> = Mutex::new(None);
+/// The manifest of the toolkit selected to install, and the url it was downloaded from.
+static SELECTED_TOOLSET: Mutex<Option<(ToolsetManifest, Url)>> = Mutex::new(None);
 // If adding more notification windows, make sure their label start with 'notification:'
 const MANAGER_UPD_POPUP_LABEL: &str = "notification:manager";
 const TOOLKIT_UPD_POPUP_LABEL: &str = "notification:toolkit";
 
-fn selected_toolset<'a>() -> MutexGuard<'a, Option<ToolsetManifest>> {
+fn selected_toolset<'a>() -> MutexGuard<'a, Option<(ToolsetManifest, Url)>> {
     SELECTED_TOOLSET
         .lock()
         .expect("unable to lock global mutex")
@@ -51,6 +53,10 @@ pub(super) fn main(msg_recv: Receiver<String>) -> Result<()> {
             get_install_dir,
             uninstall_toolkit,
             install_toolkit,
+            repair_toolkit,
+            get_previous_kit,
+            rollback_toolkit,
+            get_pins,
             check_updates_in_background,
             get_toolkit_from_url,
             common::supported_languages,
@@ -117,11 +123,40 @@ fn uninstall_toolkit(window: tauri::Window, remove_self: bool) {
     common::uninstall_toolkit_in_new_thread(window, remove_self);
 }
 
+#[tauri::command]
+fn repair_toolkit(window: tauri::Window) {
+    common::repair_toolkit_in_new_thread(window);
+}
+
+/// Get the toolkit that was installed before the last toolkit update, which can be rolled back to.
+#[tauri::command]
+fn get_previous_kit() -> Result<Option<Toolkit>> {
+    let Some(snapshot) = Snapshot::load(rim::get_installed_dir())? else {
+        return Ok(None);
+    };
+    Ok(Some(Toolkit::try_from(&snapshot.manifest)?))
+}
+
+#[tauri::command]
+fn rollback_toolkit(window: tauri::Window) {
+    common::rollback_toolkit_in_new_thread(window);
+}
+
+/// Get the components pinned by the user, which are skipped when updating toolkit.
+#[tauri::command]
+fn get_pins() -> Pins {
+    Configuration::load_from_install_dir().pins
+}
+
 #[tauri::command(rename_all = "snake_case")]
-fn install_toolkit(window: tauri::Window, components_list: Vec<Component>) -> Result<()> {
+fn install_toolkit(window: tauri::Window, mut components_list: Vec<Component>) -> Result<()> {
+    // in case a pinned component was selected somehow
+    let pins = Configuration::load_from_install_dir().pins;
+    components_list.retain(|c| !pins.is_pinned(c));
+
     UpdateOpt::new().update_toolkit(|p| {
         let guard = selected_toolset();
-        let manifest = guard
+        let (manifest, url) = guard
             .as_ref()
             .expect("internal error: a toolkit must be selected to install");
         common::install_toolkit_in_new_thread(
@@ -129,6 +164,7 @@ fn install_toolkit(window: tauri::Window, components_list: Vec<Component>) -> Re
             components_list,
             p.to_path_buf(),
             manifest.to_owned(),
+            Some(url.clone()),
             true,
         );
         Ok(())
@@ -211,13 +247,13 @@ fn get_toolkit_from_url(url: String) -> Result<Toolkit> {
     let url_ = Url::parse(&url)?;
 
     // load the manifest for components information
-    let manifest = async_runtime::block_on(get_toolset_manifest(Some(url_), false))?;
+    let manifest = async_runtime::block_on(get_toolset_manifest(Some(url_.clone()), false))?;
     // convert it to toolkit
     let toolkit = Toolkit::try_from(&manifest)?;
 
     // cache the selected toolset manifest
     let mut guard = selected_toolset();
-    *guard = Some(manifest);
+    *guard = Some((manifest, url_));
 
     Ok(toolkit)
 }
@@ -1,6 +1,8 @@
 export * from './types/Component';
 export * from './types/KitItem';
 export * from './types/CheckBoxGroup';
+export * from './types/Pins';
+export * from './types/DownloadProgress';
 export * from './installConf';
 export * from './invokeCommand';
 export * from './progress';
@@ -1,13 +1,14 @@
 import { ref, Ref, shallowRef } from 'vue';
 import { KitItem } from './types/KitItem';
-import { Component } from './types/Component';
+import { Component, ComponentType } from './types/Component';
+import { Pins } from './types/Pins';
 import { CheckGroup, CheckGroupItem } from './types/CheckBoxGroup';
 import LabelComponent from '@/views/manager/components/Label.vue';
 import { invokeCommand } from './invokeCommand';
 import { AppInfo } from './types/AppInfo';
 
 type Target = {
-  operation: 'update' | 'uninstall';
+  operation: 'update' | 'uninstall' | 'repair' | 'rollback';
   components: Component[];
 };
 
@@ -17,6 +18,7 @@ class ManagerConf {
   private _availableKits: Ref<KitItem[]> = ref([]);
   private _installedKit: Ref<KitItem | null> = ref(null);
   private _current: Ref<KitItem | null> = ref(null);
+  private _pins: Ref<Pins> = ref({});
   private _target: Ref<Target> = ref({ operation: 'update', components: [] });
   private _isUninstallManager: Ref<boolean> = ref(false);
 
@@ -61,14 +63,16 @@ class ManagerConf {
         let installedVersion = installedItem?.version;
         let isVerDifferent = installedVersion !== undefined && installedVersion !== item.version;
         let isRequiredButNotInstalled = item.required && installedItem === undefined;
+        // pinned components are skipped when updating
+        let isPinned = this.isPinned(item);
 
         let versionStr = isVerDifferent ? `(${installedVersion} -> ${item.version})` : ` (${item.version})`;
 
         return {
           label: `${item.name}${versionStr}`,
-          checked: isVerDifferent || isRequiredButNotInstalled,
+          checked: !isPinned && (isVerDifferent || isRequiredButNotInstalled),
           required: item.required,
-          disabled: false,
+          disabled: isPinned,
 
           focused: false,
           value: item,
@@ -77,6 +81,7 @@ class ManagerConf {
             label: item.name,
             oldVer: installedVersion,
             newVer: item.version,
+            pinned: isPinned,
           },
         };
       }) || [];
@@ -104,6 +109,14 @@ class ManagerConf {
     return Object.values(groups);
   }
 
+  /** Check if a component was pinned, toolchain components are pinned altogether. */
+  public isPinned(component: Component): boolean {
+    if (component.kind !== ComponentType.Tool) {
+      return this._pins.value.toolchain !== undefined;
+    }
+    return this._pins.value.tools?.[component.name] !== undefined;
+  }
+
   public getOperation() {
     return this._target.value.operation;
   }
@@ -182,7 +195,12 @@ class ManagerConf {
     this.setKits(availableKits);
   }
 
+  async loadPins() {
+    this._pins.value = (await invokeCommand('get_pins')) as Pins;
+  }
+
   async reloadKits() {
+    await this.loadPins()
     await this.loadInstalledKit()
     await this.loadAvailableKits()
   }
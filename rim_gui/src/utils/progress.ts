@@ -1,3 +1,14 @@
 export function progressFormat(value: number) {
   return value.toFixed(2).padStart(5, '0') + '%';
 }
+
+export function bytesFormat(bytes: number) {
+  const units = ['B', 'KiB', 'MiB', 'GiB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return `${unit === 0 ? value : value.toFixed(2)} ${units[unit]}`;
+}
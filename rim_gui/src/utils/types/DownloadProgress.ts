@@ -0,0 +1,7 @@
+export interface DownloadProgress {
+    name: string,
+    downloaded: number,
+    // `null` if the server does not tell the length of the file
+    total: number | null,
+    finished: boolean,
+}
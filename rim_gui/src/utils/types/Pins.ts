@@ -0,0 +1,6 @@
+export interface Pins {
+  /** The pinned version of Rust toolchain, covering every toolchain component. */
+  toolchain?: string;
+  /** Pinned tools, mapping tool names to the versions they were pinned at. */
+  tools?: Record<string, string>;
+}
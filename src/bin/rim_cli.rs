@@ -1,7 +1,20 @@
+use std::process::ExitCode;
+
 use anyhow::Result;
-use rim::Mode;
+use rim::{error::ErrorKind, Mode};
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // same as the output of returning an error from `main`
+            eprintln!("Error: {e:?}");
+            ExitCode::from(ErrorKind::of(&e).exit_code())
+        }
+    }
+}
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     match Mode::detect(None, None) {
         Mode::Installer(cli) => cli?.execute()?,
         Mode::Manager(cli) => cli?.execute()?,
@@ -0,0 +1,249 @@
+//! Answer file of the installer, which contains the options that would otherwise be
+//! passed from command line or asked interactively, allowing unattended installation
+//! with `--config`, and can be written after an interactive session with `--save-answers`.
+//!
+//! # Example
+//! ```toml
+//! lang = "en"
+//! prefix = "/opt/rust"
+//! manifest = "https://example.com/toolset-manifest.toml"
+//! components = ["llvm-tools", "cargo-expand"]
+//! rustup-dist-server = "https://example.com/rustup"
+//! rustup-update-root = "https://example.com/rustup/rustup"
+//! no-modify-path = true
+//!
+//! [registry]
+//! name = "mirror"
+//! url = "sparse+https://example.com/crates.io-index/"
+//!
+//! [proxy]
+//! https = "http://proxy.example.com:8080"
+//! no-proxy = "localhost,127.0.0.1"
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{Installer, PathOrUrl};
+use crate::components::Component;
+use crate::core::parser::TomlParser;
+use crate::core::Language;
+use crate::fingerprint::CargoRegistry;
+use crate::toolset_manifest::Proxy;
+use crate::utils;
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct AnswerFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) prefix: Option<PathBuf>,
+    /// Path or url of the toolset manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) manifest: Option<String>,
+    /// Components to install, required components will be installed whether listed or not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) components: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) registry: Option<CargoRegistry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rustup_dist_server: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) rustup_update_root: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) proxy: Option<Proxy>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) no_modify_path: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) no_modify_env: bool,
+}
+
+impl TomlParser for AnswerFile {
+    const FILENAME: &'static str = "rim-answers.toml";
+}
+
+impl AnswerFile {
+    /// Load an answer file from `path` and check the values in it,
+    /// except for the component names, which can only be checked against a toolset manifest,
+    /// see [`check_components`](AnswerFile::check_components).
+    ///
+    /// # Error
+    /// Return error if the file cannot be parsed, contains unknown keys, or has invalid values.
+    pub(crate) fn load_checked(path: &Path) -> Result<Self> {
+        let answers = <Self as TomlParser>::load(path)
+            .with_context(|| format!("invalid answer file '{}'", path.display()))?;
+        answers
+            .check()
+            .with_context(|| format!("invalid answer file '{}'", path.display()))?;
+        Ok(answers)
+    }
+
+    fn check(&self) -> Result<()> {
+        if let Some(lang) = &self.lang {
+            Language::from_str(lang)?;
+        }
+        if matches!(&self.prefix, Some(p) if utils::is_root_dir(p)) {
+            bail!("`prefix` cannot be the root directory");
+        }
+        if let Some(manifest) = &self.manifest {
+            PathOrUrl::from_str(manifest)?;
+        }
+        if let Some(registry) = &self.registry {
+            if registry.name.trim().is_empty() || registry.url.trim().is_empty() {
+                bail!("both `name` and `url` of `registry` must not be empty");
+            }
+        }
+        if self.no_modify_env && self.no_modify_path {
+            bail!("`no-modify-path` cannot be used with `no-modify-env`");
+        }
+        Ok(())
+    }
+
+    /// Make sure every component in the answer file exists in `all_components`.
+    pub(crate) fn check_components(names: &[String], all_components: &[Component]) -> Result<()> {
+        let unknown = names
+            .iter()
+            .filter(|name| !all_components.iter().any(|c| &c.name == *name))
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            bail!("{}", t!("unknown_components", list = unknown.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// Write the answers to `path`, overriding the existing file.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        utils::write_file(path, &self.to_toml()?, false)?;
+        info!("{}", t!("answers_saved", path = path.display()));
+        Ok(())
+    }
+}
+
+impl Installer {
+    /// Fill the options that were not given from command line with the values from `answers`,
+    /// and disable interaction.
+    pub(super) fn merge_answers(&self, answers: AnswerFile) -> Result<Self> {
+        let mut merged = self.clone();
+        merged.yes_to_all = true;
+        merged.no_modify_path |= answers.no_modify_path;
+        merged.no_modify_env |= answers.no_modify_env;
+        if merged.no_modify_path && merged.no_modify_env {
+            bail!("`no-modify-path` cannot be used with `no-modify-env`");
+        }
+        merged.lang = self.lang.clone().or(answers.lang);
+        merged.prefix = self.prefix.clone().or(answers.prefix);
+        if self.manifest.is_none() {
+            merged.manifest = answers
+                .manifest
+                .as_deref()
+                .map(PathOrUrl::from_str)
+                .transpose()?;
+        }
+        merged.component = self.component.clone().or(answers.components);
+        if let (None, Some(registry)) = (&self.registry_url, answers.registry) {
+            merged.registry_name = registry.name;
+            merged.registry_url = Some(registry.url);
+        }
+        merged.rustup_dist_server = self
+            .rustup_dist_server
+            .clone()
+            .or(answers.rustup_dist_server);
+        merged.rustup_update_root = self
+            .rustup_update_root
+            .clone()
+            .or(answers.rustup_update_root);
+        merged.proxy = answers.proxy;
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_answer_file() {
+        let input = r#"
+lang = "en"
+prefix = "/opt/rust"
+components = ["a", "b"]
+rustup-dist-server = "https://example.com/rustup"
+no-modify-path = true
+
+[registry]
+name = "mirror"
+url = "sparse+https://example.com/index/"
+
+[proxy]
+https = "http://proxy.example.com:8080"
+no-proxy = "localhost"
+"#;
+        let answers = AnswerFile::from_str(input).unwrap();
+        answers.check().unwrap();
+        assert_eq!(answers.lang.as_deref(), Some("en"));
+        assert_eq!(answers.prefix, Some(PathBuf::from("/opt/rust")));
+        assert_eq!(
+            answers.components,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(answers.registry.unwrap().name, "mirror");
+        assert!(answers.proxy.unwrap().https.is_some());
+        assert!(answers.no_modify_path);
+        assert!(!answers.no_modify_env);
+
+        // round trip
+        let answers = AnswerFile {
+            lang: Some("cn".into()),
+            components: Some(vec!["a".into()]),
+            ..Default::default()
+        };
+        let back = AnswerFile::from_str(&answers.to_toml().unwrap()).unwrap();
+        assert_eq!(answers, back);
+    }
+
+    #[test]
+    fn reject_invalid_answer_file() {
+        // unknown keys
+        assert!(AnswerFile::from_str("prefixx = '/opt/rust'").is_err());
+        // invalid url
+        assert!(AnswerFile::from_str("rustup-dist-server = 'not a url'").is_err());
+
+        let invalid_lang = AnswerFile::from_str("lang = 'fr'").unwrap();
+        assert!(invalid_lang.check().is_err());
+        let conflicts =
+            AnswerFile::from_str("no-modify-path = true\nno-modify-env = true").unwrap();
+        assert!(conflicts.check().is_err());
+    }
+
+    #[test]
+    fn check_component_names() {
+        let all = vec![Component::new("a", ""), Component::new("b", "")];
+        assert!(AnswerFile::check_components(&["a".into()], &all).is_ok());
+        assert!(AnswerFile::check_components(&["a".into(), "c".into()], &all).is_err());
+    }
+
+    #[test]
+    fn command_line_takes_precedence() {
+        let installer = Installer {
+            prefix: Some(PathBuf::from("/from/cli")),
+            ..Default::default()
+        };
+        let answers = AnswerFile {
+            prefix: Some(PathBuf::from("/from/answers")),
+            components: Some(vec!["a".into()]),
+            no_modify_env: true,
+            ..Default::default()
+        };
+        let merged = installer.merge_answers(answers).unwrap();
+        assert_eq!(merged.prefix, Some(PathBuf::from("/from/cli")));
+        assert_eq!(merged.component, Some(vec!["a".to_string()]));
+        assert!(merged.no_modify_env);
+        assert!(merged.yes_to_all);
+    }
+}
@@ -0,0 +1,112 @@
+//! Separated module to manage the download cache in command line.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::Subcommand;
+use serde::Serialize;
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::core::cache::DownloadCache;
+use crate::core::plan::human_size;
+use crate::get_installed_dir;
+
+#[derive(Subcommand, Debug)]
+pub(super) enum CacheCommand {
+    /// Show the cached packages, the most recently used first
+    List,
+    /// Show the total size of the cache
+    Size,
+    /// Remove the cached packages
+    Clean {
+        /// Only remove the least recently used packages until the cache is no larger than
+        /// this size in MiB
+        #[arg(long, value_name = "MiB")]
+        max_size: Option<u64>,
+    },
+}
+
+impl CacheCommand {
+    fn execute(&self) -> Result<()> {
+        let cache = DownloadCache::new(get_installed_dir());
+        match self {
+            Self::List => list_entries(&cache)?,
+            Self::Size => show_size(&cache)?,
+            Self::Clean { max_size } => {
+                let freed = match max_size {
+                    Some(mib) => cache.trim(mib.saturating_mul(1024 * 1024))?,
+                    None => cache.clean()?,
+                };
+                info!("{}", t!("cache_cleaned", size = human_size(freed)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Execute `cache` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Cache { command } = subcommand else {
+        return Ok(false);
+    };
+
+    command.execute()?;
+    Ok(true)
+}
+
+fn list_entries(cache: &DownloadCache) -> Result<()> {
+    let entries = cache.entries()?;
+    if OutputFormat::is_json() {
+        return common::print_json(&entries);
+    }
+
+    if entries.is_empty() {
+        info!("{}", t!("no_cached_package"));
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout();
+    for entry in &entries {
+        let partial = if entry.is_partial() {
+            format!(" ({})", t!("partial"))
+        } else {
+            String::new()
+        };
+        writeln!(
+            &mut stdout,
+            "{}{partial}\t{}\t{}",
+            entry.filename,
+            human_size(entry.size),
+            entry.url
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CacheSize {
+    /// The total bytes taken by the cache.
+    size: u64,
+    packages: usize,
+}
+
+fn show_size(cache: &DownloadCache) -> Result<()> {
+    let size = CacheSize {
+        size: cache.size()?,
+        packages: cache.entries()?.len(),
+    };
+    if OutputFormat::is_json() {
+        return common::print_json(&size);
+    }
+
+    writeln!(
+        std::io::stdout(),
+        "{}",
+        t!(
+            "cache_size",
+            size = human_size(size.size),
+            count = size.packages
+        )
+    )?;
+    Ok(())
+}
@@ -8,12 +8,18 @@ use std::{
     collections::HashMap,
     fmt::Display,
     io::{self, BufRead, Write},
+    path::Path,
 };
 
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
+use serde::Serialize;
 
+use super::OutputFormat;
 use crate::components::Component;
+use crate::core::error::ErrorKind;
+use crate::core::journal::{Journal, Operation};
+use crate::core::plan::{human_size, ChangeKind, Plan};
 
 /// A "convenient" helper macro to [`question_single_choice`].
 ///
@@ -81,6 +87,8 @@ pub(crate) struct VersionDiff<'c> {
     /// `true` a tool wasn't supported or installed previously, but have a new version
     /// available, which means that tool is newly supported.
     pub(crate) is_newly_supported: bool,
+    /// `true` if the component was pinned by the user, therefore it won't be updated.
+    pub(crate) is_pinned: bool,
 }
 
 pub(crate) fn question_str<Q: Display, A: Display>(
@@ -287,6 +295,228 @@ pub(crate) fn confirm<Q: Display>(question: Q, default: bool) -> Result<bool> {
     Ok(choice)
 }
 
+/// Check if the last install, update or uninstall operation in `root` was interrupted,
+/// and ask the user whether to resume it or to roll it back.
+///
+/// Return `false` if the user chose to cancel.
+pub(crate) fn handle_unfinished_operation(root: &Path) -> Result<bool> {
+    let Some(journal) = Journal::load_unfinished(root)? else {
+        return Ok(true);
+    };
+    let op = journal.operation;
+    let proceed = handle_user_choice!(
+        t!("unfinished_operation_found", op = op), 1,
+        {
+            1 t!("resume") => { true },
+            2 t!("rollback") => {
+                journal.rollback()?;
+                true
+            },
+            3 t!("cancel") => { false }
+        }
+    );
+    Ok(proceed)
+}
+
+/// Handle the failure of an operation in `root`, by asking the user whether to roll back
+/// the changes that have been made, or letting them know that it can be resumed.
+pub(crate) fn handle_failed_operation(root: &Path, error: &anyhow::Error) -> Result<()> {
+    let Some(journal) = Journal::load_unfinished(root)? else {
+        return Ok(());
+    };
+    let op = journal.operation;
+    // rolling back an update or uninstallation means installing the previous components again,
+    // which could take a while, so it's not the default
+    let default = op == Operation::Install;
+    if confirm(t!("rollback_confirmation", op = op, error = error), default)? {
+        journal.rollback()
+    } else {
+        info!("{}", t!("resume_hint", op = op));
+        Ok(())
+    }
+}
+
+/// Print a value as pretty JSON document on `stdout`.
+pub(crate) fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let mut stdout = io::stdout();
+    writeln!(&mut stdout, "{}", serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+/// Print the plan computed by `--dry-run`, as JSON document if `--format json` was given.
+pub(crate) fn print_plan(plan: &Plan) -> Result<()> {
+    if OutputFormat::is_json() {
+        return print_json(plan);
+    }
+
+    let mut stdout = io::stdout();
+    writeln!(
+        &mut stdout,
+        "{}",
+        t!(
+            "dry_run_plan",
+            action = plan.action,
+            dir = plan.install_dir.display()
+        )
+    )?;
+    if plan.is_empty() {
+        writeln!(&mut stdout, "{}", t!("dry_run_no_change"))?;
+        return Ok(());
+    }
+
+    let version_or_unknown = |v: &Option<String>| v.clone().unwrap_or_else(|| "?".to_string());
+    let sections = [
+        (
+            t!("dry_run_install"),
+            plan.changes_of(ChangeKind::Install)
+                .map(|c| match &c.to {
+                    Some(to) => format!("{} ({to})", c.name),
+                    None => c.name.clone(),
+                })
+                .collect::<Vec<_>>(),
+        ),
+        (
+            t!("dry_run_update"),
+            plan.changes_of(ChangeKind::Update)
+                .map(|c| {
+                    let (from, to) = (version_or_unknown(&c.from), version_or_unknown(&c.to));
+                    format!("{} ({from} -> {to})", c.name)
+                })
+                .collect(),
+        ),
+        (
+            t!("dry_run_remove"),
+            plan.changes_of(ChangeKind::Remove)
+                .map(|c| match &c.from {
+                    Some(from) => format!("{} ({from})", c.name),
+                    None => c.name.clone(),
+                })
+                .collect(),
+        ),
+        (t!("dry_run_downloads"), plan.downloads.clone()),
+        (t!("dry_run_commands"), plan.commands.clone()),
+        (
+            t!("dry_run_set_env_vars"),
+            plan.env_vars
+                .iter()
+                .filter_map(|(key, val)| val.as_ref().map(|val| format!("{key}={val}")))
+                .collect(),
+        ),
+        (
+            t!("dry_run_remove_env_vars"),
+            plan.env_vars
+                .iter()
+                .filter(|(_, val)| val.is_none())
+                .map(|(key, _)| key.clone())
+                .collect(),
+        ),
+        (
+            t!("dry_run_path_added"),
+            plan.path_added
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+        ),
+        (
+            t!("dry_run_path_removed"),
+            plan.path_removed
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+        ),
+        (
+            t!("dry_run_rc_files"),
+            plan.rc_files
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect(),
+        ),
+    ];
+    for (title, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+        writeln!(&mut stdout, "\n{title}:")?;
+        for item in items {
+            writeln!(&mut stdout, "    {item}")?;
+        }
+    }
+
+    if let Some(space) = &plan.disk_space {
+        let size = human_size(space.bytes);
+        writeln!(&mut stdout, "\n{}", t!("dry_run_disk_space", size = size))?;
+        if !space.unknown.is_empty() {
+            let list = space.unknown.join(", ");
+            writeln!(
+                &mut stdout,
+                "    {}",
+                t!("dry_run_disk_space_unknown", list = list)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// An error printed as JSON document, so that scripts can tell whether a query failed.
+#[derive(Serialize)]
+struct ErrorOutput {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize)]
+struct ErrorDetail {
+    /// The category of the error, check [`ErrorKind`] for possible values.
+    id: ErrorKind,
+    /// The exit code of this program, which is decided by the category of the error.
+    code: u8,
+    message: String,
+    /// The chain of underlying errors that caused this one.
+    causes: Vec<String>,
+}
+
+/// An error of a command that already printed its result as JSON document,
+/// which only needs to be reflected in the exit status.
+#[derive(Debug)]
+struct ReportedError(String);
+
+impl Display for ReportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReportedError {}
+
+/// The error returned by a command that found problems and already printed them,
+/// so that [`report_error`] does not print another JSON document after them.
+pub(crate) fn reported<M: Display>(msg: M) -> anyhow::Error {
+    anyhow::Error::new(ReportedError(msg.to_string()))
+}
+
+/// The error returned when the user chose not to continue an operation.
+pub(crate) fn cancelled() -> anyhow::Error {
+    ErrorKind::Cancelled.error(t!("operation_cancelled"))
+}
+
+/// Print the error (if any) in `result` on `stdout` when the output format is JSON,
+/// then pass the result through.
+///
+/// Errors created by [`reported`] are not printed, as their output was already printed.
+pub(crate) fn report_error<T>(result: Result<T>) -> Result<T> {
+    if let Err(e) = &result {
+        if OutputFormat::is_json() && !e.is::<ReportedError>() {
+            let kind = ErrorKind::of(e);
+            let output = ErrorOutput {
+                error: ErrorDetail {
+                    id: kind,
+                    code: kind.exit_code(),
+                    message: e.to_string(),
+                    causes: e.chain().skip(1).map(ToString::to_string).collect(),
+                },
+            };
+            print_json(&output)?;
+        }
+    }
+    result
+}
+
 pub(crate) enum Confirm {
     Yes,
     No,
@@ -383,8 +613,13 @@ impl ComponentDecoration<'_> {
             Self::VersionDiff(diff_map) => diff_map
                 .get(comp.name.as_str())
                 .map(|diff| {
+                    let pinned = if diff.is_pinned {
+                        format!(" [{}]", t!("pinned"))
+                    } else {
+                        String::new()
+                    };
                     format!(
-                        " ({} -> {})",
+                        " ({} -> {}){pinned}",
                         diff.from.unwrap_or("N/A"),
                         diff.to.unwrap_or("N/A")
                     )
@@ -0,0 +1,156 @@
+//! Shell completions of this program.
+//!
+//! The completions are dynamic, meaning that the shell asks this program for candidates
+//! by running it with [`COMPLETE_VAR`] set, therefore the names of components and toolkits
+//! are always completed from the current installation.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::{CommandFactory, ValueEnum};
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::{Bash, CompleteEnv, EnvCompleter, Fish, Powershell, Zsh};
+
+use super::{Installer, Manager, ManagerSubcommands};
+use crate::components::{self, Component};
+use crate::fingerprint::InstallationRecord;
+use crate::installations;
+use crate::toolset_manifest::baked_in_manifest;
+
+/// The env var that tells this program to print completion candidates instead of running.
+const COMPLETE_VAR: &str = "RIM_COMPLETE";
+
+/// Shells that completion scripts can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(name = "powershell")]
+    PowerShell,
+}
+
+impl CompletionShell {
+    fn completer(self) -> &'static dyn EnvCompleter {
+        match self {
+            Self::Bash => &Bash,
+            Self::Zsh => &Zsh,
+            Self::Fish => &Fish,
+            Self::PowerShell => &Powershell,
+        }
+    }
+
+    /// Return a script that registers the completions of `bin` to this shell,
+    /// the candidates are provided by running the executable at `completer`.
+    pub(crate) fn script(self, bin: &str, completer: &Path) -> Result<String> {
+        let completer = completer.to_str().with_context(|| {
+            format!(
+                "the path of '{}' contains invalid unicodes",
+                completer.display()
+            )
+        })?;
+        let mut buf = vec![];
+        self.completer()
+            .write_registration(COMPLETE_VAR, bin, bin, completer, &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    /// Similar to [`script`](CompletionShell::script), but the result is meant to be saved
+    /// as a file that is loaded by the shell automatically, instead of being sourced.
+    pub(crate) fn script_file(self, bin: &str, completer: &Path) -> Result<String> {
+        let mut script = self.script(bin, completer)?;
+        if self == Self::Zsh {
+            // zsh runs the file as the completion function on first use, which only defines
+            // the real completion function, so it needs to be called once here as well.
+            script.push_str(&format!(
+                "\n_clap_dynamic_completer_{} \"$@\"\n",
+                bin.replace('-', "_")
+            ));
+        }
+        Ok(script)
+    }
+}
+
+/// Print the candidates and exit if this program was run by a completion script,
+/// otherwise do nothing.
+pub(crate) fn complete_from_env(is_manager: bool) {
+    if is_manager {
+        CompleteEnv::with_factory(Manager::command)
+            .var(COMPLETE_VAR)
+            .complete();
+    } else {
+        CompleteEnv::with_factory(Installer::command)
+            .var(COMPLETE_VAR)
+            .complete();
+    }
+}
+
+/// Execute `completions` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Completions { shell } = subcommand else {
+        return Ok(false);
+    };
+
+    let exe = std::env::current_exe()?;
+    let bin = exe
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("unable to determine the name of current executable")?;
+    let script = shell.script(bin, &exe)?;
+    writeln!(std::io::stdout(), "{script}")?;
+    Ok(true)
+}
+
+fn to_candidates<'a, I: IntoIterator<Item = &'a Component>>(comps: I) -> Vec<CompletionCandidate> {
+    comps
+        .into_iter()
+        .map(|c| CompletionCandidate::new(&c.name).help(Some(c.desc.clone().into())))
+        .collect()
+}
+
+/// Load the record of the installation to complete from, if there is one.
+///
+/// Note that `--root` is not respected, because the arguments are not parsed yet.
+fn installation_record() -> Option<InstallationRecord> {
+    installations::select_installed_dir(None).ok()?;
+    if !InstallationRecord::exists().ok()? {
+        return None;
+    }
+    InstallationRecord::load_from_install_dir().ok()
+}
+
+fn installed_components() -> Vec<Component> {
+    installation_record()
+        .and_then(|record| components::all_components_from_installation(&record).ok())
+        .unwrap_or_default()
+}
+
+/// Candidates of the components that can be installed by the installer.
+pub(super) fn installer_components() -> Vec<CompletionCandidate> {
+    baked_in_manifest()
+        .and_then(|manifest| manifest.current_target_components(false))
+        .map(|comps| to_candidates(&comps))
+        .unwrap_or_default()
+}
+
+/// Candidates of every component offered by current installation.
+pub(super) fn available_components() -> Vec<CompletionCandidate> {
+    to_candidates(&installed_components())
+}
+
+/// Candidates of the components that have been installed.
+pub(super) fn installed_only_components() -> Vec<CompletionCandidate> {
+    to_candidates(installed_components().iter().filter(|c| c.installed))
+}
+
+/// Candidates of the toolkit of current installation, in `NAME@VERSION` format.
+pub(super) fn toolkits() -> Vec<CompletionCandidate> {
+    let Some(record) = installation_record() else {
+        return vec![];
+    };
+    match (record.name, record.version) {
+        (Some(name), Some(version)) => vec![CompletionCandidate::new(format!("{name}@{version}"))],
+        _ => vec![],
+    }
+}
@@ -1,7 +1,15 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Subcommand;
+use clap_complete::engine::ArgValueCandidates;
 
-use super::ManagerSubcommands;
+use super::{common, completions, ManagerSubcommands};
+use crate::components::{self, Component, ComponentType};
+use crate::core::history::Action;
+use crate::fingerprint::InstallationRecord;
+use crate::{
+    get_installed_dir, toolset_manifest::ToolsetManifest, InstallConfiguration,
+    UninstallConfiguration,
+};
 
 #[derive(Subcommand, Debug)]
 pub(super) enum ComponentCommand {
@@ -11,28 +19,146 @@ pub(super) enum ComponentCommand {
         /// Allow insecure connections when download packages from server.
         #[arg(short = 'k', long)]
         insecure: bool,
+        /// Show what would be installed without changing anything
+        #[arg(long)]
+        dry_run: bool,
         /// The list of components to install
-        #[arg(value_name = "COMPONENTS")]
+        #[arg(
+            value_name = "COMPONENTS",
+            required = true,
+            add = ArgValueCandidates::new(completions::available_components)
+        )]
         components: Vec<String>,
     },
     /// Uninstall a set of components, check `list component --installed` for available options
     #[command(alias = "remove")]
     Uninstall {
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
         /// The list of components to uninstall
-        #[arg(value_name = "COMPONENTS")]
+        #[arg(
+            value_name = "COMPONENTS",
+            required = true,
+            add = ArgValueCandidates::new(completions::installed_only_components)
+        )]
         components: Vec<String>,
     },
 }
 
 impl ComponentCommand {
     fn execute(&self) -> Result<()> {
+        let is_dry_run = matches!(
+            self,
+            Self::Install { dry_run: true, .. } | Self::Uninstall { dry_run: true, .. }
+        );
+        if !is_dry_run && !common::handle_unfinished_operation(get_installed_dir())? {
+            return Ok(());
+        }
+
+        let record = InstallationRecord::load_from_install_dir()?;
+        let all_components = components::all_components_from_installation(&record)?;
+
         match self {
-            Self::Install { components, .. } => todo!("install components: {components:?}"),
-            Self::Uninstall { components } => todo!("uninstall components: {components:?}"),
+            Self::Install {
+                components,
+                insecure,
+                dry_run,
+            } => {
+                let to_install = resolve_components(&all_components, components, false)?;
+                if to_install.is_empty() {
+                    info!("{}", t!("no_component_to_install"));
+                    return Ok(());
+                }
+                if *dry_run {
+                    let manifest = ToolsetManifest::load_from_install_dir()?;
+                    let plan = InstallConfiguration::for_plan(get_installed_dir(), &manifest)?
+                        .plan(Action::ComponentAdd, &to_install)?;
+                    return common::print_plan(&plan);
+                }
+                let list = component_list(&to_install);
+                if !common::confirm(t!("component_install_confirmation", list = list), true)? {
+                    return Err(common::cancelled());
+                }
+
+                let manifest = ToolsetManifest::load_from_install_dir()?;
+                InstallConfiguration::new(get_installed_dir(), &manifest)?
+                    .insecure(*insecure)
+                    .add_components(to_install)?;
+            }
+            Self::Uninstall {
+                components,
+                dry_run,
+            } => {
+                let to_remove = resolve_components(&all_components, components, true)?;
+                if to_remove.is_empty() {
+                    info!("{}", t!("no_component_to_uninstall"));
+                    return Ok(());
+                }
+                if *dry_run {
+                    let plan = UninstallConfiguration::init(None)?.plan(Some(&to_remove), false);
+                    return common::print_plan(&plan);
+                }
+                let list = component_list(&to_remove);
+                if !common::confirm(t!("uninstall_confirmation", list = list), false)? {
+                    return Err(common::cancelled());
+                }
+
+                UninstallConfiguration::init(None)?.remove_components(&to_remove)?;
+            }
         }
+        info!("{}", t!("component_operation_finished"));
+        Ok(())
     }
 }
 
+/// Find the components with the given `names` from the manifest of current installation.
+///
+/// When `installed` is `true`, the components are about to be uninstalled, therefore
+/// components that are not installed will be skipped, otherwise the installed ones will be skipped.
+///
+/// # Error
+/// Return error if any of the names is unknown, or when trying to uninstall a required component.
+fn resolve_components(
+    all_components: &[Component],
+    names: &[String],
+    installed: bool,
+) -> Result<Vec<Component>> {
+    let mut res: Vec<Component> = vec![];
+    for name in names {
+        let Some(comp) = all_components.iter().find(|c| &c.name == name) else {
+            bail!("{}", t!("component_not_found", name = name));
+        };
+        if installed && (comp.required || comp.kind == ComponentType::ToolchainProfile) {
+            bail!("{}", t!("component_required", name = name));
+        }
+        if comp.installed != installed || comp.kind == ComponentType::ToolchainProfile {
+            let reason = if installed {
+                t!("component_not_installed_skipped", name = name)
+            } else {
+                t!("component_already_installed", name = name)
+            };
+            info!("{reason}");
+            continue;
+        }
+        if !res.contains(comp) {
+            res.push(comp.clone());
+        }
+    }
+    Ok(res)
+}
+
+fn component_list(components: &[Component]) -> String {
+    components
+        .iter()
+        .map(|c| match &c.version {
+            Some(ver) => format!("{} ({ver})", c.name),
+            None => c.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
     let ManagerSubcommands::Component { command } = cmd else {
         return Ok(false);
@@ -0,0 +1,92 @@
+//! Separated module to diagnose the environment of an installation in command line.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::core::doctor::{self, Finding, Severity};
+use crate::get_installed_dir;
+
+/// Execute `doctor` command.
+///
+/// Returns an error if any error level problem remains, therefore the program exits with
+/// non-zero status, which can be used by scripts to check the environment.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Doctor { fix } = subcommand else {
+        return Ok(false);
+    };
+
+    let install_dir = get_installed_dir();
+    let mut findings = doctor::diagnose(install_dir)?;
+    let is_json = OutputFormat::is_json();
+
+    if !is_json {
+        print_findings(&findings)?;
+    }
+    if *fix {
+        apply_fixes(&mut findings)?;
+    }
+    if is_json {
+        common::print_json(&findings)?;
+    }
+
+    let error_count = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error && !f.fixed)
+        .count();
+    if error_count > 0 {
+        return Err(common::reported(t!("doctor_failed", count = error_count)));
+    }
+    Ok(true)
+}
+
+fn apply_fixes(findings: &mut [Finding]) -> Result<()> {
+    let mut fixes = vec![];
+    for fix in findings.iter().filter_map(|f| f.fix) {
+        if !fixes.contains(&fix) {
+            fixes.push(fix);
+        }
+    }
+    if fixes.is_empty() {
+        return Ok(());
+    }
+
+    let list = fixes
+        .iter()
+        .map(|fix| format!("    {fix}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !common::confirm(t!("doctor_fix_confirmation", list = list), true)? {
+        return Err(common::cancelled());
+    }
+    let install_dir = get_installed_dir();
+    for fix in fixes {
+        doctor::apply_fix(install_dir, fix)?;
+        findings
+            .iter_mut()
+            .filter(|f| f.fix == Some(fix))
+            .for_each(|f| f.fixed = true);
+    }
+    info!("{}", t!("doctor_fixed"));
+    Ok(())
+}
+
+fn print_findings(findings: &[Finding]) -> Result<()> {
+    if findings.is_empty() {
+        info!("{}", t!("doctor_no_problem"));
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout();
+    for finding in findings {
+        writeln!(&mut stdout, "{}: {}", finding.severity, finding.message)?;
+        if let Some(suggestion) = &finding.suggestion {
+            writeln!(&mut stdout, "    {}: {suggestion}", t!("suggestion"))?;
+        }
+        if finding.fix.is_some() {
+            writeln!(&mut stdout, "    {}", t!("doctor_fixable"))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Separated module to print the environment of an installation in command line.
+
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use super::ManagerSubcommands;
+use crate::core::os;
+use crate::fingerprint::InstallationRecord;
+use crate::toolset_manifest::ToolsetManifest;
+use crate::{get_installed_dir, InstallConfiguration};
+
+/// Shells that the environment can be printed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum EnvShell {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+    Pwsh,
+}
+
+impl EnvShell {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Nu => "nu",
+            Self::Pwsh => "pwsh",
+        }
+    }
+}
+
+/// Execute `env` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Env { shell } = subcommand else {
+        return Ok(false);
+    };
+
+    let install_dir = get_installed_dir();
+    let settings = InstallationRecord::load_from_install_dir()?.settings;
+    let mut manifest = ToolsetManifest::load_from_install_dir()?;
+    // the proxy might be overridden during installation
+    if let Some(settings) = &settings {
+        manifest.proxy.clone_from(&settings.proxy);
+    }
+    let mut config = InstallConfiguration::new(install_dir, &manifest)?;
+    if let Some(settings) = settings {
+        config = config
+            .with_rustup_dist_server(settings.rustup_dist_server)
+            .with_rustup_update_root(settings.rustup_update_root);
+    }
+
+    let script = os::env_script(shell.name(), &config.env_vars()?, &config.path_entries())?;
+    writeln!(std::io::stdout(), "{script}")?;
+    Ok(true)
+}
@@ -0,0 +1,50 @@
+//! Separated module to show the installation history in command line.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::{get_installed_dir, history};
+
+/// Execute `history` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::History = subcommand else {
+        return Ok(false);
+    };
+
+    let entries = history::load_history(get_installed_dir())?;
+    if OutputFormat::is_json() {
+        common::print_json(&entries)?;
+        return Ok(true);
+    }
+
+    if entries.is_empty() {
+        info!("{}", t!("no_history"));
+        return Ok(true);
+    }
+    let mut stdout = std::io::stdout();
+    for entry in &entries {
+        let none = "-".to_string();
+        write!(
+            &mut stdout,
+            "{}  {:<16} {} -> {}  {}",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            entry.action,
+            entry.from.as_ref().unwrap_or(&none),
+            entry.to.as_ref().unwrap_or(&none),
+            entry.outcome,
+        )?;
+        if !entry.components.is_empty() {
+            write!(&mut stdout, "  [{}]", entry.components.join(", "))?;
+        }
+        if let Some(url) = &entry.manifest_url {
+            write!(&mut stdout, "  {url}")?;
+        }
+        writeln!(&mut stdout)?;
+        if let Some(err) = &entry.error {
+            writeln!(&mut stdout, "    {err}")?;
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,339 @@
+//! Separated module to show the details of a component or toolkit in command line.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use url::Url;
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::components::{Component, ComponentType};
+use crate::core::tools::ToolKind;
+use crate::fingerprint::InstallationRecord;
+use crate::toolkit::{toolkits_from_server, Toolkit};
+use crate::toolset_manifest::{get_toolset_manifest, ToolInfo, ToolsetManifest};
+use crate::utils::blocking;
+
+/// Execute `info` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Info {
+        component,
+        toolkit,
+        remote,
+        insecure,
+    } = subcommand
+    else {
+        return Ok(false);
+    };
+
+    if let Some(spec) = toolkit {
+        let info = blocking!(toolkit_info(spec, *insecure))?;
+        if OutputFormat::is_json() {
+            common::print_json(&info)?;
+        } else {
+            print_toolkit_info(&info)?;
+        }
+    } else if let Some(name) = component {
+        let info = blocking!(component_info(name, *remote, *insecure))?;
+        if OutputFormat::is_json() {
+            common::print_json(&info)?;
+        } else {
+            print_component_info(&info)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Where a component is installed from.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentSource {
+    /// The kind of source, such as `git`, `url`, `path`, `registry` or `toolchain`.
+    kind: &'static str,
+    /// The url or path of the source, not available for components from registry or toolchain.
+    location: Option<String>,
+}
+
+impl From<&ToolInfo> for ComponentSource {
+    fn from(value: &ToolInfo) -> Self {
+        let (kind, location) = match value {
+            ToolInfo::PlainVersion(_) | ToolInfo::DetailedVersion { .. } => ("registry", None),
+            ToolInfo::Git {
+                git,
+                branch,
+                tag,
+                rev,
+                ..
+            } => {
+                let reference = rev.as_ref().or(tag.as_ref()).or(branch.as_ref());
+                let location = match reference {
+                    Some(r) => format!("{git}#{r}"),
+                    None => git.to_string(),
+                };
+                ("git", Some(location))
+            }
+            ToolInfo::Path { path, .. } => ("path", Some(path.display().to_string())),
+            ToolInfo::Url { url, .. } => ("url", Some(url.to_string())),
+        };
+        Self { kind, location }
+    }
+}
+
+/// Details of a single component, combined from the installed manifest,
+/// the installation record and optionally the latest manifest from server.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentInfo {
+    name: String,
+    desc: String,
+    group: Option<String>,
+    kind: ComponentType,
+    required: bool,
+    optional: bool,
+    source: ComponentSource,
+    installed: bool,
+    installed_version: Option<String>,
+    /// The version provided by the manifest of current installation.
+    offered_version: Option<String>,
+    /// The version provided by the latest toolkit on server, only available with `--remote`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_version: Option<String>,
+    /// How the tool was installed, not available for toolchain components.
+    tool_kind: Option<ToolKind>,
+    paths: Vec<PathBuf>,
+}
+
+async fn component_info(name: &str, remote: bool, insecure: bool) -> Result<ComponentInfo> {
+    let record = InstallationRecord::load_from_install_dir()?;
+    let manifest = ToolsetManifest::load_from_install_dir()?;
+    let local = find_component(&manifest, name)?;
+    let latest = if remote {
+        match latest_manifest(&record, insecure).await? {
+            Some(mf) => find_component(&mf, name)?,
+            None => None,
+        }
+    } else {
+        None
+    };
+    let Some(comp) = local.as_ref().or(latest.as_ref()) else {
+        bail!("{}", t!("component_not_found", name = name));
+    };
+
+    let (installed, installed_version, tool_kind, paths) = if comp.kind.is_from_toolchain() {
+        match record.installed_toolchain() {
+            Some((version, comps)) => {
+                let installed =
+                    comp.kind == ComponentType::ToolchainProfile || comps.iter().any(|c| c == name);
+                let version = installed.then(|| version.to_string());
+                (installed, version, None, vec![])
+            }
+            None => (false, None, None, vec![]),
+        }
+    } else if let Some(tool) = record.tools.get(name) {
+        let version = record.get_tool_version(name).map(ToOwned::to_owned);
+        (true, version, Some(tool.tool_kind()), tool.paths.clone())
+    } else {
+        (false, None, None, vec![])
+    };
+
+    let source = match &comp.tool_installer {
+        Some(tool_info) => ComponentSource::from(tool_info),
+        None => ComponentSource {
+            kind: "toolchain",
+            location: None,
+        },
+    };
+
+    Ok(ComponentInfo {
+        name: comp.name.clone(),
+        desc: comp.desc.clone(),
+        group: comp.group_name.clone(),
+        kind: comp.kind,
+        required: comp.required,
+        optional: comp.optional,
+        source,
+        installed,
+        installed_version,
+        offered_version: local.as_ref().and_then(|c| c.version.clone()),
+        latest_version: latest.as_ref().and_then(|c| c.version.clone()),
+        tool_kind,
+        paths,
+    })
+}
+
+fn find_component(manifest: &ToolsetManifest, name: &str) -> Result<Option<Component>> {
+    Ok(manifest
+        .current_target_components(false)?
+        .into_iter()
+        .find(|c| c.name == name))
+}
+
+/// Load the manifest of the newest toolkit on server that has the same name as the installed one.
+async fn latest_manifest(
+    record: &InstallationRecord,
+    insecure: bool,
+) -> Result<Option<ToolsetManifest>> {
+    let Some(name) = &record.name else {
+        return Ok(None);
+    };
+    // the list from server is sorted with the newest toolkit on top
+    let Some(url) = toolkits_from_server(insecure)
+        .await?
+        .into_iter()
+        .find(|tk| &tk.name == name)
+        .and_then(|tk| tk.manifest_url)
+    else {
+        return Ok(None);
+    };
+    let manifest = get_toolset_manifest(Some(Url::parse(&url)?), insecure).await?;
+    Ok(Some(manifest))
+}
+
+/// Details of a toolkit.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolkitInfo {
+    #[serde(flatten)]
+    toolkit: Toolkit,
+    installed: bool,
+}
+
+/// Find the toolkit matching `spec`, which is a name with an optional version,
+/// in `name@version` format, without a version, the newest one will be used.
+///
+/// The installed toolkit is preferred, otherwise it will be looked up from server,
+/// and its components will be loaded from its manifest.
+async fn toolkit_info(spec: &str, insecure: bool) -> Result<ToolkitInfo> {
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (spec, None),
+    };
+    let matches = |tk: &Toolkit| tk.name == name && version.map_or(true, |v| tk.version == v);
+
+    if let Some(mutex) = Toolkit::installed(false).await? {
+        let installed = mutex.lock().await;
+        if matches(&installed) {
+            return Ok(ToolkitInfo {
+                toolkit: installed.clone(),
+                installed: true,
+            });
+        }
+    }
+
+    let Some(mut toolkit) = toolkits_from_server(insecure)
+        .await?
+        .into_iter()
+        .find(|tk| matches(tk))
+    else {
+        bail!("{}", t!("toolkit_not_found", name = spec));
+    };
+    if let Some(url) = &toolkit.manifest_url {
+        let manifest = get_toolset_manifest(Some(Url::parse(url)?), insecure).await?;
+        toolkit.components = manifest.current_target_components(false)?;
+    }
+    Ok(ToolkitInfo {
+        toolkit,
+        installed: false,
+    })
+}
+
+/// Print a list of `label: value` lines, with the values aligned.
+fn print_fields(fields: &[(String, String)]) -> Result<()> {
+    let width = fields
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or_default();
+    let mut stdout = std::io::stdout();
+    for (label, value) in fields {
+        let pad = width - label.chars().count();
+        writeln!(&mut stdout, "{label}:{:pad$} {value}", "")?;
+    }
+    Ok(())
+}
+
+fn yes_or_no(value: bool) -> String {
+    if value { t!("yes") } else { t!("no") }.to_string()
+}
+
+fn or_none(value: Option<&str>) -> String {
+    value.unwrap_or("-").to_string()
+}
+
+fn print_component_info(info: &ComponentInfo) -> Result<()> {
+    let source = match &info.source.location {
+        Some(loc) => format!("{} ({loc})", info.source.kind),
+        None => info.source.kind.to_string(),
+    };
+    let mut fields = vec![
+        (t!("info_name").to_string(), info.name.clone()),
+        (t!("info_description").to_string(), info.desc.clone()),
+        (t!("info_group").to_string(), or_none(info.group.as_deref())),
+        (t!("info_kind").to_string(), format!("{:?}", info.kind)),
+        (t!("info_source").to_string(), source),
+        (t!("info_required").to_string(), yes_or_no(info.required)),
+        (t!("info_optional").to_string(), yes_or_no(info.optional)),
+        (t!("info_installed").to_string(), yes_or_no(info.installed)),
+        (
+            t!("info_installed_version").to_string(),
+            or_none(info.installed_version.as_deref()),
+        ),
+        (
+            t!("info_offered_version").to_string(),
+            or_none(info.offered_version.as_deref()),
+        ),
+    ];
+    if let Some(latest) = &info.latest_version {
+        fields.push((t!("info_latest_version").to_string(), latest.clone()));
+    }
+    if let Some(kind) = &info.tool_kind {
+        fields.push((t!("info_tool_kind").to_string(), format!("{kind:?}")));
+    }
+    print_fields(&fields)?;
+
+    if !info.paths.is_empty() {
+        let mut stdout = std::io::stdout();
+        writeln!(&mut stdout, "{}:", t!("info_installed_paths"))?;
+        for path in &info.paths {
+            writeln!(&mut stdout, "    {}", path.display())?;
+        }
+    }
+    Ok(())
+}
+
+fn print_toolkit_info(info: &ToolkitInfo) -> Result<()> {
+    let tk = &info.toolkit;
+    let fields = vec![
+        (t!("info_name").to_string(), tk.name.clone()),
+        (t!("info_version").to_string(), tk.version.clone()),
+        (
+            t!("info_description").to_string(),
+            or_none(tk.desc.as_deref()),
+        ),
+        (t!("info_notes").to_string(), or_none(tk.info.as_deref())),
+        (
+            t!("info_manifest").to_string(),
+            or_none(tk.manifest_url.as_deref()),
+        ),
+        (t!("info_installed").to_string(), yes_or_no(info.installed)),
+    ];
+    print_fields(&fields)?;
+
+    let mut stdout = std::io::stdout();
+    writeln!(&mut stdout, "{}:", t!("info_components"))?;
+    for comp in &tk.components {
+        let version = comp
+            .version
+            .as_ref()
+            .map(|v| format!(" {v}"))
+            .unwrap_or_default();
+        let installed = if comp.installed {
+            format!(" ({})", t!("installed"))
+        } else {
+            String::new()
+        };
+        writeln!(&mut stdout, "    {}{version}{installed}", comp.name)?;
+    }
+    Ok(())
+}
@@ -7,21 +7,31 @@ use std::path::{Path, PathBuf};
 use crate::cli::common::{self, Confirm};
 use crate::cli::GlobalOpts;
 use crate::components::Component;
+use crate::configuration::Configuration;
+use crate::core::history::Action;
 use crate::core::install::{
     default_rustup_dist_server, default_rustup_update_root, InstallConfiguration,
     DEFAULT_CARGO_REGISTRY,
 };
+use crate::core::parser::TomlParser;
+use crate::core::plan::ChangeKind;
+use crate::core::toolkit::{find_toolkit, version_changes, VersionChange};
 use crate::core::try_it;
+use crate::fingerprint::{CargoRegistry, InstallationRecord};
+use crate::lock::LockFile;
 use crate::toolset_manifest::get_toolset_manifest;
 use crate::utils::blocking;
-use crate::{default_install_dir, utils};
+use crate::{default_install_dir, get_installed_dir, utils};
 
+use super::answers::AnswerFile;
 use super::common::{
     question_single_choice, ComponentChoices, ComponentDecoration, ComponentListBuilder,
 };
-use super::{Installer, ManagerSubcommands};
+use super::completions::CompletionShell;
+use super::{tui, Installer, ManagerSubcommands};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use url::Url;
 
 /// Perform installer actions.
 ///
@@ -37,15 +47,26 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
         insecure,
         list_components,
         component,
+        lock,
+        config,
+        save_answers,
+        proxy,
+        dry_run,
         ..
     } = installer;
 
     if matches!(&prefix, Some(p) if utils::is_root_dir(p)) {
         bail!(t!("notify_root_dir"));
     }
+    if let Some(lock) = lock {
+        return install_from_lock(lock, prefix.as_deref(), *insecure, *dry_run);
+    }
 
     let manifest_url = manifest_src.as_ref().map(|s| s.to_url()).transpose()?;
-    let mut manifest = blocking!(get_toolset_manifest(manifest_url, *insecure))?;
+    let mut manifest = blocking!(get_toolset_manifest(manifest_url.clone(), *insecure))?;
+    if proxy.is_some() {
+        manifest.proxy.clone_from(proxy);
+    }
 
     if *list_components {
         // print a list of available components then return, don't do anything else
@@ -55,6 +76,9 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
     manifest.adjust_paths()?;
 
     let component_list = manifest.current_target_components(true)?;
+    if let (Some(_), Some(names)) = (config, component) {
+        AnswerFile::check_components(names, &component_list)?;
+    }
     let abs_prefix = if let Some(path) = prefix {
         utils::to_nomalized_abspath(path, None)?
     } else {
@@ -69,7 +93,36 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
         .unwrap_or(DEFAULT_CARGO_REGISTRY);
     let install_dir = user_opt.prefix;
 
-    InstallConfiguration::new(&install_dir, &manifest)?
+    if let Some(path) = save_answers {
+        let answers = AnswerFile {
+            lang: installer.lang.clone(),
+            prefix: Some(install_dir.clone()),
+            manifest: manifest_src.as_ref().map(ToString::to_string),
+            components: Some(user_opt.components.iter().map(|c| c.name.clone()).collect()),
+            registry: registry_url.as_ref().map(|url| CargoRegistry {
+                name: registry_name.to_string(),
+                url: url.clone(),
+            }),
+            rustup_dist_server: rustup_dist_server.clone(),
+            rustup_update_root: rustup_update_root.clone(),
+            proxy: proxy.clone(),
+            no_modify_path: installer.no_modify_path,
+            no_modify_env: installer.no_modify_env,
+        };
+        answers.save(path)?;
+    }
+
+    if !dry_run && !common::handle_unfinished_operation(&install_dir)? {
+        return Ok(());
+    }
+
+    let config = if *dry_run {
+        InstallConfiguration::for_plan(&install_dir, &manifest)?
+    } else {
+        InstallConfiguration::new(&install_dir, &manifest)?
+    };
+    let config = config
+        .with_manifest_url(manifest_url)
         .with_cargo_registry(registry_name, registry_value)
         .with_rustup_dist_server(
             rustup_dist_server
@@ -81,21 +134,92 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
                 .clone()
                 .unwrap_or_else(|| default_rustup_update_root().clone()),
         )
-        .insecure(*insecure)
-        .install(user_opt.components)?;
+        .insecure(*insecure);
+    if *dry_run {
+        return common::print_plan(&config.plan(Action::Install, &user_opt.components)?);
+    }
+    install_and_finish(config, &install_dir, user_opt.components, true)
+}
+
+/// Install exactly the packages pinned in a lockfile, without asking any question.
+fn install_from_lock(
+    lock_path: &Path,
+    prefix: Option<&Path>,
+    insecure: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let lock = LockFile::load(lock_path)?;
+    let manifest = lock.to_manifest()?;
+    // every package in the lockfile will be installed
+    let components = manifest.current_target_components(false)?;
+    let install_dir = if let Some(path) = prefix {
+        utils::to_nomalized_abspath(path, None)?
+    } else {
+        default_install_dir()
+    };
+
+    if !dry_run && !common::handle_unfinished_operation(&install_dir)? {
+        return Ok(());
+    }
+
+    let settings = &lock.settings;
+    let config = if dry_run {
+        InstallConfiguration::for_plan(&install_dir, &manifest)?
+    } else {
+        InstallConfiguration::new(&install_dir, &manifest)?
+    };
+    let mut config = config
+        .with_lock(Some(&lock))
+        .with_rustup_dist_server(settings.rustup_dist_server.clone())
+        .with_rustup_update_root(settings.rustup_update_root.clone())
+        .insecure(insecure);
+    if let Some(registry) = &settings.cargo_registry {
+        config = config.with_cargo_registry(&registry.name, &registry.url);
+    }
+    if dry_run {
+        return common::print_plan(&config.plan(Action::Install, &components)?);
+    }
+    install_and_finish(config, &install_dir, components, false)
+}
+
+/// Run the installation, then show some hints after it finished.
+///
+/// If `interactive` is `true`, the user will be asked whether to try the demo project.
+fn install_and_finish(
+    config: InstallConfiguration,
+    install_dir: &Path,
+    components: Vec<Component>,
+    interactive: bool,
+) -> Result<()> {
+    let result = config.install(components);
+    if let Err(e) = &result {
+        common::handle_failed_operation(install_dir, e)?;
+    }
+    result?;
 
     let g_opts = GlobalOpts::get();
     if !g_opts.quiet {
         println!("\n{}\n", t!("install_finish_info"));
     }
 
+    #[cfg(unix)]
+    if interactive
+        && !g_opts.no_modify_env()
+        && common::confirm(t!("question_install_completions"), true)?
+    {
+        // Do NOT fail installation if completions cannot be installed
+        if let Err(e) = install_completions(install_dir) {
+            warn!("{}: {e}", t!("install_completions_failed"));
+        }
+    }
+
     // NB(J-ZhengLi): the logic is flipped here because...
     // Well, the decision was allowing a `VS-Code` window to popup after installation by default.
     // However, it is not ideal when passing `--yes` when the user just want a quick install,
     // and might gets annoying when the user is doing a 'quick install' on WSL. (a VSCode
     // window will pop open on Windows)
-    if !g_opts.yes_to_all && common::confirm(t!("question_try_demo"), true)? {
-        try_it::try_it(Some(&install_dir))?;
+    if interactive && !g_opts.yes_to_all && common::confirm(t!("question_try_demo"), true)? {
+        try_it::try_it(Some(install_dir))?;
     }
 
     #[cfg(unix)]
@@ -108,6 +232,24 @@ pub(super) fn execute_installer(installer: &Installer) -> Result<()> {
     Ok(())
 }
 
+/// Write the completion scripts of the manager for every shell detected.
+#[cfg(unix)]
+fn install_completions(install_dir: &Path) -> Result<()> {
+    use clap::ValueEnum;
+
+    let bin = format!("{}-manager", t!("vendor_en"));
+    let manager_exe = install_dir.join(&bin);
+    let scripts = crate::core::os::unix::install_completions(install_dir, &bin, |shell| {
+        CompletionShell::from_str(shell, true)
+            .map_err(|e| anyhow!(e))?
+            .script_file(&bin, &manager_exe)
+    })?;
+    for path in scripts {
+        info!("{}", t!("completions_installed", path = path.display()));
+    }
+    Ok(())
+}
+
 /// Contains customized install options that will be collected from user input.
 ///
 /// Check [`collect_from_user`](CustomInstallOpt::collect_from_user) for more detail.
@@ -175,7 +317,7 @@ impl CustomInstallOpt {
                     });
                 }
                 Confirm::No => (),
-                Confirm::Abort => std::process::exit(0),
+                Confirm::Abort => return Err(common::cancelled()),
             }
         }
     }
@@ -214,11 +356,25 @@ fn custom_component_choices<'a>(
     all_components: &'a [Component],
     user_selected_comps: Option<&[String]>,
 ) -> Result<ComponentChoices<'a>> {
+    let defaults = default_component_choices(all_components, user_selected_comps);
+    if tui::is_available() {
+        let selection = tui::select_components(
+            &t!("tui_select_components_to_install"),
+            all_components,
+            &defaults,
+            ComponentDecoration::InstalledOrRequired,
+            |c| c.required && !c.installed,
+            |_| false,
+        )?;
+        // keep the default selection if the user cancelled
+        return Ok(selection.unwrap_or(defaults));
+    }
+
     let list_of_comps = ComponentListBuilder::new(all_components)
         .show_desc(true)
         .decorate(ComponentDecoration::InstalledOrRequired)
         .build();
-    let default_ids = default_component_choices(all_components, user_selected_comps)
+    let default_ids = defaults
         .keys()
         .map(|idx| (idx + 1).to_string())
         .collect::<Vec<_>>()
@@ -292,9 +448,145 @@ fn show_confirmation(install_dir: &str, choices: &ComponentChoices<'_>) -> Resul
 }
 
 pub(super) fn execute_manager(manager: &ManagerSubcommands) -> Result<bool> {
-    let ManagerSubcommands::Install { version, .. } = manager else {
+    let ManagerSubcommands::Install {
+        insecure,
+        dry_run,
+        toolkit,
+    } = manager
+    else {
         return Ok(false);
     };
 
-    todo!("install dist with version '{version}'");
+    blocking!(install_toolkit_version(toolkit, *insecure, *dry_run))?;
+    Ok(true)
+}
+
+/// Install a specific version of toolkit given as `[NAME@]VERSION`, which defaults to
+/// the installed toolkit if `NAME` is omitted, by upgrading or downgrading the installed
+/// components to the versions in its manifest, and removing the installed tools it does
+/// not provide. Pinned components are left untouched.
+async fn install_toolkit_version(spec: &str, insecure: bool, dry_run: bool) -> Result<()> {
+    let install_dir = get_installed_dir();
+    if !dry_run && !common::handle_unfinished_operation(install_dir)? {
+        return Ok(());
+    }
+
+    let record = InstallationRecord::load_from_install_dir()?;
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, version),
+        None => (
+            record
+                .name
+                .as_deref()
+                .ok_or_else(|| anyhow!(t!("toolkit_name_required")))?,
+            spec,
+        ),
+    };
+    let target = find_toolkit(name, version, insecure).await?;
+    let current = record.version.as_deref().unwrap_or("N/A");
+    if record.name.as_deref() == Some(name) && current == target.version {
+        info!(
+            "{}",
+            t!("toolkit_version_installed", name = name, version = current)
+        );
+        return Ok(());
+    }
+
+    let manifest_url = target
+        .manifest_url
+        .as_deref()
+        .and_then(|s| Url::parse(s).ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "invalid dist manifest downloaded from server: \
+            must contains a valid `manifest_url`"
+            )
+        })?;
+    let manifest = get_toolset_manifest(Some(manifest_url.clone()), insecure).await?;
+    let new_components = manifest.current_target_components(false)?;
+    let pins = Configuration::load_from_install_dir().pins;
+    let changes = version_changes(&record, &new_components)
+        .into_iter()
+        .filter(|(comp, _, change)| {
+            if *change == VersionChange::Unchanged {
+                return false;
+            }
+            let pinned = pins.is_pinned(comp);
+            if pinned {
+                warn!("{}", t!("pinned_component_skipped", name = comp.name));
+            }
+            !pinned
+        })
+        .collect::<Vec<_>>();
+    let components = changes
+        .iter()
+        .map(|(comp, _, _)| (*comp).clone())
+        .collect::<Vec<_>>();
+    // the same as rolling back, tools that are not in the target toolkit are removed
+    let tools_to_remove = record
+        .tools
+        .keys()
+        .filter(|name| {
+            !new_components.iter().any(|c| &c.name == *name)
+                && !pins.is_pinned(&Component::new(name, ""))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if dry_run {
+        let mut plan = InstallConfiguration::for_plan(install_dir, &manifest)?
+            .plan(Action::Update, &components)?;
+        for name in &tools_to_remove {
+            let version = record.get_tool_version(name);
+            plan.change(ChangeKind::Remove, name, version, None);
+        }
+        return common::print_plan(&plan);
+    }
+
+    if changes.is_empty() && tools_to_remove.is_empty() {
+        info!("{}", t!("toolkit_switch_no_change"));
+    } else {
+        let list = changes
+            .iter()
+            .map(|(comp, from, change)| version_change_line(comp, *from, *change))
+            .chain(
+                tools_to_remove
+                    .iter()
+                    .map(|name| format!("{name} ({})", t!("version_change_remove"))),
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+        let question = t!(
+            "toolkit_switch_confirmation",
+            name = name,
+            version = target.version,
+            current = current,
+            list = list
+        );
+        if !common::confirm(question, true)? {
+            return Err(common::cancelled());
+        }
+    }
+
+    let result = InstallConfiguration::new(install_dir, &manifest)?
+        .with_manifest_url(Some(manifest_url))
+        .insecure(insecure)
+        .switch_toolkit(components, &tools_to_remove);
+    if let Err(e) = &result {
+        common::handle_failed_operation(install_dir, e)?;
+    }
+    result
+}
+
+fn version_change_line(comp: &Component, from: Option<&str>, change: VersionChange) -> String {
+    let to = comp.version.as_deref().unwrap_or("N/A");
+    let label = match change {
+        VersionChange::Install => {
+            return format!("{} ({to}, {})", comp.name, t!("version_change_install"));
+        }
+        VersionChange::Upgrade => t!("version_change_upgrade"),
+        VersionChange::Downgrade => t!("version_change_downgrade"),
+        VersionChange::Replace | VersionChange::Unchanged => t!("version_change_replace"),
+    };
+    format!("{} ({} -> {to}, {label})", comp.name, from.unwrap_or("N/A"))
 }
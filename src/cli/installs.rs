@@ -0,0 +1,131 @@
+//! Separated module to manage the index of known installations in command line.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use clap::{Subcommand, ValueHint};
+use serde::Serialize;
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::core::parser::{validate_install_dir, TomlParser};
+use crate::{fingerprint::InstallationRecord, installations::InstallationIndex, utils};
+
+#[derive(Subcommand, Debug)]
+pub(super) enum InstallsCommand {
+    /// Show known installations, the default one is marked with `*`
+    List,
+    /// Set an installation as the default one to manage by a manager outside of any installation
+    Use {
+        #[arg(value_name = "PATH", value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+    },
+    /// Remove an installation from the list, this does not uninstall anything
+    Remove {
+        #[arg(value_name = "PATH", value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+    },
+}
+
+impl InstallsCommand {
+    fn execute(&self) -> Result<()> {
+        let mut index = InstallationIndex::load()?;
+        match self {
+            Self::List => list_installations(&index)?,
+            Self::Use { path } => {
+                let root = utils::to_nomalized_abspath(path, None)?;
+                validate_install_dir(&root)?;
+                index.set_default(&root);
+                index.write()?;
+                info!("{}", t!("default_installation_set", path = root.display()));
+            }
+            Self::Remove { path } => {
+                let root = utils::to_nomalized_abspath(path, None)?;
+                if !index.remove(&root) {
+                    bail!("{}", t!("installation_not_found", path = root.display()));
+                }
+                index.write()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Execute `installs` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Installs { command } = subcommand else {
+        return Ok(false);
+    };
+
+    command.execute()?;
+    Ok(true)
+}
+
+/// Summary of a known installation.
+#[derive(Serialize)]
+struct InstallationEntry<'a> {
+    path: &'a Path,
+    default: bool,
+    /// `false` if the installation no longer exists.
+    valid: bool,
+    name: Option<String>,
+    version: Option<String>,
+}
+
+impl<'a> InstallationEntry<'a> {
+    fn new(root: &'a Path, default: bool) -> Self {
+        let valid = validate_install_dir(root).is_ok();
+        let (name, version) = InstallationRecord::load_from_dir(root)
+            .ok()
+            .filter(|_| valid)
+            .map(|record| (record.name, record.version))
+            .unwrap_or_default();
+        Self {
+            path: root,
+            default,
+            valid,
+            name,
+            version,
+        }
+    }
+
+    /// Return a short description of the toolkit installed, such as `(my-toolkit 1.0.0)`.
+    fn summary(&self) -> String {
+        if !self.valid {
+            return format!("({})", t!("installation_missing"));
+        }
+        match (&self.name, &self.version) {
+            (Some(name), Some(ver)) => format!("({name} {ver})"),
+            (Some(name), None) => format!("({name})"),
+            _ => String::new(),
+        }
+    }
+}
+
+fn list_installations(index: &InstallationIndex) -> Result<()> {
+    let entries = index
+        .roots
+        .iter()
+        .map(|root| InstallationEntry::new(root, index.default.as_ref() == Some(root)))
+        .collect::<Vec<_>>();
+    if OutputFormat::is_json() {
+        return common::print_json(&entries);
+    }
+
+    if entries.is_empty() {
+        info!("{}", t!("no_known_installation"));
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout();
+    for entry in &entries {
+        let marker = if entry.default { "*" } else { " " };
+        writeln!(
+            &mut stdout,
+            "{marker} {} {}",
+            entry.path.display(),
+            entry.summary()
+        )?;
+    }
+    Ok(())
+}
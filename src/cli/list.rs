@@ -2,8 +2,9 @@ use std::io::Write;
 
 use anyhow::Result;
 use clap::Subcommand;
+use serde::Serialize;
 
-use super::{handle_user_choice, GlobalOpts, ManagerSubcommands};
+use super::{common, handle_user_choice, GlobalOpts, ManagerSubcommands, OutputFormat};
 use crate::{
     components,
     fingerprint::InstallationRecord,
@@ -69,6 +70,14 @@ pub(crate) fn list_components(
         components::all_components_from_installation(&fp)?
     };
 
+    if OutputFormat::is_json() {
+        let list = components
+            .iter()
+            .filter(|c| !installed_only || c.installed)
+            .collect::<Vec<_>>();
+        return common::print_json(&list);
+    }
+
     let comp_iter = components.iter();
     let verbose = GlobalOpts::get().verbose;
     let mut stdout = std::io::stdout();
@@ -117,8 +126,37 @@ pub(crate) fn list_components(
     Ok(())
 }
 
+/// A toolkit in the JSON output of `list toolkit`.
+#[derive(Serialize)]
+struct ToolkitEntry {
+    #[serde(flatten)]
+    toolkit: Toolkit,
+    installed: bool,
+}
+
 async fn list_toolkits(installed_only: bool) -> Result<()> {
     let maybe_installed_tk = Toolkit::installed(false).await?;
+    if OutputFormat::is_json() {
+        let installed = if let Some(mutex) = maybe_installed_tk {
+            Some(mutex.lock().await.clone())
+        } else {
+            None
+        };
+        let toolkits = if installed_only {
+            installed.clone().into_iter().collect()
+        } else {
+            toolkits_from_server(false).await?
+        };
+        let list = toolkits
+            .into_iter()
+            .map(|tk| ToolkitEntry {
+                installed: installed.as_ref() == Some(&tk),
+                toolkit: tk,
+            })
+            .collect::<Vec<_>>();
+        return common::print_json(&list);
+    }
+
     let mut stdout = std::io::stdout();
 
     writeln!(&mut stdout)?;
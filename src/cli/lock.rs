@@ -0,0 +1,23 @@
+//! Separated module to export the lockfile of an installation in command line.
+
+use anyhow::Result;
+
+use super::ManagerSubcommands;
+use crate::core::parser::TomlParser;
+use crate::{get_installed_dir, lock::LockFile, utils};
+
+/// Execute `export-lock` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::ExportLock { output } = subcommand else {
+        return Ok(false);
+    };
+
+    let lock = LockFile::from_installation(get_installed_dir())?;
+    let path = match output {
+        Some(path) => utils::to_nomalized_abspath(path, None)?,
+        None => std::env::current_dir()?.join(LockFile::FILENAME),
+    };
+    utils::write_file(&path, &lock.to_toml()?, false)?;
+    info!("{}", t!("lock_exported", path = path.display()));
+    Ok(true)
+}
@@ -1,33 +1,52 @@
 //! Contains all the definition of command line arguments.
 
+mod answers;
+mod cache;
 mod common;
+mod completions;
 mod component;
+mod doctor;
+mod env;
+mod history;
+mod info;
 mod install;
+mod installs;
 mod list;
+mod lock;
+mod pin;
+mod repair;
+mod rollback;
 mod tryit;
+mod tui;
 mod uninstall;
 mod update;
+mod verify;
 
 use anyhow::{anyhow, bail, Result};
 use clap::error::ErrorKind;
-use clap::{Parser, Subcommand, ValueHint};
+use clap::{Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::engine::ArgValueCandidates;
 use common::handle_user_choice;
 use std::{
     path::{Path, PathBuf},
     str::FromStr,
+    sync::OnceLock,
 };
 use url::Url;
 
 use crate::{
     core::{GlobalOpts, Language},
+    installations,
+    toolset_manifest::Proxy,
     utils,
 };
 pub use common::pause;
+pub(crate) use completions::complete_from_env;
 
 /// Install rustup, rust toolchain, and various tools.
 // NOTE: If you changed anything in this struct, or any other child types that related to
 // this struct, make sure the README doc is updated as well,
-#[derive(Parser, Default, Debug)]
+#[derive(Parser, Default, Debug, Clone)]
 #[command(version, about)]
 pub struct Installer {
     /// Enable verbose output
@@ -60,6 +79,14 @@ pub struct Installer {
     /// Allow insecure connections when download packages from server.
     #[arg(short = 'k', long)]
     insecure: bool,
+    /// The format of output, `json` prints machine-readable documents to `stdout`
+    /// and moves log messages to `stderr`.
+    #[arg(long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// Select components in a full-screen terminal UI instead of typing their numbers,
+    /// ignored if not running in a terminal.
+    #[arg(long)]
+    tui: bool,
 
     /// Specify another language to display
     #[arg(short, long, value_name = "LANG", value_parser = Language::possible_values())]
@@ -89,8 +116,44 @@ pub struct Installer {
     /// Note that required components will be installed whether included or not.
     ///
     /// For the complete list, use `--list-components` option.
-    #[arg(short, long, value_delimiter = ',')]
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        add = ArgValueCandidates::new(completions::installer_components)
+    )]
     component: Option<Vec<String>>,
+    /// Install exactly the packages pinned in a lockfile without interaction,
+    /// which can be exported from an existing installation using `export-lock` command.
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["manifest", "component", "list_components", "registry_url", "rustup_dist_server", "rustup_update_root"]
+    )]
+    lock: Option<PathBuf>,
+    /// Install without interaction using the options in an answer file,
+    /// such as installation path, components, registry, servers and proxy.
+    ///
+    /// Options given from command line take precedence over the ones in the file.
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["lock", "list_components"]
+    )]
+    config: Option<PathBuf>,
+    /// Write the options chosen during installation to an answer file,
+    /// which can be replayed elsewhere with `--config`.
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    save_answers: Option<PathBuf>,
+    /// Show what would be done, such as the components to install, the packages to download
+    /// and the environment changes, without changing anything.
+    #[arg(long, conflicts_with = "list_components")]
+    dry_run: bool,
+    /// The download proxy, only available from an answer file.
+    #[arg(skip)]
+    proxy: Option<Proxy>,
 }
 
 #[derive(Debug, Clone)]
@@ -113,6 +176,15 @@ impl FromStr for PathOrUrl {
     }
 }
 
+impl std::fmt::Display for PathOrUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
 impl PathOrUrl {
     /// Extract [`Url`] value or convert [`PathBuf`] to [`Url`] with file scheme.
     ///
@@ -128,6 +200,25 @@ impl PathOrUrl {
     }
 }
 
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// The format of the output of query commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// JSON document that can be parsed by scripts
+    Json,
+}
+
+impl OutputFormat {
+    /// Return `true` if user wants the output to be printed as JSON documents.
+    pub(crate) fn is_json() -> bool {
+        OUTPUT_FORMAT.get() == Some(&Self::Json)
+    }
+}
+
 /// Manage Rust installation, mostly used for uninstalling.
 // NOTE: If you changed anything in this struct, or any other child types that related to
 // this struct, make sure the README doc is updated as well,
@@ -158,10 +249,22 @@ pub struct Manager {
     /// uninstallation.
     #[arg(long, conflicts_with = "no_modify_path")]
     no_modify_env: bool,
+    /// The format of output, `json` prints machine-readable documents to `stdout`
+    /// and moves log messages to `stderr`.
+    #[arg(long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// Select components in a full-screen terminal UI instead of typing their numbers,
+    /// ignored if not running in a terminal.
+    #[arg(long)]
+    tui: bool,
 
     /// Specify another language to display
     #[arg(short, long, value_name = "LANG", value_parser = Language::possible_values())]
     pub lang: Option<String>,
+    /// Specify the installation to manage, defaulting to the one containing this program,
+    /// or the one set by `installs use` if this program is not part of any installation.
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::DirPath)]
+    root: Option<PathBuf>,
     #[command(subcommand)]
     command: Option<ManagerSubcommands>,
 }
@@ -176,20 +279,36 @@ impl Installer {
     }
 
     pub fn execute(&self) -> Result<()> {
+        // the answer file must be loaded before setting up, since it might contain the language
+        let merged;
+        let installer = match &self.config {
+            Some(path) => {
+                let answers = answers::AnswerFile::load_checked(path)?;
+                merged = self.merge_answers(answers)?;
+                &merged
+            }
+            None => self,
+        };
         setup(
-            self.verbose,
-            self.quiet,
-            self.yes_to_all,
-            self.no_modify_env,
-            self.no_modify_path,
-            self.lang.as_deref(),
+            installer.verbose,
+            installer.quiet,
+            installer.yes_to_all,
+            installer.no_modify_env,
+            installer.no_modify_path,
+            installer.format,
+            installer.lang.as_deref(),
         )?;
+        tui::enable(installer.tui);
 
-        install::execute_installer(self)
+        common::report_error(install::execute_installer(installer))
     }
 }
 
 impl Manager {
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
     pub fn execute(&self) -> Result<()> {
         setup(
             self.verbose,
@@ -197,8 +316,16 @@ impl Manager {
             self.yes_to_all,
             self.no_modify_env,
             self.no_modify_path,
+            self.format,
             self.lang.as_deref(),
         )?;
+        tui::enable(self.tui);
+
+        common::report_error(self.execute_())
+    }
+
+    fn execute_(&self) -> Result<()> {
+        installations::select_installed_dir(self.root())?;
 
         let Some(subcmd) = &self.command else {
             return ManagerSubcommands::from_interaction()?.execute();
@@ -210,14 +337,21 @@ impl Manager {
 #[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum ManagerSubcommands {
-    /// Install a specific dist version
-    #[command(hide = true)]
+    /// Install a specific version of toolkit, which could be older than the installed one
+    ///
+    /// The installed components will be upgraded or downgraded to the versions provided by
+    /// that toolkit, including the Rust toolchain, and the required ones will be installed.
     Install {
         /// Allow insecure connections when download packages from server.
         #[arg(short = 'k', long)]
         insecure: bool,
-        #[arg(value_name = "VERSION")]
-        version: String,
+        /// Show what would be changed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// The toolkit to install, such as `my-toolkit@1.80.1`, or just the version
+        /// of the installed toolkit
+        #[arg(value_name = "[NAME@]VERSION")]
+        toolkit: String,
     },
     /// Update toolkit and/or this installation manager
     ///
@@ -242,8 +376,47 @@ enum ManagerSubcommands {
         /// If you want to keep the default selection, but adding some extra components to update,
         /// you need to include a `..` in the value, such as `--component a,b,..`, then not only
         /// a and b, but also other components that were selected by default will get updated.
-        #[arg(short, long, value_delimiter = ',')]
+        #[arg(
+            short,
+            long,
+            value_delimiter = ',',
+            add = ArgValueCandidates::new(completions::available_components)
+        )]
         component: Option<Vec<String>>,
+        /// Check for available updates without installing them
+        #[arg(long, conflicts_with = "component")]
+        check: bool,
+        /// Show what would be updated, downloaded and installed without changing anything
+        #[arg(long, conflicts_with = "check")]
+        dry_run: bool,
+    },
+    /// Roll back to the toolkit that was installed before the last toolkit update
+    Rollback {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+    },
+    /// Pin components at their installed versions, so that updates skip them
+    ///
+    /// Toolchain components share the same version, therefore pinning any of them
+    /// pins the whole toolchain. Show the pinned components if none is given.
+    Pin {
+        /// The list of components to pin
+        #[arg(
+            value_name = "COMPONENTS",
+            add = ArgValueCandidates::new(completions::installed_only_components)
+        )]
+        components: Vec<String>,
+    },
+    /// Unpin components, so that they can be updated again
+    Unpin {
+        /// The list of components to unpin
+        #[arg(
+            value_name = "COMPONENTS",
+            required = true,
+            add = ArgValueCandidates::new(completions::installed_only_components)
+        )]
+        components: Vec<String>,
     },
     /// Display a list of toolkits or components
     List {
@@ -253,7 +426,30 @@ enum ManagerSubcommands {
         #[command(subcommand)]
         command: Option<list::ListCommand>,
     },
-    #[command(hide = true)]
+    /// Show the details of a component or toolkit
+    Info {
+        /// The name of the component, check `list component` for available options
+        #[arg(
+            value_name = "COMPONENT",
+            required_unless_present = "toolkit",
+            conflicts_with = "toolkit",
+            add = ArgValueCandidates::new(completions::available_components)
+        )]
+        component: Option<String>,
+        /// Show a toolkit instead, in `NAME@VERSION` format, the version can be omitted
+        #[arg(
+            long,
+            value_name = "NAME@VERSION",
+            add = ArgValueCandidates::new(completions::toolkits)
+        )]
+        toolkit: Option<String>,
+        /// Also check the latest toolkit on server for the version of the component it offers
+        #[arg(long, conflicts_with = "toolkit")]
+        remote: bool,
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+    },
     /// Install or uninstall components
     Component {
         #[command(subcommand)]
@@ -264,6 +460,63 @@ enum ManagerSubcommands {
         /// Keep this manager tool, only uninstall toolkit
         #[arg(long, alias = "keep-manager")]
         keep_self: bool,
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Reinstall broken tools and toolchain components
+    Repair {
+        /// Allow insecure connections when download packages from server.
+        #[arg(short = 'k', long)]
+        insecure: bool,
+    },
+    /// Check installed files for missing, modified or unknown ones
+    Verify {
+        /// The name of a specific component to verify, defaulting to all installed tools
+        #[arg(
+            value_name = "COMPONENT",
+            add = ArgValueCandidates::new(completions::installed_only_components)
+        )]
+        component: Option<String>,
+    },
+    /// Show the history of changes made to this installation
+    History,
+    /// Check the environment for common problems, such as env vars, `PATH` and shell configurations
+    Doctor {
+        /// Apply the fixes that are safe to do, such as rewriting the configuration
+        /// section in shell rc files
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Write a lockfile that can reproduce this installation with `--lock`
+    ExportLock {
+        /// Path to write the lockfile, defaulting to `rim-lock.toml` in current directory
+        #[arg(short, long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+    /// Manage the cache of downloaded packages
+    Cache {
+        #[command(subcommand)]
+        command: cache::CacheCommand,
+    },
+    /// Manage the list of known installations
+    Installs {
+        #[command(subcommand)]
+        command: installs::InstallsCommand,
+    },
+    /// Print the commands that set the env vars and `PATH` of this installation in a shell
+    ///
+    /// This is useful when the installation was done with `--no-modify-env` or `--no-modify-path`,
+    /// for example, run `eval "$(rim-cli env --shell bash)"` to set up current session.
+    Env {
+        /// The shell to print commands for
+        #[arg(long, value_enum)]
+        shell: env::EnvShell,
+    },
+    /// Print the script that enables completions of this program in a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: completions::CompletionShell,
     },
     /// A subcommand to create a new Rust project template and let you start coding with it.
     TryIt {
@@ -288,9 +541,21 @@ impl ManagerSubcommands {
         return_if_executed! {
             install::execute_manager(self)?,
             update::execute(self)?,
+            rollback::execute(self)?,
+            pin::execute(self)?,
             list::execute(self)?,
+            info::execute(self)?,
             component::execute(self)?,
             uninstall::execute(self)?,
+            repair::execute(self)?,
+            verify::execute(self)?,
+            history::execute(self)?,
+            doctor::execute(self)?,
+            lock::execute(self)?,
+            cache::execute(self)?,
+            installs::execute(self)?,
+            env::execute(self)?,
+            completions::execute(self)?,
             tryit::execute(self)?
         }
         Ok(())
@@ -299,8 +564,8 @@ impl ManagerSubcommands {
     fn from_interaction() -> Result<Self> {
         loop {
             let Some(mut manager_opt) = Self::question_manager_option_()? else {
-                // user choose to cancel, exit the program
-                std::process::exit(0);
+                // user choose to cancel, exit the program with the code of cancellation
+                return Err(common::cancelled());
             };
 
             match manager_opt {
@@ -343,9 +608,9 @@ impl ManagerSubcommands {
                             2 t!("skip_ssl_check") => { true }
                         }
                     );
-                    Some(Self::Update { insecure, toolkit_only: false, manager_only: false, component: None })
+                    Some(Self::Update { insecure, toolkit_only: false, manager_only: false, component: None, check: false, dry_run: false })
                 },
-                2 t!("uninstall") => { Some(Self::Uninstall { keep_self: false }) },
+                2 t!("uninstall") => { Some(Self::Uninstall { keep_self: false, dry_run: false }) },
                 3 t!("list_option") => {
                     let installed = handle_user_choice!(
                         t!("choose_an_option"), 1,
@@ -373,13 +638,13 @@ impl ManagerSubcommands {
             t!("choose_an_option"), 1,
             {
                 1 t!("update_all") => {
-                    Self::Update { insecure, toolkit_only: false, manager_only: false, component }
+                    Self::Update { insecure, toolkit_only: false, manager_only: false, component, check: false, dry_run: false }
                 },
                 2 t!("update_self_only") => {
-                    Self::Update { insecure, toolkit_only: false, manager_only: true, component }
+                    Self::Update { insecure, toolkit_only: false, manager_only: true, component, check: false, dry_run: false }
                 },
                 3 t!("update_toolkit_only") => {
-                    Self::Update { insecure, toolkit_only: true, manager_only: false, component }
+                    Self::Update { insecure, toolkit_only: true, manager_only: false, component, check: false, dry_run: false }
                 },
                 4 t!("back") => { return Ok(false) }
             }
@@ -394,8 +659,8 @@ impl ManagerSubcommands {
         *self = handle_user_choice!(
             t!("choose_an_option"), 1,
             {
-                1 t!("uninstall_all") => { Self::Uninstall { keep_self: false } },
-                2 t!("uninstall_toolkit_only") => { Self::Uninstall { keep_self: true } },
+                1 t!("uninstall_all") => { Self::Uninstall { keep_self: false, dry_run: false } },
+                2 t!("uninstall_toolkit_only") => { Self::Uninstall { keep_self: true, dry_run: false } },
                 3 t!("back") => { return Ok(false) }
             }
         );
@@ -429,6 +694,7 @@ fn setup(
     yes: bool,
     no_modify_env: bool,
     no_modify_path: bool,
+    format: OutputFormat,
     lang: Option<&str>,
 ) -> Result<()> {
     // Setup locale
@@ -439,7 +705,13 @@ fn setup(
         utils::use_current_locale();
     }
     // Setup logger
-    utils::Logger::new().verbose(verbose).quiet(quiet).setup()?;
+    let is_json = format == OutputFormat::Json;
+    utils::Logger::new()
+        .verbose(verbose)
+        .quiet(quiet)
+        .stderr(is_json)
+        .setup()?;
+    OUTPUT_FORMAT.get_or_init(|| format);
     // Setup global options
     GlobalOpts::set(verbose, quiet, yes, no_modify_env, no_modify_path);
 
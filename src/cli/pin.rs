@@ -0,0 +1,109 @@
+//! Separated module to pin or unpin components in command line.
+
+use std::io::Write;
+
+use anyhow::{bail, Result};
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::components::{self, Component};
+use crate::configuration::{Configuration, Pins};
+use crate::fingerprint::InstallationRecord;
+
+/// Execute `pin` or `unpin` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    match subcommand {
+        ManagerSubcommands::Pin { components } if components.is_empty() => {
+            show_pins(&Configuration::load_from_install_dir().pins)?
+        }
+        ManagerSubcommands::Pin { components } => pin(components)?,
+        ManagerSubcommands::Unpin { components } => unpin(components)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn pin(names: &[String]) -> Result<()> {
+    let record = InstallationRecord::load_from_install_dir()?;
+    let all_components = components::all_components_from_installation(&record)?;
+    let mut config = Configuration::load_from_install_dir();
+
+    // make sure every name is valid before pinning any of them
+    let to_pin = names
+        .iter()
+        .map(|name| find_component(&all_components, name))
+        .collect::<Result<Vec<_>>>()?;
+    for comp in to_pin {
+        let name = &comp.name;
+        // toolchain components are pinned altogether, as long as the toolchain is installed
+        let is_installed = if comp.kind.is_from_toolchain() {
+            record.installed_toolchain().is_some()
+        } else {
+            comp.installed
+        };
+        if !is_installed {
+            info!("{}", t!("component_not_installed_skipped", name = name));
+            continue;
+        }
+
+        if !config.pins.pin(comp)? {
+            info!("{}", t!("component_already_pinned", name = name));
+        } else if comp.kind.is_from_toolchain() {
+            let version = config.pins.toolchain().unwrap_or_default();
+            info!("{}", t!("toolchain_pinned", name = name, version = version));
+        } else {
+            let version = comp.version.as_deref().unwrap_or("N/A");
+            info!("{}", t!("component_pinned", name = name, version = version));
+        }
+    }
+
+    config.write_to_install_dir()
+}
+
+fn unpin(names: &[String]) -> Result<()> {
+    let record = InstallationRecord::load_from_install_dir()?;
+    let all_components = components::all_components_from_installation(&record)?;
+    let mut config = Configuration::load_from_install_dir();
+
+    for name in names {
+        // a pinned tool might not be available in current installation anymore,
+        // which can still be unpinned by its name.
+        let comp = all_components
+            .iter()
+            .find(|c| &c.name == name)
+            .cloned()
+            .unwrap_or_else(|| Component::new(name, ""));
+        if config.pins.unpin(&comp) {
+            info!("{}", t!("component_unpinned", name = name));
+        } else {
+            info!("{}", t!("component_not_pinned", name = name));
+        }
+    }
+
+    config.write_to_install_dir()
+}
+
+fn find_component<'a>(all_components: &'a [Component], name: &str) -> Result<&'a Component> {
+    let Some(comp) = all_components.iter().find(|c| c.name == name) else {
+        bail!("{}", t!("component_not_found", name = name));
+    };
+    Ok(comp)
+}
+
+fn show_pins(pins: &Pins) -> Result<()> {
+    if OutputFormat::is_json() {
+        return common::print_json(pins);
+    }
+    if pins.is_empty() {
+        info!("{}", t!("no_pinned_component"));
+        return Ok(());
+    }
+
+    let mut stdout = std::io::stdout();
+    if let Some(version) = pins.toolchain() {
+        writeln!(&mut stdout, "{} ({version})", t!("rust_toolchain"))?;
+    }
+    for (name, version) in pins.tools() {
+        writeln!(&mut stdout, "{name} ({version})")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Separated module to handle repairing in command line.
+
+use anyhow::Result;
+
+use super::{common, ManagerSubcommands};
+use crate::{get_installed_dir, toolset_manifest::ToolsetManifest, InstallConfiguration};
+
+/// Execute `repair` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Repair { insecure } = subcommand else {
+        return Ok(false);
+    };
+
+    let manifest = ToolsetManifest::load_from_install_dir()?;
+    let config = InstallConfiguration::new(get_installed_dir(), &manifest)?.insecure(*insecure);
+    let plan = config.detect_broken()?;
+    if plan.is_empty() {
+        info!("{}", t!("nothing_to_repair"));
+        return Ok(true);
+    }
+
+    if !common::confirm(t!("repair_confirmation", list = plan.to_list()), true)? {
+        return Err(common::cancelled());
+    }
+    config.repair(&plan)?;
+
+    info!("{}", t!("repair_finished"));
+    Ok(true)
+}
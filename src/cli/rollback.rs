@@ -0,0 +1,61 @@
+//! Separated module to handle rolling back to the previous toolkit in command line.
+
+use anyhow::Result;
+
+use super::{common, ManagerSubcommands};
+use crate::fingerprint::InstallationRecord;
+use crate::rollback::{RollbackPlan, Snapshot};
+use crate::{get_installed_dir, InstallConfiguration};
+
+/// Execute `rollback` command.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Rollback { insecure } = subcommand else {
+        return Ok(false);
+    };
+
+    let install_dir = get_installed_dir();
+    if !common::handle_unfinished_operation(install_dir)? {
+        return Ok(true);
+    }
+    let Some(snapshot) = Snapshot::load(install_dir)? else {
+        info!("{}", t!("no_previous_toolkit"));
+        return Ok(true);
+    };
+
+    let record = InstallationRecord::load_from_install_dir()?;
+    let plan = RollbackPlan::new(&snapshot, &record)?;
+    let question = t!(
+        "toolkit_rollback_confirmation",
+        current = toolkit_display(&record),
+        previous = toolkit_display(&snapshot.record),
+        list = plan.to_list()
+    );
+    if !common::confirm(question, true)? {
+        return Err(common::cancelled());
+    }
+
+    let result = InstallConfiguration::for_snapshot(install_dir, &snapshot)?
+        .insecure(*insecure)
+        .rollback(&plan);
+    if let Err(e) = &result {
+        common::handle_failed_operation(install_dir, e)?;
+    }
+    result?;
+
+    info!(
+        "{}",
+        t!(
+            "toolkit_rollback_finished",
+            previous = toolkit_display(&snapshot.record)
+        )
+    );
+    Ok(true)
+}
+
+fn toolkit_display(record: &InstallationRecord) -> String {
+    format!(
+        "{} {}",
+        record.name.as_deref().unwrap_or("N/A"),
+        record.version.as_deref().unwrap_or("N/A")
+    )
+}
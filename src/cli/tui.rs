@@ -0,0 +1,487 @@
+//! A full-screen terminal UI to select components with checkboxes,
+//! which is easier to use than typing numbers when there are lots of components.
+//!
+//! Components are shown under their groups, which can be collapsed or expanded.
+//! This is only used when enabled by `--tui` and both `stdin` and `stdout` are terminals,
+//! otherwise the plain prompts in [`common`](super::common) are used.
+
+use std::collections::HashSet;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+use indexmap::IndexMap;
+
+use super::common::{ComponentChoices, ComponentDecoration};
+use crate::components::Component;
+
+static TUI_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Allow the terminal UI to be used for component selection.
+pub(super) fn enable(yes: bool) {
+    TUI_ENABLED.store(yes, Ordering::Relaxed);
+}
+
+/// Return `true` if the terminal UI was enabled and can be shown in current terminal.
+pub(crate) fn is_available() -> bool {
+    TUI_ENABLED.load(Ordering::Relaxed) && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Let the user select components in a full-screen terminal UI.
+///
+/// - `defaults` are the components that are selected initially.
+/// - `locked` decides whether a component must be selected, such as required components,
+///   which cannot be unchecked by the user.
+/// - `disabled` decides whether a component must not be selected, such as pinned components,
+///   which cannot be checked by the user.
+///
+/// Return `None` if the user cancelled the selection.
+pub(crate) fn select_components<'c, F, G>(
+    title: &str,
+    components: &'c [Component],
+    defaults: &ComponentChoices<'c>,
+    decoration: ComponentDecoration<'_>,
+    locked: F,
+    disabled: G,
+) -> Result<Option<ComponentChoices<'c>>>
+where
+    F: Fn(&Component) -> bool,
+    G: Fn(&Component) -> bool,
+{
+    let mut selector = Selector::new(components, defaults.keys().copied(), locked, disabled);
+
+    let _guard = TerminalGuard::enter()?;
+    let mut stdout = io::stdout();
+    loop {
+        let (_, height) = terminal::size()?;
+        draw(&mut stdout, title, &selector, decoration, height)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match selector.handle_key(key) {
+            Some(Outcome::Confirmed) => return Ok(Some(selector.choices())),
+            Some(Outcome::Cancelled) => return Ok(None),
+            None => (),
+        }
+    }
+}
+
+/// Switches the terminal to raw mode and alternate screen, and restores it once dropped,
+/// including when returning early with an error.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        _ = terminal::disable_raw_mode();
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Confirmed,
+    Cancelled,
+}
+
+/// A line in the list, either a group header or a component (by its index).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row<'a> {
+    Group(&'a str),
+    Component(usize),
+}
+
+/// The state of the selection, kept separated from drawing so that it can be tested.
+struct Selector<'c> {
+    components: &'c [Component],
+    /// Group names with the indexes of the components in each of them, in order of appearance.
+    groups: IndexMap<&'c str, Vec<usize>>,
+    selected: HashSet<usize>,
+    locked: HashSet<usize>,
+    disabled: HashSet<usize>,
+    collapsed: HashSet<&'c str>,
+    /// The position of the cursor in [`rows`](Selector::rows).
+    cursor: usize,
+}
+
+impl<'c> Selector<'c> {
+    fn new<F, G>(
+        components: &'c [Component],
+        defaults: impl Iterator<Item = usize>,
+        locked: F,
+        disabled: G,
+    ) -> Self
+    where
+        F: Fn(&Component) -> bool,
+        G: Fn(&Component) -> bool,
+    {
+        let mut groups: IndexMap<&str, Vec<usize>> = IndexMap::new();
+        for (idx, comp) in components.iter().enumerate() {
+            let group = comp.group_name.as_deref().unwrap_or_default();
+            groups.entry(group).or_default().push(idx);
+        }
+        let locked: HashSet<usize> = components
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, c)| locked(c).then_some(idx))
+            .collect();
+        let disabled: HashSet<usize> = components
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, c)| disabled(c).then_some(idx))
+            .collect();
+        let mut selected: HashSet<usize> = defaults.collect();
+        selected.extend(&locked);
+        selected.retain(|idx| !disabled.contains(idx));
+
+        Self {
+            components,
+            groups,
+            selected,
+            locked,
+            disabled,
+            collapsed: HashSet::new(),
+            cursor: 0,
+        }
+    }
+
+    /// The visible rows, components in collapsed groups are hidden.
+    ///
+    /// Components without a group are listed at the top without a header.
+    fn rows(&self) -> Vec<Row<'c>> {
+        let mut rows: Vec<Row<'c>> = self
+            .groups
+            .get("")
+            .into_iter()
+            .flatten()
+            .map(|idx| Row::Component(*idx))
+            .collect();
+        for (group, members) in self.groups.iter().filter(|(g, _)| !g.is_empty()) {
+            rows.push(Row::Group(group));
+            if !self.collapsed.contains(group) {
+                rows.extend(members.iter().map(|idx| Row::Component(*idx)));
+            }
+        }
+        rows
+    }
+
+    fn current_row(&self) -> Option<Row<'c>> {
+        self.rows().get(self.cursor).copied()
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Outcome> {
+        let row_count = self.rows().len();
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(Outcome::Cancelled)
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(Outcome::Cancelled),
+            KeyCode::Enter => return Some(Outcome::Confirmed),
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(row_count.saturating_sub(1));
+            }
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = row_count.saturating_sub(1),
+            KeyCode::Char(' ') => match self.current_row() {
+                Some(Row::Group(group)) => self.toggle_group_collapsed(group),
+                Some(Row::Component(idx)) => self.toggle(idx),
+                None => (),
+            },
+            KeyCode::Left => {
+                if let Some(group) = self.current_group() {
+                    self.collapsed.insert(group);
+                    self.move_to_group(group);
+                }
+            }
+            KeyCode::Right => {
+                if let Some(Row::Group(group)) = self.current_row() {
+                    self.collapsed.remove(group);
+                }
+            }
+            KeyCode::Char('a') => {
+                if let Some(group) = self.current_group() {
+                    self.toggle_all_in_group(group);
+                }
+            }
+            _ => (),
+        }
+        None
+    }
+
+    fn toggle(&mut self, idx: usize) {
+        if self.locked.contains(&idx) || self.disabled.contains(&idx) {
+            return;
+        }
+        if !self.selected.remove(&idx) {
+            self.selected.insert(idx);
+        }
+    }
+
+    fn toggle_group_collapsed(&mut self, group: &'c str) {
+        if !self.collapsed.remove(group) {
+            self.collapsed.insert(group);
+        }
+    }
+
+    /// Select every component in `group`, or unselect them if all of them are already selected.
+    fn toggle_all_in_group(&mut self, group: &str) {
+        let Some(members) = self.groups.get(group) else {
+            return;
+        };
+        let all_selected = members
+            .iter()
+            .filter(|idx| !self.disabled.contains(idx))
+            .all(|idx| self.selected.contains(idx));
+        for idx in members.iter().filter(|idx| !self.disabled.contains(idx)) {
+            if all_selected && !self.locked.contains(idx) {
+                self.selected.remove(idx);
+            } else {
+                self.selected.insert(*idx);
+            }
+        }
+    }
+
+    /// The group of the row under the cursor.
+    fn current_group(&self) -> Option<&'c str> {
+        let group = match self.current_row()? {
+            Row::Group(group) => group,
+            Row::Component(idx) => self.components[idx].group_name.as_deref()?,
+        };
+        Some(group)
+    }
+
+    fn move_to_group(&mut self, group: &str) {
+        if let Some(pos) = self.rows().iter().position(|r| *r == Row::Group(group)) {
+            self.cursor = pos;
+        }
+    }
+
+    /// The selected components, in the same order as the full list.
+    fn choices(&self) -> ComponentChoices<'c> {
+        self.components
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| self.selected.contains(idx))
+            .collect()
+    }
+}
+
+fn draw(
+    out: &mut impl Write,
+    title: &str,
+    selector: &Selector<'_>,
+    decoration: ComponentDecoration<'_>,
+    height: u16,
+) -> Result<()> {
+    let rows = selector.rows();
+    // title, a blank line, then the list, a blank line, description and key hints at the bottom
+    let list_height = usize::from(height).saturating_sub(6).max(1);
+    let first = selector.cursor.saturating_sub(list_height - 1);
+
+    queue!(
+        out,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0),
+        SetAttribute(Attribute::Bold),
+        Print(title),
+        SetAttribute(Attribute::Reset),
+        cursor::MoveToNextLine(2),
+    )?;
+    for (pos, row) in rows.iter().enumerate().skip(first).take(list_height) {
+        let pointer = if pos == selector.cursor { ">" } else { " " };
+        let line = match row {
+            Row::Group(group) => {
+                let (sign, count) = selector
+                    .groups
+                    .get(*group)
+                    .map(|members| {
+                        let selected = members
+                            .iter()
+                            .filter(|idx| selector.selected.contains(idx))
+                            .count();
+                        (
+                            if selector.collapsed.contains(group) {
+                                "+"
+                            } else {
+                                "-"
+                            },
+                            format!("{selected}/{}", members.len()),
+                        )
+                    })
+                    .unwrap_or_default();
+                format!("{pointer} {sign} {group} ({count})")
+            }
+            Row::Component(idx) => {
+                let comp = &selector.components[*idx];
+                let checkbox = if selector.locked.contains(idx) {
+                    "[*]"
+                } else if selector.disabled.contains(idx) {
+                    "[-]"
+                } else if selector.selected.contains(idx) {
+                    "[x]"
+                } else {
+                    "[ ]"
+                };
+                let indent = if comp.group_name.is_some() {
+                    "    "
+                } else {
+                    "  "
+                };
+                format!(
+                    "{pointer}{indent}{checkbox} {}{}",
+                    comp.name,
+                    decoration.for_component(comp)
+                )
+            }
+        };
+        queue!(out, Print(line), cursor::MoveToNextLine(1))?;
+    }
+
+    let desc = match selector.current_row() {
+        Some(Row::Component(idx)) => selector.components[idx].desc.as_str(),
+        _ => "",
+    };
+    queue!(
+        out,
+        cursor::MoveTo(0, height.saturating_sub(3)),
+        Print(format!("{}: {desc}", t!("description"))),
+        cursor::MoveToNextLine(1),
+        SetAttribute(Attribute::Dim),
+        Print(t!("tui_key_hints")),
+        SetAttribute(Attribute::Reset),
+    )?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn components() -> Vec<Component> {
+        vec![
+            Component::new("a", "")
+                .with_group(Some("group 1"))
+                .required(true),
+            Component::new("b", "").with_group(Some("group 1")),
+            Component::new("c", "").with_group(Some("group 2")),
+            Component::new("d", ""),
+        ]
+    }
+
+    fn press(selector: &mut Selector<'_>, code: KeyCode) -> Option<Outcome> {
+        selector.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn selected_names(selector: &Selector<'_>) -> Vec<String> {
+        selector
+            .choices()
+            .values()
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn ungrouped_components_come_first() {
+        let comps = components();
+        let selector = Selector::new(&comps, [].into_iter(), |_| false, |_| false);
+        assert_eq!(
+            selector.rows(),
+            [
+                Row::Component(3),
+                Row::Group("group 1"),
+                Row::Component(0),
+                Row::Component(1),
+                Row::Group("group 2"),
+                Row::Component(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn locked_components_cannot_be_unselected() {
+        let comps = components();
+        let mut selector = Selector::new(&comps, [].into_iter(), |c| c.required, |_| false);
+        assert_eq!(selected_names(&selector), ["a"]);
+
+        // move to `a` and try to uncheck it
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Char(' '));
+        assert_eq!(selected_names(&selector), ["a"]);
+
+        // toggle all in group then toggle again, `a` stays selected
+        press(&mut selector, KeyCode::Char('a'));
+        assert_eq!(selected_names(&selector), ["a", "b"]);
+        press(&mut selector, KeyCode::Char('a'));
+        assert_eq!(selected_names(&selector), ["a"]);
+    }
+
+    #[test]
+    fn disabled_components_cannot_be_selected() {
+        let comps = components();
+        // `b` is disabled even though it is selected by default
+        let mut selector = Selector::new(&comps, [1, 2].into_iter(), |_| false, |c| c.name == "b");
+        assert_eq!(selected_names(&selector), ["c"]);
+
+        // move to `b` and try to check it
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Char(' '));
+        assert_eq!(selected_names(&selector), ["c"]);
+
+        // toggle all in group only selects `a`, then unselects it
+        press(&mut selector, KeyCode::Char('a'));
+        assert_eq!(selected_names(&selector), ["a", "c"]);
+        press(&mut selector, KeyCode::Char('a'));
+        assert_eq!(selected_names(&selector), ["c"]);
+    }
+
+    #[test]
+    fn collapse_and_expand_group() {
+        let comps = components();
+        let mut selector = Selector::new(&comps, [2].into_iter(), |_| false, |_| false);
+        let full_len = selector.rows().len();
+
+        // collapse `group 1` with the cursor on its member
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Down);
+        press(&mut selector, KeyCode::Left);
+        assert_eq!(selector.rows().len(), full_len - 2);
+        assert_eq!(selector.current_row(), Some(Row::Group("group 1")));
+
+        press(&mut selector, KeyCode::Right);
+        assert_eq!(selector.rows().len(), full_len);
+
+        // collapsing does not change the selection
+        press(&mut selector, KeyCode::Char(' '));
+        assert_eq!(selected_names(&selector), ["c"]);
+        assert_eq!(
+            press(&mut selector, KeyCode::Enter),
+            Some(Outcome::Confirmed)
+        );
+    }
+
+    #[test]
+    fn cancel_selection() {
+        let comps = components();
+        let mut selector = Selector::new(&comps, [].into_iter(), |_| false, |_| false);
+        assert_eq!(press(&mut selector, KeyCode::Esc), Some(Outcome::Cancelled));
+    }
+}
@@ -1,6 +1,7 @@
 //! Separated module to handle uninstallation in command line.
 
 use crate::core::uninstall::UninstallConfiguration;
+use crate::get_installed_dir;
 
 use super::{common, ManagerSubcommands};
 
@@ -8,14 +9,27 @@ use anyhow::Result;
 
 /// Execute `uninstall` command.
 pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
-    let ManagerSubcommands::Uninstall { keep_self } = subcommand else {
+    let ManagerSubcommands::Uninstall { keep_self, dry_run } = subcommand else {
         return Ok(false);
     };
 
+    if *dry_run {
+        let plan = UninstallConfiguration::init(None)?.plan(None, !keep_self);
+        common::print_plan(&plan)?;
+        return Ok(true);
+    }
+    if !common::handle_unfinished_operation(get_installed_dir())? {
+        return Ok(true);
+    }
+
     let config = UninstallConfiguration::init(None)?;
     let installed = config.install_record.print_installation();
 
-    // Ask confirmation
+    // Ask confirmation, showing which installation is going to be affected
+    info!(
+        "{}",
+        t!("uninstall_root", path = get_installed_dir().display())
+    );
     let prompt = if !keep_self {
         t!(
             "uninstall_all_confirmation",
@@ -26,10 +40,13 @@ pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
         t!("uninstall_confirmation", list = installed)
     };
     if !common::confirm(prompt, false)? {
-        return Ok(true);
+        return Err(common::cancelled());
     }
 
-    config.uninstall(!keep_self)?;
+    if let Err(e) = config.uninstall(!keep_self) {
+        common::handle_failed_operation(get_installed_dir(), &e)?;
+        return Err(e);
+    }
 
     Ok(true)
 }
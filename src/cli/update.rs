@@ -1,20 +1,29 @@
 use anyhow::{anyhow, Result};
+use semver::Version;
+use serde::Serialize;
 use std::collections::HashSet;
+use std::fmt::Display;
+use std::io::Write;
 use std::path::Path;
 use url::Url;
 
 use crate::components::Component;
+use crate::configuration::{Configuration, Pins};
+use crate::core::history::Action;
+use crate::core::plan::Plan;
 use crate::core::toolkit::Toolkit;
-use crate::core::update::UpdateOpt;
+use crate::core::update::{
+    check_self_update, check_toolkit_update, UpdateKind, UpdateOpt, UpdatePayload,
+};
 use crate::toolkit::latest_installable_toolkit;
 use crate::toolset_manifest::get_toolset_manifest;
 use crate::utils::blocking;
-use crate::InstallConfiguration;
+use crate::{get_installed_dir, InstallConfiguration};
 
 use super::common::{
     ComponentChoices, ComponentDecoration, ComponentListBuilder, VersionDiff, VersionDiffMap,
 };
-use super::{common, GlobalOpts, ManagerSubcommands};
+use super::{common, tui, GlobalOpts, ManagerSubcommands, OutputFormat};
 
 pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
     let ManagerSubcommands::Update {
@@ -22,15 +31,37 @@ pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
         manager_only,
         insecure,
         component,
+        check,
+        dry_run,
     } = cmd
     else {
         return Ok(false);
     };
 
+    if *check {
+        blocking!(check_updates(*toolkit_only, *manager_only, *insecure))?;
+        return Ok(true);
+    }
+    if *dry_run {
+        blocking!(plan_updates(
+            *toolkit_only,
+            *manager_only,
+            *insecure,
+            component.as_deref()
+        ))?;
+        return Ok(true);
+    }
+
     let update_opt = UpdateOpt::new().insecure(*insecure);
     if !manager_only {
         update_opt.update_toolkit(|path| {
-            blocking!(update_toolkit_(path, *insecure, component.as_deref()))
+            blocking!(update_toolkit_(
+                path,
+                *insecure,
+                component.as_deref(),
+                false
+            ))
+            .map(|_| ())
         })?;
     }
     if !toolkit_only {
@@ -40,20 +71,123 @@ pub(super) fn execute(cmd: &ManagerSubcommands) -> Result<bool> {
     Ok(true)
 }
 
+/// Available updates of the manager and toolkit.
+#[derive(Serialize)]
+struct UpdateCheck {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manager: Option<UpdateKind<Version>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toolkit: Option<UpdateKind<UpdatePayload>>,
+}
+
+/// Check and print available updates, without installing anything.
+async fn check_updates(toolkit_only: bool, manager_only: bool, insecure: bool) -> Result<()> {
+    let mut result = UpdateCheck {
+        manager: None,
+        toolkit: None,
+    };
+    if !manager_only {
+        result.toolkit = Some(check_toolkit_update(insecure).await?);
+    }
+    if !toolkit_only {
+        result.manager = Some(check_self_update(insecure).await?);
+    }
+
+    if OutputFormat::is_json() {
+        return common::print_json(&result);
+    }
+
+    let mut stdout = std::io::stdout();
+    if let Some(kind) = &result.toolkit {
+        let status = update_status(kind, |payload| payload.version.clone());
+        writeln!(&mut stdout, "{}: {status}", t!("toolkit"))?;
+    }
+    if let Some(kind) = &result.manager {
+        let status = update_status(kind, Version::to_string);
+        writeln!(&mut stdout, "{}: {status}", t!("manager"))?;
+    }
+    Ok(())
+}
+
+/// The plan of updating the manager and toolkit.
+#[derive(Serialize)]
+struct UpdatePlan {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manager: Option<UpdateKind<Version>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toolkit: Option<Plan>,
+}
+
+/// Print what would be done by an update, without changing anything.
+async fn plan_updates(
+    toolkit_only: bool,
+    manager_only: bool,
+    insecure: bool,
+    user_selected_comps: Option<&[String]>,
+) -> Result<()> {
+    let mut result = UpdatePlan {
+        manager: None,
+        toolkit: None,
+    };
+    if !manager_only {
+        result.toolkit =
+            update_toolkit_(get_installed_dir(), insecure, user_selected_comps, true).await?;
+    }
+    if !toolkit_only {
+        result.manager = Some(check_self_update(insecure).await?);
+    }
+
+    if OutputFormat::is_json() {
+        return common::print_json(&result);
+    }
+
+    if let Some(plan) = &result.toolkit {
+        common::print_plan(plan)?;
+    }
+    if let Some(kind) = &result.manager {
+        let status = update_status(kind, Version::to_string);
+        writeln!(std::io::stdout(), "\n{}: {status}", t!("manager"))?;
+    }
+    Ok(())
+}
+
+fn update_status<T, F, D>(kind: &UpdateKind<T>, version_of: F) -> String
+where
+    F: Fn(&T) -> D,
+    D: Display,
+{
+    match kind {
+        UpdateKind::Newer { current, latest } => {
+            format!("{} -> {}", version_of(current), version_of(latest))
+        }
+        UpdateKind::Uncertain => t!("update_uncertain").to_string(),
+        UpdateKind::UnNeeded => t!("up_to_date").to_string(),
+    }
+}
+
+/// Update the toolkit in `install_dir` to the latest one.
+///
+/// If `dry_run` is `true`, nothing will be changed, and the plan of the update is returned instead.
 async fn update_toolkit_(
     install_dir: &Path,
     insecure: bool,
     user_selected_comps: Option<&[String]>,
-) -> Result<()> {
+    dry_run: bool,
+) -> Result<Option<Plan>> {
+    if !dry_run && !common::handle_unfinished_operation(install_dir)? {
+        return Ok(None);
+    }
+
     let Some(installed) = Toolkit::installed(false).await? else {
         info!("{}", t!("no_toolkit_installed"));
-        return Ok(());
+        return Ok(None);
     };
     let installed = &*installed.lock().await;
 
     // get possible update
     let Some(latest_toolkit) = latest_installable_toolkit(installed, insecure).await? else {
-        return Ok(());
+        // still show a plan in dry run, so that it's clear that nothing would be updated
+        return Ok(dry_run.then(|| Plan::new(Action::Update, install_dir)));
     };
     log::debug!(
         "detected latest toolkit: {}-{}",
@@ -72,7 +206,7 @@ async fn update_toolkit_(
             must contains a valid `manifest_url`"
             )
         })?;
-    let manifest = get_toolset_manifest(Some(manifest_url), insecure).await?;
+    let manifest = get_toolset_manifest(Some(manifest_url.clone()), insecure).await?;
     let new_components = manifest.current_target_components(false)?;
 
     // notify user that we will install the latest update to replace their current installation
@@ -85,15 +219,34 @@ async fn update_toolkit_(
         )
     );
 
-    let updater = ComponentsUpdater::new(&installed.components, &new_components);
+    let pins = Configuration::load_from_install_dir().pins;
+    let updater = ComponentsUpdater::new(&installed.components, &new_components, &pins);
+    if dry_run {
+        // plan with the default selection, a dry run should never prompt
+        let components = updater
+            .default_component_choices(user_selected_comps)
+            .into_values()
+            .cloned()
+            .collect::<Vec<_>>();
+        let plan = InstallConfiguration::for_plan(install_dir, &manifest)?
+            .plan(Action::Update, &components)?;
+        return Ok(Some(plan));
+    }
+
     // let user choose if they want to update installed component only, or want to select more components to install
-    if let UpdateOption::Yes(components) = updater.to_update_option(user_selected_comps)? {
-        // install update for selected components
-        let config = InstallConfiguration::new(install_dir, &manifest)?;
-        config.update(components.into_values().cloned().collect())
-    } else {
-        Ok(())
+    let UpdateOption::Yes(components) = updater.to_update_option(user_selected_comps)? else {
+        return Err(common::cancelled());
+    };
+    let components = components.into_values().cloned().collect::<Vec<_>>();
+
+    // install update for selected components
+    let config =
+        InstallConfiguration::new(install_dir, &manifest)?.with_manifest_url(Some(manifest_url));
+    let result = config.update(components);
+    if let Err(e) = &result {
+        common::handle_failed_operation(install_dir, e)?;
     }
+    result.map(|_| None)
 }
 
 enum UpdateOption<'c> {
@@ -104,10 +257,11 @@ enum UpdateOption<'c> {
 struct ComponentsUpdater<'c> {
     target: &'c [Component],
     version_diff: VersionDiffMap<'c>,
+    pins: &'c Pins,
 }
 
 impl<'c> ComponentsUpdater<'c> {
-    fn new(installed: &'c [Component], target: &'c [Component]) -> Self {
+    fn new(installed: &'c [Component], target: &'c [Component], pins: &'c Pins) -> Self {
         let version_diff = target
             .iter()
             .map(|c| {
@@ -128,6 +282,7 @@ impl<'c> ComponentsUpdater<'c> {
                         from: installed_version,
                         to: c.version.as_deref(),
                         is_newly_supported,
+                        is_pinned: pins.is_pinned(c),
                     },
                 )
             })
@@ -135,6 +290,7 @@ impl<'c> ComponentsUpdater<'c> {
         Self {
             target,
             version_diff,
+            pins,
         }
     }
 
@@ -146,8 +302,8 @@ impl<'c> ComponentsUpdater<'c> {
         self.version_diff
             .iter()
             .filter_map(|(name, diff)| {
-                // return only the components that are previously installed
-                if diff.is_newly_supported {
+                // return only the components that are previously installed and not pinned
+                if diff.is_newly_supported || diff.is_pinned {
                     None
                 } else {
                     (diff.from != diff.to).then_some(*name)
@@ -164,6 +320,8 @@ impl<'c> ComponentsUpdater<'c> {
     /// Default component set contains components that:
     /// - User provide a list of components via commandline, such as `--components comp_a,comp_b`.
     /// - Was previously installed and have new version available.
+    ///
+    /// Pinned components are always excluded.
     fn default_component_choices(
         &self,
         user_selected_comps: Option<&[String]>,
@@ -177,19 +335,39 @@ impl<'c> ComponentsUpdater<'c> {
         );
         let is_append = user_set.remove("..");
         if is_append {
-            base_set.extend(user_set);
+            base_set.extend(&user_set);
         } else {
-            base_set = user_set;
+            base_set = user_set.clone();
         }
 
         self.target
             .iter()
             .enumerate()
             .filter(|(_, c)| base_set.contains(c.name.as_str()))
+            .filter(|(_, c)| {
+                let pinned = self.pins.is_pinned(c);
+                if pinned && user_set.contains(c.name.as_str()) {
+                    warn!("{}", t!("pinned_component_skipped", name = c.name));
+                }
+                !pinned
+            })
             .collect()
     }
 
     fn custom_component_choices(&self, orig: ComponentChoices<'c>) -> Result<ComponentChoices<'c>> {
+        if tui::is_available() {
+            let selection = tui::select_components(
+                &t!("tui_select_components_to_update"),
+                self.target,
+                &orig,
+                ComponentDecoration::VersionDiff(&self.version_diff),
+                |_| false,
+                |c| self.pins.is_pinned(c),
+            )?;
+            // keep the original selection if the user cancelled
+            return Ok(selection.unwrap_or(orig));
+        }
+
         let choices = ComponentListBuilder::new(self.target)
             .decorate(ComponentDecoration::VersionDiff(&self.version_diff))
             .show_desc(true)
@@ -215,6 +393,13 @@ impl<'c> ComponentsUpdater<'c> {
             .iter()
             .enumerate()
             .filter(|(idx, _)| index_set.contains(&(idx + 1)))
+            .filter(|(_, c)| {
+                let pinned = self.pins.is_pinned(c);
+                if pinned {
+                    warn!("{}", t!("pinned_component_skipped", name = c.name));
+                }
+                !pinned
+            })
             .collect())
     }
 
@@ -0,0 +1,75 @@
+//! Separated module to handle installation verification in command line.
+
+use std::io::Write;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{common, ManagerSubcommands, OutputFormat};
+use crate::{core::verify, fingerprint::InstallationRecord};
+
+/// The result of `verify`, printed as a single JSON document with `--format json`.
+#[derive(Serialize)]
+struct VerifyOutput<'a> {
+    reports: &'a [verify::ToolReport],
+    problems: usize,
+}
+
+/// Execute `verify` command.
+///
+/// Returns an error if any problem was found, therefore the program exits with
+/// non-zero status, which can be used by scripts to check the installation.
+pub(super) fn execute(subcommand: &ManagerSubcommands) -> Result<bool> {
+    let ManagerSubcommands::Verify { component } = subcommand else {
+        return Ok(false);
+    };
+
+    let record = InstallationRecord::load_from_install_dir()?;
+    let reports = verify::verify_installation(&record, component.as_deref())?;
+    let problem_count: usize = reports.iter().map(|r| r.problems.len()).sum();
+
+    if OutputFormat::is_json() {
+        common::print_json(&VerifyOutput {
+            reports: &reports,
+            problems: problem_count,
+        })?;
+    } else {
+        print_reports(&reports)?;
+    }
+
+    if problem_count > 0 {
+        return Err(common::reported(t!("verify_failed", count = problem_count)));
+    }
+    Ok(true)
+}
+
+fn print_reports(reports: &[verify::ToolReport]) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    for report in reports {
+        if report.is_ok() {
+            let status = if report.tracked {
+                t!("ok")
+            } else {
+                t!("verify_untracked")
+            };
+            writeln!(&mut stdout, "{}: {status}", report.name)?;
+            continue;
+        }
+
+        writeln!(
+            &mut stdout,
+            "{}: {}",
+            report.name,
+            t!("problems_found", count = report.problems.len())
+        )?;
+        for problem in &report.problems {
+            writeln!(
+                &mut stdout,
+                "    {}: {}",
+                problem.kind,
+                problem.path.display()
+            )?;
+        }
+    }
+    Ok(())
+}
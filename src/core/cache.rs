@@ -0,0 +1,290 @@
+//! A persistent cache of the packages downloaded from urls, stored under the installation
+//! directory, so that they can be reused when installing, updating, repairing or rolling back,
+//! and the interrupted downloads can be resumed instead of starting over.
+//!
+//! Each package is stored in its own directory named after the SHA-256 of its url,
+//! along with an [`entry.toml`](CacheEntry) describing it. A package is downloaded to
+//! a `.part` file first, which is renamed once finished, and then the checksum of it is recorded,
+//! so a cached package is only reused if it matches the expected checksum (when there is one).
+
+use std::{
+    cmp::Reverse,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::parser::TomlParser;
+use crate::utils;
+
+/// The name of the cache directory under installation directory.
+pub(crate) const CACHE_DIR: &str = "cache";
+const PARTIAL_EXT: &str = "part";
+
+/// The information of a cached package.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CacheEntry {
+    pub url: Url,
+    pub filename: String,
+    /// The checksum of the package, which is `None` if it's not completely downloaded yet.
+    pub sha256: Option<String>,
+    pub last_used: DateTime<Utc>,
+    /// The bytes of the package that have been downloaded.
+    #[serde(skip_deserializing)]
+    pub size: u64,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl TomlParser for CacheEntry {
+    const FILENAME: &'static str = "entry.toml";
+}
+
+impl CacheEntry {
+    /// Return `true` if the package is not completely downloaded yet.
+    pub fn is_partial(&self) -> bool {
+        self.sha256.is_none()
+    }
+
+    /// The path to the package, or the partially downloaded file of it.
+    pub fn path(&self) -> PathBuf {
+        if self.is_partial() {
+            self.dir.join(format!("{}.{PARTIAL_EXT}", self.filename))
+        } else {
+            self.dir.join(&self.filename)
+        }
+    }
+
+    fn touch(&mut self) -> Result<()> {
+        self.last_used = Utc::now();
+        self.write_to_dir(&self.dir)
+    }
+}
+
+/// The download cache of an installation.
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    root: PathBuf,
+}
+
+impl DownloadCache {
+    /// Get the download cache of the installation at `install_dir`,
+    /// nothing will be created until something is cached.
+    pub fn new(install_dir: &Path) -> Self {
+        Self {
+            root: install_dir.join(CACHE_DIR),
+        }
+    }
+
+    fn entry_dir(&self, url: &Url) -> PathBuf {
+        let key = Sha256::digest(url.as_str().as_bytes());
+        self.root.join(format!("{key:x}"))
+    }
+
+    fn load_entry(dir: &Path) -> Option<CacheEntry> {
+        let mut entry = CacheEntry::load(dir.join(CacheEntry::FILENAME)).ok()?;
+        entry.dir = dir.to_path_buf();
+        entry.size = entry.path().metadata().map(|m| m.len()).unwrap_or_default();
+        Some(entry)
+    }
+
+    fn find(&self, url: &Url, expected_sha256: Option<&str>) -> Option<CacheEntry> {
+        let entry = Self::load_entry(&self.entry_dir(url))?;
+        let sha256 = entry.sha256.as_deref()?;
+        if expected_sha256.is_some_and(|expected| !expected.eq_ignore_ascii_case(sha256)) {
+            return None;
+        }
+        entry.path().is_file().then_some(entry)
+    }
+
+    /// Get the path to the cached package downloaded from `url`, and mark it as recently used.
+    ///
+    /// `None` is returned if it's not downloaded completely, or if its checksum
+    /// does not match the `expected_sha256`.
+    pub fn get(&self, url: &Url, expected_sha256: Option<&str>) -> Option<PathBuf> {
+        let mut entry = self.find(url, expected_sha256)?;
+        if let Err(e) = entry.touch() {
+            debug!("unable to update the cache entry of '{url}': {e}");
+        }
+        Some(entry.path())
+    }
+
+    /// Like [`get`](Self::get), but without changing anything in the cache.
+    pub fn peek(&self, url: &Url, expected_sha256: Option<&str>) -> Option<PathBuf> {
+        self.find(url, expected_sha256).map(|entry| entry.path())
+    }
+
+    /// Prepare the cache entry of the package from `url` to be downloaded,
+    /// and return the path of the partial file to download into,
+    /// which should be downloaded with resuming enabled, then [`finish`](Self::finish)ed.
+    ///
+    /// The previously downloaded content is kept if it's partial, or removed otherwise,
+    /// such as when it's outdated, so the package is downloaded again.
+    pub fn prepare(&self, url: &Url, filename: &str) -> Result<PathBuf> {
+        let dir = self.entry_dir(url);
+        let resumable = Self::load_entry(&dir)
+            .is_some_and(|entry| entry.is_partial() && entry.filename == filename);
+        if !resumable {
+            utils::remove(&dir)?;
+        }
+
+        let mut entry = CacheEntry {
+            url: url.clone(),
+            filename: filename.to_string(),
+            sha256: None,
+            last_used: Utc::now(),
+            size: 0,
+            dir,
+        };
+        utils::ensure_dir(&entry.dir)?;
+        entry.touch()?;
+        Ok(entry.path())
+    }
+
+    /// Finish downloading the package from `url` into its partial file,
+    /// and return the path to the package.
+    pub fn finish(&self, url: &Url) -> Result<PathBuf> {
+        let dir = self.entry_dir(url);
+        let mut entry = Self::load_entry(&dir)
+            .with_context(|| format!("no download of '{url}' was prepared in cache"))?;
+        let partial = entry.path();
+        let dest = dir.join(&entry.filename);
+        utils::move_to(&partial, &dest, true)?;
+
+        entry.sha256 = Some(utils::file_sha256(&dest)?);
+        entry.touch()?;
+        Ok(dest)
+    }
+
+    /// Return the cached packages, the most recently used first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        if !self.root.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut entries = utils::walk_dir(&self.root, false)?
+            .iter()
+            .filter_map(|dir| Self::load_entry(dir))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| Reverse(entry.last_used));
+        Ok(entries)
+    }
+
+    /// Return the total bytes taken by the cache.
+    pub fn size(&self) -> Result<u64> {
+        if !self.root.is_dir() {
+            return Ok(0);
+        }
+        let size = utils::walk_dir(&self.root, true)?
+            .iter()
+            .filter_map(|path| path.metadata().ok())
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len())
+            .sum();
+        Ok(size)
+    }
+
+    /// Remove everything in the cache, and return the number of bytes freed.
+    pub fn clean(&self) -> Result<u64> {
+        let size = self.size()?;
+        utils::remove(&self.root)?;
+        Ok(size)
+    }
+
+    /// Remove the least recently used packages until the size of cache is no more than `max_size`
+    /// bytes, and return the number of bytes freed.
+    ///
+    /// Anything in the cache directory that is not a valid entry is removed as well.
+    pub fn trim(&self, max_size: u64) -> Result<u64> {
+        let before = self.size()?;
+        let entries = self.entries()?;
+        for path in utils::walk_dir(&self.root, false).unwrap_or_default() {
+            if !entries.iter().any(|e| e.dir == path) {
+                utils::remove(&path)?;
+            }
+        }
+
+        let mut size = self.size()?;
+        // remove from the least recently used one
+        for entry in entries.iter().rev() {
+            if size <= max_size {
+                break;
+            }
+            let entry_size = utils::walk_dir(&entry.dir, true)?
+                .iter()
+                .filter_map(|path| path.metadata().ok())
+                .map(|meta| meta.len())
+                .sum::<u64>();
+            utils::remove(&entry.dir)?;
+            size = size.saturating_sub(entry_size);
+        }
+        Ok(before.saturating_sub(size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(cache: &DownloadCache, url: &Url, content: &str) -> PathBuf {
+        let partial = cache.prepare(url, "pkg.tar.gz").unwrap();
+        utils::write_bytes(&partial, content.as_bytes(), true).unwrap();
+        cache.finish(url).unwrap()
+    }
+
+    #[test]
+    fn reuse_cached_package() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::new(root.path());
+        let url = Url::parse("https://example.com/a/pkg.tar.gz").unwrap();
+        assert!(cache.get(&url, None).is_none());
+
+        // partially downloaded content is kept for resuming
+        let partial = cache.prepare(&url, "pkg.tar.gz").unwrap();
+        assert!(partial.ends_with("pkg.tar.gz.part"));
+        utils::write_bytes(&partial, b"a", false).unwrap();
+        assert!(cache.get(&url, None).is_none());
+        assert!(cache.entries().unwrap()[0].is_partial());
+        let path = download(&cache, &url, "bc");
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "abc");
+        assert_eq!(cache.peek(&url, None), Some(path.clone()));
+        assert_eq!(cache.get(&url, None), Some(path.clone()));
+        let checksum = utils::file_sha256(&path).unwrap();
+        assert_eq!(cache.get(&url, Some(&checksum.to_uppercase())), Some(path));
+        assert!(cache.get(&url, Some("abcd")).is_none());
+
+        // outdated package is downloaded again
+        let path = download(&cache, &url, "new");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "new");
+    }
+
+    #[test]
+    fn trim_least_recently_used() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = DownloadCache::new(root.path());
+        let old = Url::parse("https://example.com/old.tar.gz").unwrap();
+        let new = Url::parse("https://example.com/new.tar.gz").unwrap();
+        download(&cache, &old, &"a".repeat(1000));
+        download(&cache, &new, &"b".repeat(1000));
+        utils::ensure_dir(root.path().join(CACHE_DIR).join("garbage")).unwrap();
+
+        assert_eq!(cache.trim(u64::MAX).unwrap(), 0);
+        assert_eq!(cache.entries().unwrap().len(), 2);
+        assert!(!root.path().join(CACHE_DIR).join("garbage").exists());
+
+        assert!(cache.trim(1500).unwrap() > 1000);
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url, new);
+        assert_eq!(entries[0].size, 1000);
+
+        assert!(cache.clean().unwrap() > 1000);
+        assert_eq!(cache.size().unwrap(), 0);
+        assert!(cache.entries().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! Advisory lock over an installation directory, which prevents multiple operations
+//! (such as an update running in GUI and an uninstallation running in CLI)
+//! from modifying the same installation at the same time.
+//!
+//! The lock is an OS level file lock, which will be released automatically when the
+//! process exits, even if it was killed. Along with the lock file, the pid of the process
+//! holding the lock is written into a separated file, so that we can tell the user which
+//! process is blocking them, or detect a stale lock left by a process that did not exit normally.
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use fs2::FileExt;
+
+use crate::utils;
+
+const LOCK_FILENAME: &str = ".operation.lock";
+const PID_FILENAME: &str = ".operation.pid";
+/// How long to wait for another operation to finish before giving up.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A lock held by current process.
+struct HeldLock {
+    /// The thread that acquired this lock, which is allowed to acquire it again.
+    owner: ThreadId,
+    count: usize,
+    /// The locked file, the lock will be released once this is closed.
+    _file: File,
+}
+
+/// Locks that are held by current process.
+///
+/// File locks are associated with file handles instead of threads, meaning that
+/// the same process could acquire a second lock on a different handle and end up blocking itself.
+/// Therefore, we need to keep track of these locks within current process.
+fn held_locks() -> &'static Mutex<HashMap<PathBuf, HeldLock>> {
+    static HELD_LOCKS: OnceLock<Mutex<HashMap<PathBuf, HeldLock>>> = OnceLock::new();
+    HELD_LOCKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A guard of the exclusive lock over an installation directory,
+/// the lock is released once this guard is dropped.
+///
+/// The lock is re-entrant within the same thread, so an operation that holds the lock
+/// can call other operations that also acquire it.
+#[derive(Debug)]
+pub(crate) struct InstallDirLock {
+    root: PathBuf,
+}
+
+impl InstallDirLock {
+    /// Acquire the lock of an installation directory `root`.
+    ///
+    /// If the lock is held by another operation, this will wait for it
+    /// to be released until timed out.
+    ///
+    /// Note that the lock is held by the thread acquiring it, so an operation
+    /// should acquire it in the thread doing the work, and only after the user has
+    /// confirmed it, otherwise other operations will be blocked by the interaction.
+    ///
+    /// # Error
+    /// Return error if the lock cannot be acquired in time.
+    pub(crate) fn acquire(root: &Path) -> Result<Self> {
+        utils::ensure_dir(root)?;
+        // different spellings of the same directory should share the same lock
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("unable to resolve path '{}'", root.display()))?;
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        let mut notified = false;
+        loop {
+            let TryLock::Blocked(holder) = try_lock(&root)? else {
+                return Ok(Self { root });
+            };
+            let pid = holder.map_or_else(|| "?".to_string(), |pid| pid.to_string());
+            if Instant::now() >= deadline {
+                bail!("{}", t!("operation_in_progress", pid = pid));
+            }
+            if !notified {
+                info!("{}", t!("waiting_for_operation", pid = pid));
+                notified = true;
+            }
+            thread::sleep(RETRY_INTERVAL);
+        }
+    }
+}
+
+impl Drop for InstallDirLock {
+    fn drop(&mut self) {
+        let mut held = held_locks().lock().unwrap_or_else(|e| e.into_inner());
+        let Some(lock) = held.get_mut(&self.root) else {
+            return;
+        };
+        lock.count -= 1;
+        if lock.count == 0 {
+            // remove the pid file first, otherwise it will be considered as stale by the next holder.
+            _ = utils::remove(self.root.join(PID_FILENAME));
+            // closing the file releases the lock
+            held.remove(&self.root);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TryLock {
+    Acquired,
+    /// The lock is being held by another operation, containing the pid of its process if known.
+    Blocked(Option<u32>),
+}
+
+/// Try locking an installation directory without waiting.
+fn try_lock(root: &Path) -> Result<TryLock> {
+    let mut held = held_locks().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(lock) = held.get_mut(root) {
+        if lock.owner != thread::current().id() {
+            return Ok(TryLock::Blocked(Some(std::process::id())));
+        }
+        lock.count += 1;
+        return Ok(TryLock::Acquired);
+    }
+
+    let lock_path = root.join(LOCK_FILENAME);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .with_context(|| format!("unable to open lock file '{}'", lock_path.display()))?;
+    let pid_path = root.join(PID_FILENAME);
+    if let Err(e) = file.try_lock_exclusive() {
+        if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+            return Ok(TryLock::Blocked(read_pid(&pid_path)));
+        }
+        return Err(e).with_context(|| format!("unable to lock file '{}'", lock_path.display()));
+    }
+
+    // The pid file is always removed when the lock is released normally,
+    // if it still exists, the process holding it must have exited unexpectedly.
+    if let Some(pid) = read_pid(&pid_path) {
+        warn!("{}", t!("stale_lock_detected", pid = pid));
+    }
+    utils::write_file(&pid_path, &std::process::id().to_string(), false)?;
+
+    held.insert(
+        root.to_path_buf(),
+        HeldLock {
+            owner: thread::current().id(),
+            count: 1,
+            _file: file,
+        },
+    );
+    Ok(TryLock::Acquired)
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reentrant_lock() {
+        let root = tempfile::tempdir().unwrap();
+        let outer = InstallDirLock::acquire(root.path()).unwrap();
+        let inner = InstallDirLock::acquire(root.path()).unwrap();
+        let pid_file = root.path().join(PID_FILENAME);
+        assert_eq!(read_pid(&pid_file), Some(std::process::id()));
+
+        drop(inner);
+        assert!(pid_file.is_file());
+        drop(outer);
+        assert!(!pid_file.exists());
+    }
+
+    #[test]
+    fn same_lock_for_different_spellings() {
+        let root = tempfile::tempdir().unwrap();
+        let sub = root.path().join("sub");
+        let outer = InstallDirLock::acquire(&sub).unwrap();
+        let inner = InstallDirLock::acquire(&sub.join("..").join("sub")).unwrap();
+        let pid_file = sub.join(PID_FILENAME);
+
+        drop(inner);
+        assert!(pid_file.is_file());
+        drop(outer);
+        assert!(!pid_file.exists());
+    }
+
+    #[test]
+    fn lock_held_by_another_thread() {
+        let root = tempfile::tempdir().unwrap();
+        let _lock = InstallDirLock::acquire(root.path()).unwrap();
+
+        let path = root.path().to_path_buf();
+        let holder = thread::spawn(move || try_lock(&path).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(holder, TryLock::Blocked(Some(std::process::id())));
+    }
+
+    #[test]
+    fn stale_pid_file_is_replaced() {
+        let root = tempfile::tempdir().unwrap();
+        let pid_file = root.path().join(PID_FILENAME);
+        std::fs::write(&pid_file, "4294967295").unwrap();
+
+        let lock = InstallDirLock::acquire(root.path()).unwrap();
+        assert_eq!(read_pid(&pid_file), Some(std::process::id()));
+        drop(lock);
+        assert!(!pid_file.exists());
+    }
+}
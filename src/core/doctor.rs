@@ -0,0 +1,467 @@
+//! Diagnose common problems of the environment that an installation is used in,
+//! such as shell rc files that are not sourced, env vars pointing elsewhere,
+//! or another `cargo` that comes earlier in `PATH`.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::parser::fingerprint::InstallationRecord;
+use super::parser::TomlParser;
+use super::{ALL_VARS, CARGO_HOME, RUSTUP_DIST_SERVER, RUSTUP_HOME, RUSTUP_UPDATE_ROOT};
+use crate::utils;
+
+/// Commands that should be resolved to the ones in this installation.
+const RUST_COMMANDS: &[&str] = &["cargo", "rustc", "rustup"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    /// Something that might cause problems, or was done intentionally by the user.
+    Warning,
+    /// Something that will likely cause the installation to not work as intended.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Warning => t!("warning"),
+            Self::Error => t!("error"),
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Fixes that are safe to apply, as they only touch the files owned by this installation,
+/// or the configuration sections written by this program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Fix {
+    /// Rewrite the env vars and `PATH` in the configuration sections of shell rc files.
+    WriteShellConfig,
+    /// Update the root path in fingerprint file to the actual installation directory.
+    FingerprintRoot,
+}
+
+impl Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::WriteShellConfig => t!("doctor_fix_shell_config"),
+            Self::FingerprintRoot => t!("doctor_fix_fingerprint_root"),
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Finding {
+    pub(crate) severity: Severity,
+    pub(crate) message: String,
+    /// What the user could do to solve the problem.
+    pub(crate) suggestion: Option<String>,
+    /// The fix that can be applied automatically, if there is one.
+    pub(crate) fix: Option<Fix>,
+    /// Whether the [`fix`](Finding::fix) has been applied.
+    pub(crate) fixed: bool,
+}
+
+impl Finding {
+    fn new<S: Display>(severity: Severity, message: S) -> Self {
+        Self {
+            severity,
+            message: message.to_string(),
+            suggestion: None,
+            fix: None,
+            fixed: false,
+        }
+    }
+
+    fn suggest<S: Display>(mut self, suggestion: S) -> Self {
+        self.suggestion = Some(suggestion.to_string());
+        self
+    }
+
+    fn fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// Inspect the environment of the installation in `install_dir`.
+pub(crate) fn diagnose(install_dir: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    let record = check_fingerprint(install_dir, &mut findings);
+    let expected = expected_env_vars(install_dir, record.as_ref());
+
+    check_env_vars(&expected, &mut findings);
+    #[cfg(unix)]
+    check_shell_configs(install_dir, &expected, &mut findings);
+    check_path(install_dir, record.as_ref(), &mut findings);
+    check_leftovers(install_dir, &mut findings);
+
+    Ok(findings)
+}
+
+/// Apply a fix to the installation in `install_dir`.
+pub(crate) fn apply_fix(install_dir: &Path, fix: Fix) -> Result<()> {
+    match fix {
+        Fix::WriteShellConfig => {
+            #[cfg(unix)]
+            {
+                let record = InstallationRecord::load_from_dir(install_dir)?;
+                let vars = expected_env_vars(install_dir, Some(&record));
+                super::os::unix::write_env_config(install_dir, &vars)?;
+                super::os::add_to_path(&cargo_bin(install_dir))?;
+            }
+        }
+        Fix::FingerprintRoot => {
+            let mut record = InstallationRecord::load_from_dir(install_dir)?;
+            record.root = install_dir.to_path_buf();
+            record.write()?;
+        }
+    }
+    Ok(())
+}
+
+fn cargo_home(install_dir: &Path) -> PathBuf {
+    install_dir.join(".cargo")
+}
+
+fn cargo_bin(install_dir: &Path) -> PathBuf {
+    cargo_home(install_dir).join("bin")
+}
+
+fn rustup_home(install_dir: &Path) -> PathBuf {
+    install_dir.join(".rustup")
+}
+
+/// Check whether two paths point to the same location,
+/// fallback to compare them literally if either of them cannot be resolved.
+fn is_same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.components().eq(b.components()),
+    }
+}
+
+/// Compare the values of env vars, which could either be paths or urls.
+fn is_same_value(key: &str, a: &str, b: &str) -> bool {
+    if [CARGO_HOME, RUSTUP_HOME].contains(&key) {
+        is_same_path(Path::new(a), Path::new(b))
+    } else {
+        a.trim_end_matches('/') == b.trim_end_matches('/')
+    }
+}
+
+/// Return the env vars that should be set for this installation,
+/// the servers are only known if they were recorded in fingerprint.
+fn expected_env_vars(
+    install_dir: &Path,
+    record: Option<&InstallationRecord>,
+) -> HashMap<&'static str, String> {
+    let mut vars = HashMap::from([
+        (CARGO_HOME, cargo_home(install_dir).display().to_string()),
+        (RUSTUP_HOME, rustup_home(install_dir).display().to_string()),
+    ]);
+    if let Some(settings) = record.and_then(|r| r.settings.as_ref()) {
+        vars.insert(RUSTUP_DIST_SERVER, settings.rustup_dist_server.to_string());
+        vars.insert(RUSTUP_UPDATE_ROOT, settings.rustup_update_root.to_string());
+    }
+    vars
+}
+
+/// Suggestion to reload the environment, such as sourcing the rc file.
+fn suggest_reload() -> String {
+    #[cfg(unix)]
+    if let Some(cmd) = super::os::unix::source_command() {
+        return t!("doctor_suggest_source", cmd = cmd).to_string();
+    }
+    t!("doctor_suggest_restart_terminal").to_string()
+}
+
+/// Make sure the fingerprint exists and belongs to this installation, return the loaded record.
+fn check_fingerprint(
+    install_dir: &Path,
+    findings: &mut Vec<Finding>,
+) -> Option<InstallationRecord> {
+    let path = install_dir.join(InstallationRecord::FILENAME);
+    if !path.is_file() {
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                t!("doctor_fingerprint_missing", path = path.display()),
+            )
+            .suggest(t!("doctor_suggest_reinstall")),
+        );
+        return None;
+    }
+    let record = match utils::read_to_string("installation fingerprint", &path)
+        .and_then(|raw| InstallationRecord::from_str(&raw))
+    {
+        Ok(record) => record,
+        Err(e) => {
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    t!(
+                        "doctor_fingerprint_invalid",
+                        path = path.display(),
+                        reason = e
+                    ),
+                )
+                .suggest(t!("doctor_suggest_reinstall")),
+            );
+            return None;
+        }
+    };
+
+    if !is_same_path(&record.root, install_dir) {
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                t!(
+                    "doctor_fingerprint_root_mismatch",
+                    recorded = record.root.display(),
+                    actual = install_dir.display()
+                ),
+            )
+            .fix(Fix::FingerprintRoot),
+        );
+    }
+
+    if let Some((version, _)) = record.installed_toolchain() {
+        let has_toolchain = rustup_home(install_dir)
+            .join("toolchains")
+            .read_dir()
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or_default();
+        if !has_toolchain {
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    t!("doctor_toolchain_missing", version = version),
+                )
+                .suggest(t!("doctor_suggest_repair")),
+            );
+        }
+    }
+    Some(record)
+}
+
+/// Check the env vars from `declare_env_vars!` of current process.
+fn check_env_vars(expected: &HashMap<&'static str, String>, findings: &mut Vec<Finding>) {
+    for key in ALL_VARS {
+        let Some(expected_val) = expected.get(key) else {
+            continue;
+        };
+        match std::env::var(key) {
+            Err(_) => findings.push(
+                Finding::new(Severity::Warning, t!("doctor_env_var_unset", key = key))
+                    .suggest(suggest_reload()),
+            ),
+            Ok(val) if !is_same_value(key, &val, expected_val) => findings.push(
+                Finding::new(
+                    Severity::Error,
+                    t!(
+                        "doctor_env_var_mismatch",
+                        key = key,
+                        value = val,
+                        expected = expected_val
+                    ),
+                )
+                .suggest(t!("doctor_suggest_env_var", key = key)),
+            ),
+            _ => (),
+        }
+    }
+}
+
+/// Check the configuration sections written to shell rc files.
+#[cfg(unix)]
+fn check_shell_configs(
+    install_dir: &Path,
+    expected: &HashMap<&'static str, String>,
+    findings: &mut Vec<Finding>,
+) {
+    let bin_dir = cargo_bin(install_dir);
+    for section in super::os::unix::inspect_config_sections() {
+        let rc = section.rc.display();
+        let Some(vars) = &section.vars else {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    t!("doctor_rc_section_missing", shell = section.shell, rc = rc),
+                )
+                .fix(Fix::WriteShellConfig),
+            );
+            continue;
+        };
+        for key in ALL_VARS {
+            let Some(expected_val) = expected.get(key) else {
+                continue;
+            };
+            if !matches!(vars.get(*key), Some(val) if is_same_value(key, val, expected_val)) {
+                findings.push(
+                    Finding::new(
+                        Severity::Warning,
+                        t!("doctor_rc_var_mismatch", key = key, rc = rc),
+                    )
+                    .fix(Fix::WriteShellConfig),
+                );
+            }
+        }
+        if !section.paths.iter().any(|p| is_same_path(p, &bin_dir)) {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    t!("doctor_rc_path_missing", path = bin_dir.display(), rc = rc),
+                )
+                .fix(Fix::WriteShellConfig),
+            );
+        }
+    }
+}
+
+/// Check whether the commands in `PATH` are resolved to the ones of this installation.
+fn check_path(
+    install_dir: &Path,
+    record: Option<&InstallationRecord>,
+    findings: &mut Vec<Finding>,
+) {
+    let bin_dir = cargo_bin(install_dir);
+    let path_var = std::env::var_os("PATH").unwrap_or_default();
+    if !std::env::split_paths(&path_var).any(|p| is_same_path(&p, &bin_dir)) {
+        findings.push(
+            Finding::new(
+                Severity::Warning,
+                t!("doctor_path_missing", path = bin_dir.display()),
+            )
+            .suggest(suggest_reload()),
+        );
+    }
+
+    let has_toolchain = record.is_some_and(|r| r.installed_toolchain().is_some());
+    for cmd in RUST_COMMANDS {
+        let expected = bin_dir.join(format!("{cmd}{}", std::env::consts::EXE_SUFFIX));
+        if !expected.is_file() {
+            if has_toolchain {
+                findings.push(
+                    Finding::new(
+                        Severity::Error,
+                        t!("doctor_cmd_missing", cmd = cmd, path = expected.display()),
+                    )
+                    .suggest(t!("doctor_suggest_repair")),
+                );
+            }
+            continue;
+        }
+        let Some(found) = utils::find_in_path(cmd) else {
+            continue;
+        };
+        if !is_same_path(&found, &expected) {
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    t!(
+                        "doctor_cmd_shadowed",
+                        cmd = cmd,
+                        found = found.display(),
+                        expected = expected.display()
+                    ),
+                )
+                .suggest(t!("doctor_suggest_path_order", path = bin_dir.display())),
+            );
+        }
+    }
+}
+
+/// Check for a rustup installation in the default location, which is not this one.
+fn check_leftovers(install_dir: &Path, findings: &mut Vec<Finding>) {
+    let home = utils::home_dir();
+    let default_rustup = home
+        .join(".cargo")
+        .join("bin")
+        .join(format!("rustup{}", std::env::consts::EXE_SUFFIX));
+    let leftovers = [home.join(".rustup"), default_rustup];
+    let ours = [rustup_home(install_dir), cargo_bin(install_dir)];
+    for (leftover, ours) in leftovers.iter().zip(&ours) {
+        let Some(parent) = leftover.parent() else {
+            continue;
+        };
+        if leftover.exists() && !is_same_path(leftover, ours) && !is_same_path(parent, ours) {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    t!("doctor_leftover_rustup", path = leftover.display()),
+                )
+                .suggest(t!("doctor_suggest_remove_leftover")),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_env_values() {
+        assert!(is_same_value(
+            RUSTUP_DIST_SERVER,
+            "https://example.com/",
+            "https://example.com"
+        ));
+        assert!(!is_same_value(
+            RUSTUP_DIST_SERVER,
+            "https://example.com",
+            "https://example.org"
+        ));
+        assert!(is_same_value(
+            CARGO_HOME,
+            "/path/to/rust/.cargo",
+            "/path/to/rust/./.cargo"
+        ));
+        assert!(!is_same_value(
+            CARGO_HOME,
+            "/path/to/rust/.cargo",
+            "/path/to/other/.cargo"
+        ));
+    }
+
+    #[test]
+    fn fingerprint_root_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = InstallationRecord {
+            root: PathBuf::from("/somewhere/else"),
+            ..Default::default()
+        };
+        utils::write_file(
+            dir.path().join(InstallationRecord::FILENAME),
+            &record.to_toml().unwrap(),
+            false,
+        )
+        .unwrap();
+
+        let mut findings = vec![];
+        check_fingerprint(dir.path(), &mut findings).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].fix, Some(Fix::FingerprintRoot));
+
+        apply_fix(dir.path(), Fix::FingerprintRoot).unwrap();
+        findings.clear();
+        check_fingerprint(dir.path(), &mut findings).unwrap();
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn missing_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut findings = vec![];
+        assert!(check_fingerprint(dir.path(), &mut findings).is_none());
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+}
@@ -0,0 +1,221 @@
+//! Categories of the errors, so that a failure can be told apart by scripts
+//! with the exit code of this program, or the error id in JSON output.
+//!
+//! Errors are still passed around as [`anyhow::Error`], a category is attached to an error
+//! with [`ResultExt::with_kind`] at where it happens, without changing its message.
+//! Some categories are detected from the underlying errors as well, such as
+//! [`io::ErrorKind::PermissionDenied`].
+
+use std::{error::Error, fmt::Display, io};
+
+use serde::Serialize;
+
+/// The category of an error, each of them has a stable [exit code](ErrorKind::exit_code)
+/// and [id](ErrorKind::id).
+///
+/// | id                 | exit code |
+/// |--------------------|-----------|
+/// | `other`            | 1         |
+/// | `download`         | 10        |
+/// | `extraction`       | 11        |
+/// | `rustup`           | 12        |
+/// | `cargo-install`    | 13        |
+/// | `env-modification` | 14        |
+/// | `manifest-parse`   | 15        |
+/// | `permission`       | 16        |
+/// | `cancelled`        | 17        |
+///
+/// Note that exit code `2` is used when the command line arguments are invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// Failed to download a file or to fetch something from server.
+    Download,
+    /// Failed to extract an archive.
+    Extraction,
+    /// `rustup` failed to install, update or remove the toolchain.
+    Rustup,
+    /// `cargo install` or `cargo uninstall` failed.
+    CargoInstall,
+    /// Failed to modify env vars or `PATH`, including writing shell rc files.
+    EnvModification,
+    /// A manifest or other configuration file is not valid.
+    ManifestParse,
+    /// Permission denied when accessing files.
+    Permission,
+    /// The operation was cancelled by the user.
+    Cancelled,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorKind {
+    /// Return the category of an error.
+    ///
+    /// Cancellations and permission problems take precedence,
+    /// as they are usually what the user needs to know the most.
+    pub fn of(error: &anyhow::Error) -> Self {
+        let attached = error
+            .chain()
+            .find_map(|e| e.downcast_ref::<KindError>())
+            .map(|e| e.kind);
+        if attached == Some(Self::Cancelled) {
+            return Self::Cancelled;
+        }
+
+        let is_permission_denied = original_chain(error)
+            .filter_map(|e| e.downcast_ref::<io::Error>())
+            .any(|e| e.kind() == io::ErrorKind::PermissionDenied);
+        if is_permission_denied {
+            return Self::Permission;
+        }
+
+        attached
+            .or_else(|| original_chain(error).find_map(Self::detect))
+            .unwrap_or(Self::Other)
+    }
+
+    /// Detect the category from the type of an underlying error.
+    fn detect(error: &(dyn Error + 'static)) -> Option<Self> {
+        if error.is::<reqwest::Error>() {
+            Some(Self::Download)
+        } else if error.is::<toml::de::Error>() {
+            Some(Self::ManifestParse)
+        } else {
+            None
+        }
+    }
+
+    /// The machine-readable id of this category, which is the same as its serialized value.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Extraction => "extraction",
+            Self::Rustup => "rustup",
+            Self::CargoInstall => "cargo-install",
+            Self::EnvModification => "env-modification",
+            Self::ManifestParse => "manifest-parse",
+            Self::Permission => "permission",
+            Self::Cancelled => "cancelled",
+            Self::Other => "other",
+        }
+    }
+
+    /// The exit code of this program when failed with this category of error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::Download => 10,
+            Self::Extraction => 11,
+            Self::Rustup => 12,
+            Self::CargoInstall => 13,
+            Self::EnvModification => 14,
+            Self::ManifestParse => 15,
+            Self::Permission => 16,
+            Self::Cancelled => 17,
+        }
+    }
+
+    /// Create an error of this category with a message.
+    pub fn error<M: Display + Send + Sync + 'static>(self, msg: M) -> anyhow::Error {
+        self.attach(anyhow::Error::msg(msg.to_string()))
+    }
+
+    /// Attach this category to an error, unless it already has one,
+    /// because the category attached closer to where the error happens is more accurate,
+    /// such as a download error that happens when installing toolchain.
+    fn attach(self, error: anyhow::Error) -> anyhow::Error {
+        if error.chain().any(|e| e.is::<KindError>()) {
+            return error;
+        }
+        anyhow::Error::new(KindError { kind: self, error })
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// An error with a category attached, which is displayed exactly like the original one,
+/// and has the same sources as well.
+#[derive(Debug)]
+struct KindError {
+    kind: ErrorKind,
+    error: anyhow::Error,
+}
+
+impl Display for KindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // not forwarding the formatter, so the sources are not printed with `{:#}`
+        write!(f, "{}", self.error)
+    }
+}
+
+impl Error for KindError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.chain().nth(1)
+    }
+}
+
+/// Iterate over the chain of `error` like [`anyhow::Error::chain`],
+/// but with the attached category replaced by the error it was attached to.
+fn original_chain(error: &anyhow::Error) -> impl Iterator<Item = &(dyn Error + 'static)> {
+    error.chain().map(|e| match e.downcast_ref::<KindError>() {
+        Some(attached) => &*attached.error,
+        None => e,
+    })
+}
+
+/// Extension methods to attach an [`ErrorKind`] to the error of a [`Result`].
+pub trait ResultExt<T> {
+    fn with_kind(self, kind: ErrorKind) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> ResultExt<T> for Result<T, E> {
+    fn with_kind(self, kind: ErrorKind) -> anyhow::Result<T> {
+        self.map_err(|e| kind.attach(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+
+    #[test]
+    fn keep_error_message() {
+        let res: anyhow::Result<()> = Err(anyhow!("root cause"))
+            .context("outer")
+            .with_kind(ErrorKind::Rustup);
+        let err = res.context("outermost").unwrap_err();
+
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Rustup);
+        let chain = err.chain().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(chain, ["outermost", "outer", "root cause"]);
+    }
+
+    #[test]
+    fn innermost_kind_wins() {
+        let res: anyhow::Result<()> =
+            Err(ErrorKind::Download.error("no network")).with_kind(ErrorKind::Rustup);
+        assert_eq!(ErrorKind::of(&res.unwrap_err()), ErrorKind::Download);
+    }
+
+    #[test]
+    fn detect_kind_from_source() {
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let res: anyhow::Result<()> = Err(denied).with_kind(ErrorKind::Extraction);
+        assert_eq!(ErrorKind::of(&res.unwrap_err()), ErrorKind::Permission);
+
+        let parse_err = toml::from_str::<toml::Table>("a = ").unwrap_err();
+        let err = anyhow::Error::new(parse_err).context("invalid manifest");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::ManifestParse);
+
+        assert_eq!(ErrorKind::of(&anyhow!("unknown")), ErrorKind::Other);
+        let cancelled = ErrorKind::Cancelled.error("cancelled");
+        assert_eq!(ErrorKind::of(&cancelled), ErrorKind::Cancelled);
+        assert_eq!(ErrorKind::Cancelled.exit_code(), 17);
+    }
+}
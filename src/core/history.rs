@@ -0,0 +1,184 @@
+//! Append-only history of the changes made to an installation.
+//!
+//! Each line of the history file is a JSON object representing a [`HistoryEntry`],
+//! new entries are always appended to the end of the file, existing entries are never modified.
+
+use std::{fmt::Display, path::Path};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{setter, utils};
+
+pub(crate) const HISTORY_FILENAME: &str = "history.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Install,
+    Update,
+    Uninstall,
+    ComponentAdd,
+    ComponentRemove,
+    SelfUpdate,
+    Repair,
+    Rollback,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Install => "install",
+            Self::Update => "update",
+            Self::Uninstall => "uninstall",
+            Self::ComponentAdd => "component-add",
+            Self::ComponentRemove => "component-remove",
+            Self::SelfUpdate => "self-update",
+            Self::Repair => "repair",
+            Self::Rollback => "rollback",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Success => t!("success"),
+            Self::Failure => t!("failure"),
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A single operation made to an installation.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub action: Action,
+    /// The version before this operation, such as the toolkit version or the manager version.
+    pub from: Option<String>,
+    /// The version after this operation.
+    pub to: Option<String>,
+    /// The URL of the toolkit manifest used by this operation.
+    pub manifest_url: Option<String>,
+    /// The components affected by this operation, if the operation is not done to the whole toolkit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<String>,
+    pub outcome: Outcome,
+    /// The error message if the operation failed.
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub(crate) fn new(action: Action) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            action,
+            from: None,
+            to: None,
+            manifest_url: None,
+            components: vec![],
+            outcome: Outcome::Success,
+            error: None,
+        }
+    }
+
+    setter!(from_version(self.from, Option<String>));
+    setter!(to_version(self.to, Option<String>));
+    setter!(manifest_url(self.manifest_url, Option<String>));
+    setter!(components(self.components, Vec<String>));
+
+    /// Set the outcome of this entry based on the result of the operation.
+    pub(crate) fn with_result<T>(mut self, result: &Result<T>) -> Self {
+        if let Err(e) = result {
+            self.outcome = Outcome::Failure;
+            self.error = Some(format!("{e:#}"));
+        } else {
+            self.outcome = Outcome::Success;
+            self.error = None;
+        }
+        self
+    }
+
+    /// Append this entry to the history file under `root`.
+    fn append_to(&self, root: &Path) -> Result<()> {
+        let line = serde_json::to_string(self).context("unable to serialize history entry")?;
+        utils::write_file(root.join(HISTORY_FILENAME), &line, true)
+    }
+}
+
+/// Record an entry into the history of the installation in `root`.
+///
+/// Failing to record the history should not affect the operation itself,
+/// therefore only a warning will be shown on error.
+pub(crate) fn record(root: &Path, entry: HistoryEntry) {
+    if let Err(e) = entry.append_to(root) {
+        warn!("{}: {e}", t!("record_history_failed"));
+    }
+}
+
+/// Load every history entry of the installation in `root`, from the oldest to the newest.
+pub fn load_history(root: &Path) -> Result<Vec<HistoryEntry>> {
+    let path = root.join(HISTORY_FILENAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    let content = utils::read_to_string("history", &path)?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(idx, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "invalid history entry at line {} of '{}'",
+                    idx + 1,
+                    path.display()
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_load_history() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(load_history(root.path()).unwrap().is_empty());
+
+        let install = HistoryEntry::new(Action::Install)
+            .to_version(Some("1.0.0".into()))
+            .manifest_url(Some("https://example.com/toolset-manifest.toml".into()))
+            .with_result(&Ok(()));
+        record(root.path(), install);
+        let failed: Result<()> = Err(anyhow::anyhow!("network unreachable"));
+        let update = HistoryEntry::new(Action::Update)
+            .from_version(Some("1.0.0".into()))
+            .to_version(Some("1.1.0".into()))
+            .with_result(&failed);
+        record(root.path(), update);
+
+        let history = load_history(root.path()).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].action, Action::Install);
+        assert_eq!(history[0].outcome, Outcome::Success);
+        assert_eq!(history[0].from, None);
+        assert_eq!(history[0].to.as_deref(), Some("1.0.0"));
+        assert_eq!(history[1].action, Action::Update);
+        assert_eq!(history[1].outcome, Outcome::Failure);
+        assert_eq!(history[1].error.as_deref(), Some("network unreachable"));
+        assert!(history[0].timestamp <= history[1].timestamp);
+    }
+}
@@ -1,21 +1,33 @@
 use super::{
+    cache::DownloadCache,
     components::{component_list_to_tool_map, Component, ComponentType},
+    dir_lock::InstallDirLock,
     directories::RimDir,
+    error::{ErrorKind, ResultExt},
+    history::{self, Action, HistoryEntry},
+    journal::{Journal, Operation, Step},
+    lock::LockFile,
     parser::{
         cargo_config::CargoConfig,
-        fingerprint::{InstallationRecord, ToolRecord},
+        configuration::DownloadConf,
+        fingerprint::{CargoRegistry, InstallSettings, InstallationRecord, ToolRecord},
+        installations::InstallationIndex,
         toolset_manifest::{ToolInfo, ToolsetManifest},
         TomlParser,
     },
-    rustup::ToolchainInstaller,
-    tools::Tool,
-    CARGO_HOME, RUSTUP_DIST_SERVER, RUSTUP_HOME, RUSTUP_UPDATE_ROOT,
+    plan::{ChangeKind, DiskSpace, Plan},
+    repair::{RepairPlan, ToolchainRepair},
+    rollback::{self, RollbackPlan, Snapshot},
+    rustup::{rustup_init_url, ToolchainInstaller, RUSTUP},
+    tools::{Tool, ToolKind},
+    uninstall::UninstallConfiguration,
+    GlobalOpts, CARGO_HOME, RUSTUP_DIST_SERVER, RUSTUP_HOME, RUSTUP_UPDATE_ROOT,
 };
 use crate::{
     core::os::add_to_path,
     setter,
     toolset_manifest::{ToolMap, ToolchainComponent},
-    utils::{self, Extractable, Progress},
+    utils::{self, DownloadTask, Extractable, Progress},
 };
 use anyhow::{anyhow, bail, Context, Result};
 use std::{
@@ -78,6 +90,14 @@ pub struct InstallConfiguration<'a> {
     pub(crate) progress_indicator: Option<Progress<'a>>,
     manifest: &'a ToolsetManifest,
     insecure: bool,
+    /// Records the completed steps of current operation, so it can be resumed or rolled back.
+    journal: Option<Journal>,
+    /// The URL of the toolkit manifest, which is recorded into the installation history.
+    manifest_url: Option<Url>,
+    /// The lockfile to reproduce, packages that does not match the lockfile will not be installed.
+    lock: Option<&'a LockFile>,
+    /// Whether to keep the current toolkit as the one to roll back to when switching toolkits.
+    save_snapshot: bool,
 }
 
 impl RimDir for InstallConfiguration<'_> {
@@ -88,10 +108,54 @@ impl RimDir for InstallConfiguration<'_> {
 
 impl<'a> InstallConfiguration<'a> {
     pub fn new(install_dir: &'a Path, manifest: &'a ToolsetManifest) -> Result<Self> {
-        Ok(Self {
+        // Note: `InstallationRecord::load_from_dir` creates `install_dir` if it does not exist
+        let install_record = InstallationRecord::load_from_dir(install_dir)?;
+        Ok(Self::with_record(install_dir, manifest, install_record))
+    }
+
+    /// Create a configuration to roll back to the toolkit in `snapshot`.
+    ///
+    /// The servers and registry recorded in the snapshot are used instead of the default ones,
+    /// so that an installation using a mirror keeps using it after rolling back.
+    pub fn for_snapshot(install_dir: &'a Path, snapshot: &'a Snapshot) -> Result<Self> {
+        let mut config = Self::new(install_dir, &snapshot.manifest)?;
+        if let Some(settings) = &snapshot.record.settings {
+            config = config
+                .with_rustup_dist_server(settings.rustup_dist_server.clone())
+                .with_rustup_update_root(settings.rustup_update_root.clone());
+            if let Some(registry) = &settings.cargo_registry {
+                config = config.with_cargo_registry(&registry.name, &registry.url);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Similar to [`new`](InstallConfiguration::new), but nothing will be created in `install_dir`,
+    /// which is meant to [`plan`](InstallConfiguration::plan) an operation only.
+    pub(crate) fn for_plan(install_dir: &'a Path, manifest: &'a ToolsetManifest) -> Result<Self> {
+        let fp_path = install_dir.join(InstallationRecord::FILENAME);
+        let install_record = if fp_path.is_file() {
+            InstallationRecord::from_str(&utils::read_to_string(
+                "installation fingerprint",
+                &fp_path,
+            )?)?
+        } else {
+            InstallationRecord {
+                root: install_dir.to_path_buf(),
+                ..Default::default()
+            }
+        };
+        Ok(Self::with_record(install_dir, manifest, install_record))
+    }
+
+    fn with_record(
+        install_dir: &'a Path,
+        manifest: &'a ToolsetManifest,
+        install_record: InstallationRecord,
+    ) -> Self {
+        Self {
             install_dir: install_dir.to_path_buf(),
-            // Note: `InstallationRecord::load_from_dir` creates `install_dir` if it does not exist
-            install_record: InstallationRecord::load_from_dir(install_dir)?,
+            install_record,
             cargo_registry: None,
             rustup_dist_server: default_rustup_dist_server().clone(),
             rustup_update_root: default_rustup_update_root().clone(),
@@ -99,7 +163,11 @@ impl<'a> InstallConfiguration<'a> {
             progress_indicator: None,
             manifest,
             insecure: false,
-        })
+            journal: None,
+            manifest_url: None,
+            lock: None,
+            save_snapshot: true,
+        }
     }
     /// Creating install diretory and other preperations related to filesystem.
     ///
@@ -134,16 +202,79 @@ impl<'a> InstallConfiguration<'a> {
     }
 
     pub fn install(mut self, components: Vec<Component>) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = self.history_entry(Action::Install);
+        let result = self.install_(components);
+        history::record(&self.install_dir, entry.with_result(&result));
+        result?;
+
+        // Do NOT fail installation if this installation cannot be added to the index
+        if let Err(e) = InstallationIndex::register(&self.install_dir) {
+            warn!("{}: {e}", t!("register_installation_failed"));
+        }
+        Ok(())
+    }
+
+    fn install_(&mut self, components: Vec<Component>) -> Result<()> {
         let (tc_components, tools) = split_components(components);
 
-        self.setup()?;
-        self.config_env_vars()?;
-        self.config_cargo()?;
+        self.journal = Some(Journal::begin(
+            &self.install_dir,
+            Operation::Install,
+            Some(Journal::target_of(self.manifest)),
+        )?);
+        self.install_record.settings = Some(self.settings());
+        // The env vars are needed by the following steps even if the installation is resumed,
+        // (where `config_env_vars` might be skipped), so set them for current process first.
+        for (key, val) in self.env_vars()? {
+            std::env::set_var(key, val);
+        }
+
+        self.run_step(Step::Setup, Self::setup)?;
+        self.run_step(Step::ConfigEnvVars, |c| {
+            c.config_env_vars().with_kind(ErrorKind::EnvModification)
+        })?;
+        self.run_step(Step::ConfigCargo, |c| c.config_cargo())?;
         // This step taking cares of requirements, such as `MSVC`, also third-party app such as `VS Code`.
         self.install_tools(&tools)?;
-        self.install_rust(&tc_components)?;
+        self.run_step(Step::InstallRust, |c| c.install_rust(&tc_components))?;
+        self.cargo_is_installed = true;
         // install third-party tools via cargo that got installed by rustup
         self.cargo_install(&tools)?;
+
+        self.finish_journal()
+    }
+
+    /// Create a history entry of an operation that changes the toolkit
+    /// from the installed version to the version in current manifest.
+    fn history_entry(&self, action: Action) -> HistoryEntry {
+        HistoryEntry::new(action)
+            .from_version(self.install_record.version.clone())
+            .to_version(self.manifest.version.clone())
+            .manifest_url(self.manifest_url.as_ref().map(ToString::to_string))
+    }
+
+    /// Run a step of current operation, unless it was already done in a previous (interrupted) run,
+    /// then mark it as completed in the journal.
+    fn run_step<F>(&mut self, step: Step, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        if self.journal.as_ref().is_some_and(|j| j.is_done(&step)) {
+            info!("{}", t!("step_skipped", step = step));
+            return Ok(());
+        }
+        f(self)?;
+        if let Some(journal) = &mut self.journal {
+            journal.record(step)?;
+        }
+        Ok(())
+    }
+
+    fn finish_journal(&mut self) -> Result<()> {
+        if let Some(journal) = self.journal.take() {
+            journal.finish()?;
+        }
         Ok(())
     }
 
@@ -163,16 +294,49 @@ impl<'a> InstallConfiguration<'a> {
     setter!(with_rustup_update_root(self.rustup_update_root, Url));
     setter!(with_progress_indicator(self.progress_indicator, Option<Progress<'a>>));
     setter!(insecure(self.insecure, bool));
+    setter!(with_manifest_url(self.manifest_url, Option<Url>));
+    setter!(with_lock(self.lock, Option<&'a LockFile>));
+
+    /// Do not replace the snapshot of previous toolkit when switching toolkits,
+    /// which is used when rolling back an unfinished operation, where the current
+    /// installation is incomplete and not worth rolling back to.
+    pub(crate) fn keep_snapshot(mut self) -> Self {
+        self.save_snapshot = false;
+        self
+    }
+
+    /// Get the servers and registry settings of this installation.
+    pub(crate) fn settings(&self) -> InstallSettings {
+        InstallSettings {
+            rustup_dist_server: self.rustup_dist_server.clone(),
+            rustup_update_root: self.rustup_update_root.clone(),
+            cargo_registry: self
+                .cargo_registry
+                .as_ref()
+                .map(|(name, url)| CargoRegistry {
+                    name: name.clone(),
+                    url: url.clone(),
+                }),
+            proxy: self.manifest.proxy.clone(),
+        }
+    }
 
     pub(crate) fn env_vars(&self) -> Result<HashMap<&'static str, String>> {
-        let cargo_home = self
-            .cargo_home()
+        self.env_vars_of(self.cargo_home(), self.rustup_home())
+    }
+
+    fn env_vars_of(
+        &self,
+        cargo_home: &Path,
+        rustup_home: &Path,
+    ) -> Result<HashMap<&'static str, String>> {
+        let cargo_home = cargo_home
             .to_str()
             .map(ToOwned::to_owned)
             .context("`install-dir` cannot contains invalid unicodes")?;
         // This `unwrap` is safe here because we've already make sure the `install_dir`'s path can be
         // converted to string with the `cargo_home` variable.
-        let rustup_home = self.rustup_home().to_str().unwrap().to_string();
+        let rustup_home = rustup_home.to_str().unwrap().to_string();
 
         let mut env_vars = HashMap::from([
             (RUSTUP_DIST_SERVER, self.rustup_dist_server.to_string()),
@@ -197,6 +361,20 @@ impl<'a> InstallConfiguration<'a> {
         Ok(env_vars)
     }
 
+    /// Paths that are added to `PATH` by this installation, in the order of being added.
+    pub(crate) fn path_entries(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.install_dir.clone(), self.cargo_bin().to_path_buf()];
+        let tool_bins = self
+            .install_record
+            .tools
+            .values()
+            .filter(|tool| tool.tool_kind() == ToolKind::DirWithBin)
+            .filter_map(|tool| tool.paths.first())
+            .map(|dir| dir.join("bin"));
+        paths.extend(tool_bins);
+        paths
+    }
+
     fn install_tools_(&mut self, use_cargo: bool, tools: &ToolMap, weight: f32) -> Result<()> {
         let to_install = tools
             .iter()
@@ -214,21 +392,50 @@ impl<'a> InstallConfiguration<'a> {
         }
         let sub_progress_delta = weight / to_install.len() as f32;
 
-        for (name, tool) in to_install {
-            let info = if use_cargo {
-                t!("installing_via_cargo_info", name = name)
-            } else {
-                t!("installing_tool_info", name = name)
-            };
-            info!("{info}");
+        let steps = to_install
+            .into_iter()
+            .map(|(name, tool)| {
+                let step = match self.journal.as_ref().map(|j| j.operation) {
+                    Some(Operation::Update) => Step::UpdateTool {
+                        name: name.to_string(),
+                    },
+                    _ => Step::InstallTool {
+                        name: name.to_string(),
+                    },
+                };
+                (step, name, tool)
+            })
+            .collect::<Vec<_>>();
 
-            self.install_tool(name, tool)?;
+        // download the packages of the tools that are not installed yet, all at once
+        let to_download = steps
+            .iter()
+            .filter(|(step, ..)| !self.journal.as_ref().is_some_and(|j| j.is_done(step)))
+            .map(|(_, name, tool)| (*name, *tool))
+            .collect::<Vec<_>>();
+        let downloaded = self.prefetch_packages(&to_download)?;
+
+        for (step, name, tool) in steps {
+            self.run_step(step, |c| {
+                let info = if use_cargo {
+                    t!("installing_via_cargo_info", name = name)
+                } else {
+                    t!("installing_tool_info", name = name)
+                };
+                info!("{info}");
+
+                c.install_tool(name, tool, downloaded.get(name))?;
+                // write the record of each tool, so it can be uninstalled when rolling back
+                c.install_record.write()
+            })?;
 
             self.inc_progress(sub_progress_delta)?;
         }
 
-        self.install_record.write()?;
-
+        // the packages in cache are no longer needed by now
+        if !downloaded.is_empty() {
+            self.trim_download_cache();
+        }
         Ok(())
     }
 
@@ -266,58 +473,96 @@ impl<'a> InstallConfiguration<'a> {
         self.inc_progress(30.0)
     }
 
+    /// Get the packages of the `tools` that are from urls, which are reused from
+    /// the [`DownloadCache`] if possible, otherwise downloaded into it concurrently,
+    /// the number of concurrent downloads is limited by [`DownloadConf::concurrency`].
+    ///
+    /// Return the paths of the packages by tool names.
+    fn prefetch_packages(&self, tools: &[(&str, &ToolInfo)]) -> Result<HashMap<String, PathBuf>> {
+        let cache = DownloadCache::new(&self.install_dir);
+        let mut packages = HashMap::new();
+        let mut tasks = vec![];
+        for (name, tool) in tools {
+            let ToolInfo::Url { url, filename, .. } = tool else {
+                continue;
+            };
+            let expected_checksum = self.lock.and_then(|lock| lock.checksum_of(name));
+            if let Some(path) = cache.get(url, expected_checksum) {
+                info!("{}", t!("package_cached", name = name));
+                packages.insert(name.to_string(), path);
+                continue;
+            }
+            tasks.push(DownloadTask {
+                name: name.to_string(),
+                url: url.clone(),
+                dest: cache.prepare(url, package_filename(url, filename.as_deref())?)?,
+            });
+        }
+        if tasks.is_empty() {
+            return Ok(packages);
+        }
+
+        let limit = DownloadConf::load(&self.install_dir).concurrency();
+        let proxy = self.manifest.proxy.clone();
+        let lock = self.lock;
+        utils::blocking!(utils::download_all(
+            tasks,
+            limit,
+            |task| {
+                let checksum = lock.and_then(|lock| lock.checksum_of(&task.name));
+                utils::DownloadOpt::new(&task.name)
+                    .with_proxy(proxy.clone())
+                    .with_sha256(checksum.map(ToString::to_string))
+                    .resume(true)
+            },
+            |task| {
+                let path = cache.finish(&task.url)?;
+                info!("{}", t!("package_downloaded", name = task.name));
+                packages.insert(task.name.clone(), path);
+                Ok(())
+            },
+        ))?;
+        Ok(packages)
+    }
+
+    /// Remove the least recently used packages from the [`DownloadCache`]
+    /// if it exceeds the [size limit](DownloadConf::cache_size_limit).
+    ///
+    /// This is not essential to an installation, so errors are only logged as warnings.
+    fn trim_download_cache(&self) {
+        let limit = DownloadConf::load(&self.install_dir).cache_size_limit();
+        match DownloadCache::new(&self.install_dir).trim(limit) {
+            Ok(0) => (),
+            Ok(freed) => debug!("{freed} bytes freed from download cache"),
+            Err(e) => warn!("{}", t!("trim_cache_failed", reason = format!("{e:#}"))),
+        }
+    }
+
     // TODO: Write version info after installing each tool,
     // which is later used for updating.
-    fn install_tool(&mut self, name: &str, tool: &ToolInfo) -> Result<()> {
+    /// Install a tool, the package of which might be `prefetched` if it's from a url,
+    /// otherwise it will be fetched here.
+    fn install_tool(
+        &mut self,
+        name: &str,
+        tool: &ToolInfo,
+        prefetched: Option<&PathBuf>,
+    ) -> Result<()> {
         let tool_ver = tool.version();
         let record = match tool {
-            ToolInfo::PlainVersion(version) | ToolInfo::DetailedVersion { ver: version, .. } => {
-                Tool::cargo_tool(name, Some(vec![name, "--version", version]))
-                    .install(tool_ver, self)?
-            }
-            ToolInfo::Git {
-                git,
-                branch,
-                tag,
-                rev,
-                ..
-            } => {
-                let mut args = vec!["--git", git.as_str()];
-                if let Some(s) = &branch {
-                    args.extend(["--branch", s]);
-                }
-                if let Some(s) = &tag {
-                    args.extend(["--tag", s]);
-                }
-                if let Some(s) = &rev {
-                    args.extend(["--rev", s]);
-                }
-
-                Tool::cargo_tool(name, Some(args)).install(tool_ver, self)?
+            ToolInfo::PlainVersion(_) | ToolInfo::DetailedVersion { .. } | ToolInfo::Git { .. } => {
+                Tool::cargo_tool(name, cargo_install_args(name, tool)).install(tool_ver, self)?
             }
             ToolInfo::Path { path, .. } => self.try_install_from_path(name, tool_ver, path)?,
-            // TODO: Have a dedicated download folder, do not use temp dir to store downloaded artifacts,
-            // so then we can have the `resume download` feature.
-            ToolInfo::Url { url, filename, .. } => {
-                let temp_dir = self.create_temp_dir("download")?;
-                let downloaded_file_name = if let Some(name) = filename {
-                    name
-                } else {
-                    url.path_segments()
-                        .ok_or_else(|| anyhow!("unsupported url format '{url}'"))?
-                        .last()
-                        // Sadly, a path segment could be empty string, so we need to filter that out
-                        .filter(|seg| !seg.is_empty())
-                        .ok_or_else(|| {
-                            anyhow!("'{url}' doesn't appear to be a downloadable file")
-                        })?
+            ToolInfo::Url { .. } => {
+                let package = match prefetched {
+                    Some(path) => path.clone(),
+                    None => self
+                        .prefetch_packages(&[(name, tool)])?
+                        .remove(name)
+                        .with_context(|| format!("unable to get the package of '{name}'"))?,
                 };
-                let dest = temp_dir.path().join(downloaded_file_name);
-                utils::DownloadOpt::new(name)
-                    .with_proxy(self.manifest.proxy.clone())
-                    .blocking_download(url, &dest)?;
-
-                self.try_install_from_path(name, tool_ver, &dest)?
+                self.try_install_from_path(name, tool_ver, &package)?
             }
         };
 
@@ -339,11 +584,35 @@ impl<'a> InstallConfiguration<'a> {
             );
         }
 
+        let checksum = path
+            .is_file()
+            .then(|| utils::file_sha256(path))
+            .transpose()?;
+        if let Some(lock) = self.lock {
+            // packages without a checksum cannot be verified, which defeats the lockfile
+            let expected = lock
+                .checksum_of(name)
+                .with_context(|| t!("lock_missing_checksum", name = name))?;
+            if checksum.as_deref() != Some(expected) {
+                bail!(
+                    "{}",
+                    t!(
+                        "lock_checksum_mismatch",
+                        name = name,
+                        expected = expected,
+                        actual = checksum.as_deref().unwrap_or("-")
+                    )
+                );
+            }
+        }
+
         let temp_dir = self.create_temp_dir(name)?;
         let tool_installer_path = self.extract_or_copy_to(path, temp_dir.path())?;
         let tool_installer = Tool::from_path(name, &tool_installer_path)
             .with_context(|| format!("no install method for tool '{name}'"))?;
-        tool_installer.install(version, self)
+        Ok(tool_installer
+            .install(version, self)?
+            .with_source_sha256(checksum))
     }
 
     /// Configuration options for `cargo`.
@@ -392,10 +661,49 @@ impl<'a> InstallConfiguration<'a> {
 // For updates
 impl InstallConfiguration<'_> {
     pub fn update(mut self, components: Vec<Component>) -> Result<()> {
-        // Create a copy of the manifest which is later used for component management.
-        self.manifest.write_to_dir(&self.install_dir)?;
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = self.history_entry(Action::Update);
+        let result = self.update_(components);
+        history::record(&self.install_dir, entry.with_result(&result));
+        result
+    }
+
+    fn update_(&mut self, components: Vec<Component>) -> Result<()> {
+        self.journal = Some(Journal::begin(
+            &self.install_dir,
+            Operation::Update,
+            Some(Journal::target_of(self.manifest)),
+        )?);
+
+        // Keep the previous toolkit when switching to another one, so that it can be rolled back to.
+        let switches_toolkit = self.save_snapshot
+            && (self.install_record.name != self.manifest.name
+                || self.install_record.version != self.manifest.version);
+        self.run_step(Step::SaveSnapshot, |c| {
+            if switches_toolkit {
+                Snapshot::stage(&c.install_dir)?;
+            }
+            Ok(())
+        })?;
 
         let (toolchain, tools) = split_components(components);
+        // Create a copy of the manifest which is later used for component management.
+        // The toolchain is not updated if none of its components are selected, such as when
+        // it's pinned, so the installed version is kept in the copy to manage the right toolchain.
+        let kept_toolchain = self
+            .install_record
+            .installed_toolchain()
+            .filter(|(ver, _)| toolchain.is_empty() && *ver != self.manifest.rust_version())
+            .map(|(ver, _)| ver.to_string());
+        self.run_step(Step::WriteManifest, |c| match kept_toolchain {
+            Some(version) => {
+                let mut manifest = c.manifest.clone();
+                manifest.rust.version = version;
+                manifest.write_to_dir(&c.install_dir)
+            }
+            None => c.manifest.write_to_dir(&c.install_dir),
+        })?;
+
         // setup env for current process
         for (key, val) in self.env_vars()? {
             std::env::set_var(key, val);
@@ -404,20 +712,34 @@ impl InstallConfiguration<'_> {
 
         // don't update toolchain if no toolchain components are selected
         if !toolchain.is_empty() {
-            self.update_toolchain(&toolchain)?;
+            self.run_step(Step::UpdateRust, |c| c.update_toolchain(&toolchain))?;
         }
         self.update_tools(&tools)?;
-        Ok(())
+
+        // the toolkit might be switched without touching the toolchain
+        self.install_record
+            .clone_toolkit_meta_from_manifest(self.manifest);
+        self.install_record.write()?;
+        Snapshot::commit(&self.install_dir)?;
+
+        self.finish_journal()
     }
 
     fn update_toolchain(&mut self, components: &[ToolchainComponent]) -> Result<()> {
         info!("{}", t!("update_toolchain"));
 
         let manifest = self.manifest;
+        let obsolete = rollback::obsolete_toolchains(
+            &self.install_dir,
+            self.install_record
+                .installed_toolchain()
+                .map(|(ver, _)| ver),
+            manifest.rust_version(),
+        )?;
 
         ToolchainInstaller::init()
             .insecure(self.insecure)
-            .update(self, manifest)?;
+            .update(self, manifest, components, &obsolete)?;
 
         let record = &mut self.install_record;
         // Add the rust info to the fingerprint.
@@ -438,12 +760,358 @@ impl InstallConfiguration<'_> {
     }
 }
 
+// For component management
+impl InstallConfiguration<'_> {
+    /// Install extra tools and toolchain components into an existing installation,
+    /// using the sources and versions from the manifest of current installation.
+    pub fn add_components(mut self, components: Vec<Component>) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = HistoryEntry::new(Action::ComponentAdd)
+            .components(components.iter().map(|c| c.name.clone()).collect());
+        let result = self.add_components_(components);
+        history::record(&self.install_dir, entry.with_result(&result));
+        result
+    }
+
+    fn add_components_(&mut self, components: Vec<Component>) -> Result<()> {
+        // setup env for current process
+        for (key, val) in self.env_vars()? {
+            std::env::set_var(key, val);
+        }
+        self.cargo_is_installed = self.install_record.rust.is_some();
+        self.inc_progress(10.0)?;
+
+        let (toolchain, tools) = split_components(components);
+        let toolchain = toolchain
+            .iter()
+            .filter(|c| !c.is_profile)
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        if !toolchain.is_empty() {
+            info!("{}", t!("install_toolchain_components"));
+            let manifest = self.manifest;
+            ToolchainInstaller::init()
+                .insecure(self.insecure)
+                .add_components(self, manifest, &toolchain)?;
+            for name in toolchain {
+                self.install_record.add_component_record(name);
+            }
+            self.install_record.write()?;
+        }
+        self.inc_progress(30.0)?;
+
+        self.install_tools(&tools)?;
+        self.cargo_install(&tools)
+    }
+}
+
+// For repairs
+impl InstallConfiguration<'_> {
+    /// Look for broken tools and toolchain components of the current installation.
+    pub fn detect_broken(&self) -> Result<RepairPlan> {
+        RepairPlan::detect(&self.install_record, self.manifest, self)
+    }
+
+    /// Reinstall the broken components listed in a [`RepairPlan`], using the same
+    /// sources and versions from the manifest of current installation.
+    ///
+    /// Healthy components and user configurations (such as environment variables)
+    /// will not be touched.
+    pub fn repair(mut self, plan: &RepairPlan) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = HistoryEntry::new(Action::Repair)
+            .components(plan.to_list().lines().map(ToString::to_string).collect());
+        let result = self.repair_(plan);
+        history::record(&self.install_dir, entry.with_result(&result));
+        result
+    }
+
+    fn repair_(&mut self, plan: &RepairPlan) -> Result<()> {
+        // setup env for current process
+        for (key, val) in self.env_vars()? {
+            std::env::set_var(key, val);
+        }
+        self.inc_progress(10.0)?;
+
+        if let Some(toolchain) = &plan.toolchain {
+            self.repair_toolchain(toolchain)?;
+        }
+        self.inc_progress(30.0)?;
+
+        let mut tools = ToolMap::new();
+        if let Some(all_tools) = self.manifest.current_target_tools() {
+            for name in &plan.tools {
+                if let Some(info) = all_tools.get(name) {
+                    tools.insert(name.clone(), info.clone());
+                } else {
+                    warn!("{}", t!("repair_tool_source_not_found", name = name));
+                }
+            }
+        }
+        info!("{}", t!("repair_tools"));
+        self.install_tools_(false, &tools, 30.0)?;
+        self.install_tools_(true, &tools, 30.0)?;
+        Ok(())
+    }
+
+    fn repair_toolchain(&mut self, repair: &ToolchainRepair) -> Result<()> {
+        info!("{}", t!("repair_toolchain"));
+
+        let manifest = self.manifest;
+        let installer = ToolchainInstaller::init().insecure(self.insecure);
+        match repair {
+            ToolchainRepair::Reinstall => {
+                let recorded = self
+                    .install_record
+                    .installed_toolchain()
+                    .map(|(_, comps)| comps.to_vec())
+                    .unwrap_or_default();
+                let profile = manifest.toolchain_profile();
+                let components = recorded
+                    .iter()
+                    .map(|name| {
+                        let is_profile = profile.is_some_and(|p| {
+                            &p.name == name || p.verbose_name.as_ref() == Some(name)
+                        });
+                        ToolchainComponent::new(name).is_profile(is_profile)
+                    })
+                    .collect::<Vec<_>>();
+                installer.reinstall(self, manifest, &components)?;
+            }
+            ToolchainRepair::Components(comps) => {
+                let comps = comps.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                installer.add_components(self, manifest, &comps)?;
+            }
+        }
+        self.cargo_is_installed = true;
+        Ok(())
+    }
+}
+
+// For rolling back
+impl InstallConfiguration<'_> {
+    /// Roll back to the previous toolkit, by uninstalling the tools that were not installed
+    /// in it, then installing the components in `plan` with their previous versions.
+    ///
+    /// This configuration must be created with the manifest of the [`Snapshot`]
+    /// that `plan` was computed from.
+    pub fn rollback(mut self, plan: &RollbackPlan) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = self.history_entry(Action::Rollback);
+        let result = self.rollback_(plan);
+        history::record(&self.install_dir, entry.with_result(&result));
+        result
+    }
+
+    fn rollback_(&mut self, plan: &RollbackPlan) -> Result<()> {
+        self.remove_tools_(&plan.tools_to_remove)?;
+        self.update_(plan.components.clone())
+    }
+
+    /// Switch to the toolkit of current manifest, by updating `components` to the versions
+    /// in it, and removing the installed `tools_to_remove` which it does not provide.
+    pub fn switch_toolkit(
+        mut self,
+        components: Vec<Component>,
+        tools_to_remove: &[String],
+    ) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = self.history_entry(Action::Update);
+        let result = self
+            .remove_tools_(tools_to_remove)
+            .and_then(|_| self.update_(components));
+        history::record(&self.install_dir, entry.with_result(&result));
+        result
+    }
+
+    /// Uninstall the given tools and remove them from the fingerprint.
+    fn remove_tools_(&mut self, names: &[String]) -> Result<()> {
+        if !names.is_empty() {
+            let config = UninstallConfiguration::at(&self.install_dir)?;
+            for name in names {
+                let Some(tool) = self
+                    .install_record
+                    .tools
+                    .get(name)
+                    .and_then(|record| Tool::from_record(name, record))
+                else {
+                    continue;
+                };
+                info!("{}", t!("uninstalling_for", name = name));
+                if tool.uninstall(&config).is_err() {
+                    info!(
+                        "{}: {}",
+                        t!("uninstall_tool_skipped", tool = name),
+                        t!("maybe_uninstalled_already")
+                    );
+                }
+                self.install_record.remove_tool_record(name);
+                self.install_record.write()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// For dry runs
+impl InstallConfiguration<'_> {
+    /// Compute what would be done to install or update `components` with this configuration,
+    /// where `action` is one of [`Install`](Action::Install), [`Update`](Action::Update)
+    /// or [`ComponentAdd`](Action::ComponentAdd).
+    pub(crate) fn plan(&self, action: Action, components: &[Component]) -> Result<Plan> {
+        let mut plan = Plan::new(action, &self.install_dir);
+        let mut space = DiskSpace::default();
+        let (toolchain, tools) = split_components(components.to_vec());
+        let installed_toolchain = self.install_record.installed_toolchain();
+        // Not using `cargo_home` etc., because they create the directories.
+        let cargo_home = self.install_dir.join(".cargo");
+        let cargo_bin = cargo_home.join("bin");
+
+        if !toolchain.is_empty() {
+            let version = self.manifest.rust_version();
+            let installed_version = installed_toolchain.map(|(ver, _)| ver);
+            match action {
+                Action::Install => plan.change(ChangeKind::Install, "rust", None, Some(version)),
+                Action::Update if installed_version != Some(version) => {
+                    plan.change(ChangeKind::Update, "rust", installed_version, Some(version));
+                }
+                _ => (),
+            }
+            let installed_comps = installed_toolchain.map(|(_, c)| c).unwrap_or_default();
+            for comp in toolchain
+                .iter()
+                .filter(|c| !installed_comps.contains(&c.name))
+            {
+                plan.change(ChangeKind::Install, &comp.name, None, Some(version));
+            }
+
+            if !cargo_bin.join(RUSTUP).exists()
+                && !self.manifest.rustup_bin()?.is_some_and(|p| p.is_file())
+            {
+                plan.downloads
+                    .push(rustup_init_url(&self.rustup_update_root)?.to_string());
+            }
+            match self.manifest.offline_dist_server()? {
+                Some(server) => {
+                    if let Ok(path) = server.to_file_path() {
+                        space.add_path("rust", &path);
+                    }
+                }
+                None => {
+                    plan.downloads.push(self.rustup_dist_server.to_string());
+                    space.add_unknown("rust");
+                }
+            }
+        }
+
+        let cache = DownloadCache::new(&self.install_dir);
+        for (name, tool) in tools.iter() {
+            let installed_version = self.install_record.get_tool_version(name);
+            let is_installed = self.install_record.tools.contains_key(name);
+            let kind = if action == Action::Update && is_installed {
+                ChangeKind::Update
+            } else {
+                ChangeKind::Install
+            };
+            plan.change(kind, name, installed_version, tool.version());
+
+            match tool {
+                ToolInfo::Path { path, .. } => space.add_path(name, path),
+                ToolInfo::Url { url, .. } => {
+                    let expected_checksum = self.lock.and_then(|lock| lock.checksum_of(name));
+                    if let Some(cached) = cache.peek(url, expected_checksum) {
+                        space.add_path(name, &cached);
+                    } else {
+                        plan.downloads.push(url.to_string());
+                        space.add_unknown(name);
+                    }
+                }
+                _ => {
+                    if let Some(args) = cargo_install_args(name, tool) {
+                        plan.commands
+                            .push(format!("cargo install {}", args.join(" ")));
+                    }
+                    space.add_unknown(name);
+                }
+            }
+        }
+        plan.disk_space = Some(space);
+
+        if action == Action::Install {
+            let g_opts = GlobalOpts::get();
+            if !g_opts.no_modify_env() {
+                plan.env_vars = self
+                    .env_vars_of(&cargo_home, &self.install_dir.join(".rustup"))?
+                    .into_iter()
+                    .map(|(key, val)| (key.to_string(), Some(val)))
+                    .collect();
+                #[cfg(unix)]
+                {
+                    plan.rc_files = super::os::unix::rc_files_to_update();
+                }
+            }
+            if !g_opts.no_modify_path() {
+                plan.path_added.push(self.install_dir.clone());
+                if !toolchain.is_empty() {
+                    plan.path_added.push(cargo_bin);
+                }
+            }
+        }
+
+        Ok(plan)
+    }
+}
+
 /// Get the default installation directory,
 /// which is a directory under [`home_dir`](utils::home_dir).
 pub fn default_install_dir() -> PathBuf {
     utils::home_dir().join(&*t!("vendor_en"))
 }
 
+/// Return the arguments after `cargo install` to install `tool`,
+/// or `None` if it is not installed by cargo.
+fn cargo_install_args<'a>(name: &'a str, tool: &'a ToolInfo) -> Option<Vec<&'a str>> {
+    match tool {
+        ToolInfo::PlainVersion(version) | ToolInfo::DetailedVersion { ver: version, .. } => {
+            Some(vec![name, "--version", version])
+        }
+        ToolInfo::Git {
+            git,
+            branch,
+            tag,
+            rev,
+            ..
+        } => {
+            let mut args = vec!["--git", git.as_str()];
+            if let Some(s) = &branch {
+                args.extend(["--branch", s]);
+            }
+            if let Some(s) = &tag {
+                args.extend(["--tag", s]);
+            }
+            if let Some(s) = &rev {
+                args.extend(["--rev", s]);
+            }
+            Some(args)
+        }
+        ToolInfo::Path { .. } | ToolInfo::Url { .. } => None,
+    }
+}
+
+/// Return the name of the package downloaded from `url`, which is `filename` if specified,
+/// otherwise the last segment of the url path.
+fn package_filename<'a>(url: &'a Url, filename: Option<&'a str>) -> Result<&'a str> {
+    if let Some(name) = filename {
+        return Ok(name);
+    }
+    url.path_segments()
+        .ok_or_else(|| anyhow!("unsupported url format '{url}'"))?
+        .last()
+        // Sadly, a path segment could be empty string, so we need to filter that out
+        .filter(|seg| !seg.is_empty())
+        .ok_or_else(|| anyhow!("'{url}' doesn't appear to be a downloadable file"))
+}
+
 /// Split components list to `toolchain_components` and `toolset_components`,
 /// as we are running `rustup` to install toolchain components, but using other methods
 /// for toolset components.
@@ -510,4 +1178,28 @@ mod tests {
             .join(InstallationRecord::FILENAME)
             .is_file());
     }
+    #[tokio::test]
+    async fn rollback_with_recorded_settings() {
+        let install_root = tempfile::tempdir().unwrap();
+        let mirror: Url = "https://mirror.example.com/".parse().unwrap();
+        let snapshot = Snapshot {
+            manifest: get_toolset_manifest(None, false).await.unwrap(),
+            record: InstallationRecord {
+                settings: Some(InstallSettings {
+                    rustup_dist_server: mirror.clone(),
+                    rustup_update_root: mirror.join("rustup").unwrap(),
+                    cargo_registry: None,
+                    proxy: None,
+                }),
+                ..Default::default()
+            },
+        };
+
+        let config = InstallConfiguration::for_snapshot(install_root.path(), &snapshot).unwrap();
+        assert_eq!(config.rustup_dist_server, mirror);
+        assert_eq!(
+            config.rustup_update_root.as_str(),
+            "https://mirror.example.com/rustup"
+        );
+    }
 }
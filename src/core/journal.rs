@@ -0,0 +1,412 @@
+//! Journal of the steps that have been completed by an install, update or uninstall operation.
+//!
+//! The journal file is written to the installation directory before the operation starts,
+//! and it will be removed once the operation finishes successfully. Therefore, if the journal
+//! file still exists the next time this program runs, we know that the last operation was
+//! interrupted (either by an error or by the user, such as pressing `Ctrl+C`),
+//! which can then be resumed by skipping the completed steps, or be rolled back.
+//!
+//! An installation is rolled back by undoing the completed steps in reverse order,
+//! while an update or uninstallation is rolled back by installing the components
+//! that were installed before it again, which are known from the backup of the
+//! manifest and fingerprint made before the operation started.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dir_lock::InstallDirLock,
+    directories::RimDir,
+    install::{EnvConfig, InstallConfiguration},
+    os::remove_from_path,
+    parser::{
+        cargo_config::CargoConfig, fingerprint::InstallationRecord,
+        toolset_manifest::ToolsetManifest, TomlParser,
+    },
+    rollback::{RollbackPlan, Snapshot},
+    rustup::ToolchainInstaller,
+    tools::Tool,
+    uninstall::{UninstallConfiguration, Uninstallation},
+};
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Operation {
+    Install,
+    Update,
+    Uninstall,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Install => t!("install"),
+            Self::Update => t!("update"),
+            Self::Uninstall => t!("uninstall"),
+        };
+        write!(f, "{}", s.to_lowercase())
+    }
+}
+
+/// A single step of an operation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub(crate) enum Step {
+    // Installation steps
+    Setup,
+    ConfigEnvVars,
+    ConfigCargo,
+    InstallTool { name: String },
+    InstallRust,
+    // Update steps
+    SaveSnapshot,
+    WriteManifest,
+    UpdateRust,
+    UpdateTool { name: String },
+    // Uninstallation steps
+    UninstallTools,
+    UninstallRust,
+    RemoveEnvVars,
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Setup => write!(f, "setup"),
+            Self::ConfigEnvVars => write!(f, "config-env-vars"),
+            Self::ConfigCargo => write!(f, "config-cargo"),
+            Self::InstallTool { name } => write!(f, "install-tool ({name})"),
+            Self::InstallRust => write!(f, "install-rust"),
+            Self::SaveSnapshot => write!(f, "save-snapshot"),
+            Self::WriteManifest => write!(f, "write-manifest"),
+            Self::UpdateRust => write!(f, "update-rust"),
+            Self::UpdateTool { name } => write!(f, "update-tool ({name})"),
+            Self::UninstallTools => write!(f, "uninstall-tools"),
+            Self::UninstallRust => write!(f, "uninstall-rust"),
+            Self::RemoveEnvVars => write!(f, "remove-env-vars"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Journal {
+    pub operation: Operation,
+    /// The toolkit that the operation installs, see [`target_of`](Self::target_of).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(default)]
+    steps: Vec<Step>,
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+impl TomlParser for Journal {
+    const FILENAME: &'static str = ".journal.toml";
+}
+
+impl RimDir for Journal {
+    fn install_dir(&self) -> &Path {
+        self.root.as_path()
+    }
+}
+
+impl Journal {
+    /// Load the journal of an unfinished operation in `root`, return `None` if there isn't one.
+    pub fn load_unfinished(root: &Path) -> Result<Option<Self>> {
+        let path = root.join(Self::FILENAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let mut journal = Self::load(path)?;
+        journal.root = root.to_path_buf();
+        Ok(Some(journal))
+    }
+
+    /// Identify the toolkit described by `manifest` as the target of an operation.
+    pub(crate) fn target_of(manifest: &ToolsetManifest) -> String {
+        format!(
+            "{} {} (rust {})",
+            manifest.name.as_deref().unwrap_or("N/A"),
+            manifest.version.as_deref().unwrap_or("N/A"),
+            manifest.rust_version()
+        )
+    }
+
+    /// Start journaling an operation in `root`, which installs the `target` toolkit
+    /// (see [`target_of`](Self::target_of)) if it's not an uninstallation.
+    ///
+    /// If there is an unfinished journal of the same operation, it will be loaded
+    /// so that the operation can be resumed.
+    ///
+    /// # Error
+    /// Return error if there is an unfinished journal of a different operation,
+    /// or of the same operation but for another target, because the steps completed
+    /// for that target cannot be skipped for this one.
+    pub(crate) fn begin(root: &Path, operation: Operation, target: Option<String>) -> Result<Self> {
+        if let Some(journal) = Self::load_unfinished(root)? {
+            if journal.operation != operation {
+                bail!(
+                    "{}",
+                    t!("unfinished_operation_conflict", op = journal.operation)
+                );
+            }
+            if journal.target != target {
+                bail!(
+                    "{}",
+                    t!(
+                        "unfinished_operation_target_mismatch",
+                        op = operation,
+                        expected = journal.target.as_deref().unwrap_or("N/A"),
+                        actual = target.as_deref().unwrap_or("N/A")
+                    )
+                );
+            }
+            info!("{}", t!("resuming_operation", op = operation));
+            return Ok(journal);
+        }
+
+        let journal = Self {
+            operation,
+            target,
+            steps: vec![],
+            root: root.to_path_buf(),
+        };
+        journal.backup()?;
+        journal.write()?;
+        Ok(journal)
+    }
+
+    pub(crate) fn is_done(&self, step: &Step) -> bool {
+        self.steps.contains(step)
+    }
+
+    /// Mark a step as completed.
+    pub(crate) fn record(&mut self, step: Step) -> Result<()> {
+        self.steps.push(step);
+        self.write()
+    }
+
+    /// Remove the journal and the backups, this should be called once the operation succeed.
+    pub(crate) fn finish(self) -> Result<()> {
+        utils::remove(self.root.join(Self::FILENAME))?;
+        let backup_dir = self.backup_dir();
+        if backup_dir.exists() {
+            utils::remove(backup_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Revert the changes made by the unfinished operation, then remove the journal.
+    ///
+    /// An installation is rolled back by undoing the completed steps in reverse order,
+    /// where failing to undo a step will not stop the rollback, a warning will be shown instead.
+    /// Updates and uninstallations are rolled back by [restoring](Self::restore_previous)
+    /// the components installed before them.
+    pub fn rollback(mut self) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.root)?;
+        info!("{}", t!("rolling_back", op = self.operation));
+        if self.operation != Operation::Install {
+            return self.restore_previous();
+        }
+
+        let mut config = UninstallConfiguration::at(&self.root)?;
+        while let Some(step) = self.steps.pop() {
+            info!("{}", t!("undoing_step", step = step));
+            if let Err(e) = self.undo(&step, &mut config) {
+                warn!("{}: {e}", t!("undo_step_failed", step = step));
+            }
+            self.write()?;
+        }
+        // restore fingerprint last, because undoing some of the steps will update it
+        self.restore(InstallationRecord::FILENAME, &self.root)?;
+
+        info!("{}", t!("rollback_finished"));
+        self.finish()
+    }
+
+    fn undo(&self, step: &Step, config: &mut UninstallConfiguration) -> Result<()> {
+        match step {
+            Step::Setup => {
+                let manager_name = format!("{}-manager", t!("vendor_en"));
+                let manager_exe = self.root.join(utils::exe!(manager_name));
+                if manager_exe.is_file() && manager_exe != std::env::current_exe()? {
+                    utils::remove(&manager_exe)?;
+                }
+                remove_from_path(&self.root)?;
+                #[cfg(windows)]
+                super::os::windows::remove_from_programs(&self.root)?;
+                self.restore(ToolsetManifest::FILENAME, &self.root)
+            }
+            Step::ConfigEnvVars => config.remove_rustup_env_vars(),
+            Step::ConfigCargo => self.restore(CargoConfig::FILENAME, self.cargo_home()),
+            Step::InstallTool { name } => {
+                if let Some(tool) = config
+                    .install_record
+                    .tools
+                    .get(name)
+                    .and_then(|record| Tool::from_record(name, record))
+                {
+                    tool.uninstall(config)?;
+                }
+                config.install_record.remove_tool_record(name);
+                config.install_record.write()
+            }
+            Step::InstallRust => {
+                ToolchainInstaller::init().remove_self(config)?;
+                config.install_record.remove_rust_record();
+                config.install_record.remove_toolkit_meta();
+                config.install_record.write()
+            }
+            // Steps of other operations are not undone one by one, see `restore_previous`
+            _ => Ok(()),
+        }
+    }
+
+    /// Install the components of the toolkit that was installed before the update or
+    /// uninstallation again, using the manifest and fingerprint backed up before it started.
+    ///
+    /// This is an update to the previous toolkit on its own, so it is journaled as well
+    /// and can be resumed if interrupted, after the journal of this operation is removed.
+    fn restore_previous(self) -> Result<()> {
+        let Some(previous) = Snapshot::load_from(&self.backup_dir())? else {
+            bail!("{}", t!("rollback_no_backup", op = self.operation));
+        };
+        let env_removed = self.is_done(&Step::RemoveEnvVars);
+        self.restore(CargoConfig::FILENAME, self.cargo_home())?;
+        // the update did not finish, so the toolkit before it remains the one to roll back to
+        Snapshot::discard_staged(&self.root)?;
+        let root = self.root.clone();
+        self.finish()?;
+
+        let plan = RollbackPlan::new(&previous, &InstallationRecord::load_from_dir(&root)?)?;
+        let config = InstallConfiguration::for_snapshot(&root, &previous)?.keep_snapshot();
+        if env_removed {
+            config.config_env_vars()?;
+        }
+        config.rollback(&plan)?;
+
+        info!("{}", t!("rollback_finished"));
+        Ok(())
+    }
+
+    fn write(&self) -> Result<()> {
+        self.write_to_dir(&self.root)
+    }
+
+    fn backup_dir(&self) -> PathBuf {
+        self.root.join("backup").join("journal")
+    }
+
+    /// Backup the files that might be modified by the operation.
+    fn backup(&self) -> Result<()> {
+        let backup_dir = self.backup_dir();
+        utils::ensure_dir(&backup_dir)?;
+        for path in [
+            self.root.join(ToolsetManifest::FILENAME),
+            self.root.join(InstallationRecord::FILENAME),
+            self.cargo_home().join(CargoConfig::FILENAME),
+        ] {
+            if path.is_file() {
+                utils::copy_into(&path, &backup_dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore a file from backup into `dir`,
+    /// or remove that file if it did not exist before the operation.
+    fn restore(&self, filename: &str, dir: &Path) -> Result<()> {
+        let backup = self.backup_dir().join(filename);
+        let dest = dir.join(filename);
+        if backup.is_file() {
+            utils::copy_as(backup, dest)?;
+        } else if dest.exists() {
+            utils::remove(dest)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_serialization() {
+        let root = tempfile::tempdir().unwrap();
+        let mut journal = Journal::begin(root.path(), Operation::Update, None).unwrap();
+        journal.record(Step::WriteManifest).unwrap();
+        journal
+            .record(Step::UpdateTool {
+                name: "mingw64".into(),
+            })
+            .unwrap();
+
+        let content = std::fs::read_to_string(root.path().join(Journal::FILENAME)).unwrap();
+        assert_eq!(
+            content.trim(),
+            r#"operation = "update"
+
+[[steps]]
+kind = "write-manifest"
+
+[[steps]]
+kind = "update-tool"
+name = "mingw64""#
+        );
+
+        let loaded = Journal::load_unfinished(root.path()).unwrap().unwrap();
+        assert_eq!(loaded.operation, Operation::Update);
+        assert_eq!(loaded.steps, journal.steps);
+    }
+
+    #[test]
+    fn resume_unfinished_journal() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(Journal::load_unfinished(root.path()).unwrap().is_none());
+
+        let mut journal = Journal::begin(root.path(), Operation::Install, None).unwrap();
+        journal.record(Step::Setup).unwrap();
+        journal.record(Step::ConfigCargo).unwrap();
+        drop(journal);
+
+        // an unfinished journal cannot be continued by another operation
+        assert!(Journal::begin(root.path(), Operation::Uninstall, None).is_err());
+
+        let journal = Journal::begin(root.path(), Operation::Install, None).unwrap();
+        assert!(journal.is_done(&Step::Setup));
+        assert!(journal.is_done(&Step::ConfigCargo));
+        assert!(!journal.is_done(&Step::ConfigEnvVars));
+        assert!(!journal.is_done(&Step::InstallTool { name: "a".into() }));
+
+        journal.finish().unwrap();
+        assert!(Journal::load_unfinished(root.path()).unwrap().is_none());
+        assert!(!root.path().join("backup").join("journal").exists());
+    }
+
+    #[test]
+    fn resume_for_the_same_target_only() {
+        let root = tempfile::tempdir().unwrap();
+        let target = || Some("my-toolkit 1.0.0 (rust 1.82.0)".to_string());
+        let mut journal = Journal::begin(root.path(), Operation::Update, target()).unwrap();
+        journal
+            .record(Step::UpdateTool {
+                name: "mingw64".into(),
+            })
+            .unwrap();
+        drop(journal);
+
+        let another = Some("my-toolkit 1.1.0 (rust 1.83.0)".to_string());
+        assert!(Journal::begin(root.path(), Operation::Update, another).is_err());
+        let journal = Journal::begin(root.path(), Operation::Update, target()).unwrap();
+        assert!(journal.is_done(&Step::UpdateTool {
+            name: "mingw64".into()
+        }));
+    }
+}
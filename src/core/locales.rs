@@ -50,3 +50,55 @@ impl Into<PossibleValue> for Language {
         PossibleValue::new(self.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fmt;
+    use std::path::Path;
+
+    use serde::de::{Deserializer, IgnoredAny, MapAccess, Visitor};
+
+    use super::Language;
+
+    /// Collects the duplicated keys of a JSON object,
+    /// which would otherwise silently replace the earlier ones.
+    struct DuplicatedKeys;
+
+    impl<'de> Visitor<'de> for DuplicatedKeys {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of translations")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut seen = HashSet::new();
+            let mut duplicated = vec![];
+            while let Some((key, IgnoredAny)) = map.next_entry::<String, IgnoredAny>()? {
+                if !seen.insert(key.clone()) {
+                    duplicated.push(key);
+                }
+            }
+            Ok(duplicated)
+        }
+    }
+
+    #[test]
+    fn no_duplicated_keys() {
+        for lang in Language::possible_values() {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("locales")
+                .join(format!("{}.json", lang.locale_str()));
+            let content = std::fs::read_to_string(&path).unwrap();
+            let duplicated = serde_json::Deserializer::from_str(&content)
+                .deserialize_map(DuplicatedKeys)
+                .unwrap();
+            assert!(
+                duplicated.is_empty(),
+                "duplicated keys in {}: {duplicated:?}",
+                path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Lockfile that pins every package of an installation to an exact artifact,
+//! which can then be used to reproduce the same installation on another machine.
+//!
+//! A lockfile is exported from an existing installation, containing the resolved
+//! toolchain version, the resolved source of each tool (such as the git revision of
+//! a tool installed from git, or the checksum of a downloaded package),
+//! and the server settings that the installation was configured with.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{
+    directories::RimDir,
+    install::{default_rustup_dist_server, default_rustup_update_root},
+    parser::{
+        fingerprint::{InstallSettings, InstallationRecord, ToolRecord},
+        toolset_manifest::{
+            RustToolchain, ToolInfo, ToolMap, ToolchainProfile, Tools, ToolsetManifest,
+        },
+        TomlParser,
+    },
+    rustup::RUSTUP,
+    CARGO_HOME, RUSTUP_HOME,
+};
+use crate::utils;
+
+/// The version of lockfile format, bump this when making incompatible changes.
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockFile {
+    lock_version: u32,
+    /// Name of the toolkit.
+    pub name: Option<String>,
+    /// Version of the toolkit.
+    pub version: Option<String>,
+    pub rust: LockedToolchain,
+    pub settings: InstallSettings,
+    #[serde(default)]
+    pub tools: IndexMap<String, LockedTool>,
+}
+
+impl TomlParser for LockFile {
+    const FILENAME: &'static str = "rim-lock.toml";
+
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let raw = utils::read_to_string("lockfile", &path)?;
+        let lock = Self::from_str(&raw)?;
+        if lock.lock_version != LOCK_VERSION {
+            bail!(
+                "{}",
+                t!(
+                    "unsupported_lock_version",
+                    path = path.as_ref().display(),
+                    version = lock.lock_version
+                )
+            );
+        }
+        Ok(lock)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedToolchain {
+    /// The exact toolchain to install, such as `1.82.0` or `nightly-2024-10-20`.
+    pub version: String,
+    pub profile: ToolchainProfile,
+    /// Components that are installed in addition to the profile.
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+/// The resolved source of a tool.
+///
+/// Tools installed by `cargo` are locked to an exact `version` or a git `rev`,
+/// other tools are locked to their package `url` or `path`, along with its checksum,
+/// which is required so that the same package is installed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LockedTool {
+    pub version: Option<String>,
+    pub git: Option<Url>,
+    pub rev: Option<String>,
+    pub url: Option<Url>,
+    pub filename: Option<String>,
+    pub path: Option<PathBuf>,
+    pub sha256: Option<String>,
+}
+
+/// Installation directories of an existing installation.
+struct Installed<'a>(&'a Path);
+
+impl RimDir for Installed<'_> {
+    fn install_dir(&self) -> &Path {
+        self.0
+    }
+}
+
+impl LockFile {
+    /// Create a lockfile from the installation in `root`.
+    ///
+    /// # Error
+    /// Return error if any of the installed packages cannot be resolved to an exact artifact,
+    /// such as a tool installed from a git branch whose revision is unknown.
+    pub fn from_installation(root: &Path) -> Result<Self> {
+        let record = InstallationRecord::load_from_dir(root)?;
+        let manifest = ToolsetManifest::load(root.join(ToolsetManifest::FILENAME))?;
+        let dirs = Installed(root);
+
+        let Some((channel, recorded_comps)) = record.installed_toolchain() else {
+            bail!("{}", t!("lock_no_toolchain"));
+        };
+        let profile = manifest.toolchain_profile().cloned().unwrap_or_default();
+        let is_profile =
+            |name: &str| profile.name == name || profile.verbose_name.as_deref() == Some(name);
+        let rust = LockedToolchain {
+            version: resolve_toolchain(channel, &dirs)?,
+            components: recorded_comps
+                .iter()
+                .filter(|c| !is_profile(c))
+                .cloned()
+                .collect(),
+            profile,
+        };
+
+        let settings = record.settings.clone().unwrap_or_else(|| {
+            warn!("{}", t!("lock_settings_not_recorded"));
+            InstallSettings {
+                rustup_dist_server: default_rustup_dist_server().clone(),
+                rustup_update_root: default_rustup_update_root().clone(),
+                cargo_registry: None,
+                proxy: manifest.proxy.clone(),
+            }
+        });
+
+        let crates = installed_crates(dirs.cargo_home())?;
+        let mut tools = IndexMap::new();
+        for (name, tool_rec) in &record.tools {
+            let info = manifest
+                .current_target_tools()
+                .and_then(|map| map.iter().find(|(n, _)| *n == name.as_str()))
+                .map(|(_, info)| info)
+                .with_context(|| t!("lock_unknown_source", name = name))?;
+            let version = record.get_tool_version(name);
+            tools.insert(
+                name.clone(),
+                lock_tool(name, info, tool_rec, version, &crates)?,
+            );
+        }
+
+        Ok(Self {
+            lock_version: LOCK_VERSION,
+            name: record.name.clone(),
+            version: record.version.clone(),
+            rust,
+            settings,
+            tools,
+        })
+    }
+
+    /// Get the expected checksum of the package of a certain tool.
+    pub(crate) fn checksum_of(&self, tool: &str) -> Option<&str> {
+        self.tools.get(tool).and_then(|t| t.sha256.as_deref())
+    }
+
+    /// Convert this lockfile to a [`ToolsetManifest`] that contains only the locked packages,
+    /// so it can be installed as a regular toolkit.
+    pub fn to_manifest(&self) -> Result<ToolsetManifest> {
+        let mut rust = RustToolchain::new(&self.rust.version);
+        rust.profile = Some(self.rust.profile.clone());
+        rust.optional_components.clone_from(&self.rust.components);
+
+        let tool_map = self
+            .tools
+            .iter()
+            .map(|(name, tool)| Ok((name.clone(), tool.to_tool_info(name)?)))
+            .collect::<Result<ToolMap>>()?;
+
+        let mut manifest = ToolsetManifest::default();
+        manifest.name.clone_from(&self.name);
+        manifest.version.clone_from(&self.version);
+        manifest.proxy.clone_from(&self.settings.proxy);
+        manifest.rust = rust;
+        manifest.tools = Tools::new([(env!("TARGET").to_string(), tool_map)]);
+        Ok(manifest)
+    }
+}
+
+impl LockedTool {
+    fn to_tool_info(&self, name: &str) -> Result<ToolInfo> {
+        let info = if let Some(git) = &self.git {
+            let Some(rev) = &self.rev else {
+                bail!("{}", t!("lock_missing_rev", name = name));
+            };
+            ToolInfo::Git {
+                git: git.clone(),
+                branch: None,
+                tag: None,
+                rev: Some(rev.clone()),
+                required: false,
+                optional: false,
+                identifier: None,
+            }
+        } else if let Some(url) = &self.url {
+            self.ensure_checksum(name)?;
+            ToolInfo::Url {
+                url: url.clone(),
+                version: self.version.clone(),
+                required: false,
+                optional: false,
+                identifier: None,
+                filename: self.filename.clone(),
+            }
+        } else if let Some(path) = &self.path {
+            self.ensure_checksum(name)?;
+            ToolInfo::Path {
+                path: path.clone(),
+                version: self.version.clone(),
+                required: false,
+                optional: false,
+                identifier: None,
+            }
+        } else if let Some(version) = &self.version {
+            ToolInfo::PlainVersion(version.clone())
+        } else {
+            bail!("{}", t!("lock_unknown_source", name = name));
+        };
+        Ok(info)
+    }
+
+    fn ensure_checksum(&self, name: &str) -> Result<()> {
+        if self.sha256.is_none() {
+            bail!("{}", t!("lock_missing_checksum", name = name));
+        }
+        Ok(())
+    }
+}
+
+/// Resolve the source of an installed tool to an exact artifact.
+fn lock_tool(
+    name: &str,
+    info: &ToolInfo,
+    record: &ToolRecord,
+    recorded_version: Option<&str>,
+    crates: &[InstalledCrate],
+) -> Result<LockedTool> {
+    let mut locked = LockedTool {
+        version: recorded_version.or(info.version()).map(ToString::to_string),
+        ..Default::default()
+    };
+    match info {
+        ToolInfo::PlainVersion(_) | ToolInfo::DetailedVersion { .. } => {
+            if let Some(krate) = crates.iter().find(|c| c.name == name && c.rev.is_none()) {
+                locked.version = Some(krate.version.clone());
+            }
+            if locked.version.is_none() {
+                bail!("{}", t!("lock_unresolved_version", name = name));
+            }
+        }
+        ToolInfo::Git { git, rev, .. } => {
+            let resolved = crates
+                .iter()
+                .find(|c| c.git.as_ref() == Some(git))
+                .and_then(|c| c.rev.clone());
+            let Some(rev) = resolved.or_else(|| rev.clone()) else {
+                bail!("{}", t!("lock_missing_rev", name = name));
+            };
+            locked.git = Some(git.clone());
+            locked.rev = Some(rev);
+        }
+        ToolInfo::Url { url, filename, .. } => {
+            locked.url = Some(url.clone());
+            locked.filename.clone_from(filename);
+            locked.sha256.clone_from(&record.source_sha256);
+        }
+        ToolInfo::Path { path, .. } => {
+            locked.path = Some(path.clone());
+            locked.sha256 = match &record.source_sha256 {
+                Some(sum) => Some(sum.clone()),
+                None if path.is_file() => Some(utils::file_sha256(path)?),
+                None => None,
+            };
+        }
+    }
+    if locked.url.is_some() || locked.path.is_some() {
+        locked.ensure_checksum(name)?;
+    }
+    Ok(locked)
+}
+
+/// Resolve a toolchain channel, such as `stable`, to an exact toolchain version
+/// using the `rustc` of that toolchain.
+fn resolve_toolchain(channel: &str, dirs: &impl RimDir) -> Result<String> {
+    if semver::Version::parse(channel).is_ok() {
+        return Ok(channel.to_string());
+    }
+
+    let rustup = dirs.cargo_bin().join(RUSTUP);
+    let output = utils::cmd!(
+        [CARGO_HOME = dirs.cargo_home(), RUSTUP_HOME = dirs.rustup_home()] rustup,
+        "run",
+        channel,
+        "rustc",
+        "--version"
+    )
+    .output()
+    .ok()
+    .filter(|out| out.status.success())
+    .with_context(|| t!("lock_unresolved_toolchain", channel = channel))?;
+
+    parse_rustc_version(&String::from_utf8_lossy(&output.stdout))
+        .with_context(|| t!("lock_unresolved_toolchain", channel = channel))
+}
+
+/// Get the toolchain name from the output of `rustc --version`, which looks like:
+///
+/// ```text
+/// rustc 1.82.0 (f6e511eec 2024-10-15)
+/// rustc 1.84.0-nightly (3b5a5ec0d 2024-10-20)
+/// ```
+fn parse_rustc_version(output: &str) -> Option<String> {
+    let mut parts = output.split_whitespace().skip(1);
+    let version = parts.next()?;
+    let date = parts.nth(1)?.trim_end_matches(')');
+    let res = if version.ends_with("-nightly") {
+        format!("nightly-{date}")
+    } else if version.contains("-beta") {
+        format!("beta-{date}")
+    } else {
+        version.to_string()
+    };
+    Some(res)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct InstalledCrate {
+    name: String,
+    version: String,
+    git: Option<Url>,
+    rev: Option<String>,
+}
+
+/// Get the exact version and source of crates installed by `cargo install`,
+/// which are recorded in the `.crates.toml` file under `CARGO_HOME`.
+fn installed_crates(cargo_home: &Path) -> Result<Vec<InstalledCrate>> {
+    let path = cargo_home.join(".crates.toml");
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    let raw = utils::read_to_string("installed crates", &path)?;
+    let table: HashMap<String, toml::Table> = toml::from_str(&raw)?;
+    Ok(table
+        .get("v1")
+        .map(|v1| v1.keys().filter_map(|key| parse_crate_id(key)).collect())
+        .unwrap_or_default())
+}
+
+/// Parse the package id of a crate installed by `cargo install`, which looks like:
+///
+/// ```text
+/// mdbook 0.4.40 (registry+https://github.com/rust-lang/crates.io-index)
+/// foo 0.1.0 (git+https://github.com/foo/foo?branch=main#0a1b2c3d)
+/// ```
+fn parse_crate_id(id: &str) -> Option<InstalledCrate> {
+    let mut parts = id.splitn(3, ' ');
+    let name = parts.next()?.to_string();
+    let version = parts.next()?.to_string();
+    let source = parts.next()?.trim_start_matches('(').trim_end_matches(')');
+
+    let (git, rev) = if let Some(git_src) = source.strip_prefix("git+") {
+        let (url, rev) = git_src.split_once('#').unzip();
+        let url = url.unwrap_or(git_src);
+        let url = url.split_once('?').map_or(url, |(u, _)| u);
+        (Url::parse(url).ok(), rev.map(ToString::to_string))
+    } else {
+        (None, None)
+    };
+    Some(InstalledCrate {
+        name,
+        version,
+        git,
+        rev,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_installed_crates() {
+        assert_eq!(
+            parse_crate_id("mdbook 0.4.40 (registry+https://github.com/rust-lang/crates.io-index)"),
+            Some(InstalledCrate {
+                name: "mdbook".into(),
+                version: "0.4.40".into(),
+                git: None,
+                rev: None,
+            })
+        );
+        assert_eq!(
+            parse_crate_id("foo 0.1.0 (git+https://github.com/foo/foo?branch=main#0a1b2c3d)"),
+            Some(InstalledCrate {
+                name: "foo".into(),
+                version: "0.1.0".into(),
+                git: Some(Url::parse("https://github.com/foo/foo").unwrap()),
+                rev: Some("0a1b2c3d".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_toolchain_version() {
+        assert_eq!(
+            parse_rustc_version("rustc 1.82.0 (f6e511eec 2024-10-15)").as_deref(),
+            Some("1.82.0")
+        );
+        assert_eq!(
+            parse_rustc_version("rustc 1.84.0-nightly (3b5a5ec0d 2024-10-20)").as_deref(),
+            Some("nightly-2024-10-20")
+        );
+        assert_eq!(
+            parse_rustc_version("rustc 1.83.0-beta.3 (1a2b3c4d5 2024-10-18)").as_deref(),
+            Some("beta-2024-10-18")
+        );
+    }
+
+    #[test]
+    fn lock_to_manifest() {
+        let input = r#"
+lock-version = 1
+name = "my-toolkit"
+version = "1.0.0"
+
+[rust]
+version = "1.82.0"
+components = ["clippy"]
+
+[rust.profile]
+name = "minimal"
+
+[settings]
+rustup-dist-server = "https://example.com/"
+rustup-update-root = "https://example.com/rustup"
+
+[settings.cargo-registry]
+name = "mirror"
+url = "sparse+https://example.com/index/"
+
+[tools.foo]
+git = "https://github.com/foo/foo"
+rev = "0a1b2c3d"
+
+[tools.bar]
+version = "0.4.40"
+
+[tools.baz]
+url = "https://example.com/baz.zip"
+sha256 = "abcd"
+"#;
+        let lock = LockFile::from_str(input).unwrap();
+        assert_eq!(lock.checksum_of("baz"), Some("abcd"));
+        assert_eq!(lock.checksum_of("bar"), None);
+
+        let manifest = lock.to_manifest().unwrap();
+        assert_eq!(manifest.rust_version(), "1.82.0");
+        assert_eq!(manifest.optional_toolchain_components(), ["clippy"]);
+        let tools = manifest.current_target_tools().unwrap();
+        assert_eq!(
+            tools.get("bar"),
+            Some(&ToolInfo::PlainVersion("0.4.40".into()))
+        );
+        assert!(matches!(
+            tools.get("foo"),
+            Some(ToolInfo::Git { rev: Some(rev), branch: None, .. }) if rev == "0a1b2c3d"
+        ));
+        assert!(matches!(tools.get("baz"), Some(ToolInfo::Url { .. })));
+    }
+
+    #[test]
+    fn reject_package_without_checksum() {
+        let input = r#"
+lock-version = 1
+
+[rust]
+version = "1.82.0"
+
+[rust.profile]
+name = "minimal"
+
+[settings]
+rustup-dist-server = "https://example.com/"
+rustup-update-root = "https://example.com/rustup"
+
+[tools.baz]
+url = "https://example.com/baz.zip"
+"#;
+        let lock = LockFile::from_str(input).unwrap();
+        assert!(lock.to_manifest().is_err());
+    }
+}
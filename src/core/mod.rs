@@ -2,20 +2,31 @@
 //!
 //! Including configuration, toolchain, toolset management.
 
+pub mod cache;
 pub mod components;
 mod custom_instructions;
+pub(crate) mod dir_lock;
 pub(crate) mod directories;
+pub(crate) mod doctor;
+pub mod error;
+pub mod history;
 pub mod install;
+pub mod journal;
 mod locales;
+pub mod lock;
 pub(crate) mod os;
 pub(crate) mod parser;
 mod path_ext;
+pub(crate) mod plan;
+pub mod repair;
+pub mod rollback;
 pub(crate) mod rustup;
 pub mod toolkit;
 pub(crate) mod tools;
 pub mod try_it;
 pub(crate) mod uninstall;
 pub mod update;
+pub(crate) mod verify;
 
 use anyhow::Result;
 // re-exports
@@ -29,7 +40,6 @@ use std::{env, sync::OnceLock};
 macro_rules! declare_env_vars {
     ($($key:ident),+) => {
         $(pub(crate) const $key: &str = stringify!($key);)*
-        #[cfg(windows)]
         pub(crate) static ALL_VARS: &[&str] = &[$($key),+];
     };
 }
@@ -127,6 +137,7 @@ impl Mode {
             is_manager: true,
         });
 
+        cli::complete_from_env(true);
         let maybe_args = cli::parse_manager_cli();
         // execute callback function on cli args
         if let Ok(args) = &maybe_args {
@@ -145,6 +156,7 @@ impl Mode {
             is_manager: false,
         });
 
+        cli::complete_from_env(false);
         let maybe_args = cli::parse_installer_cli();
         if let Ok(args) = &maybe_args {
             if let Some(cb) = installer_callback {
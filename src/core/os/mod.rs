@@ -10,7 +10,10 @@ pub(crate) mod unix;
 pub(crate) mod windows;
 
 use anyhow::Result;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::error::{ErrorKind, ResultExt};
 
 /// Add a given path to OS's `PATH` variable.
 ///
@@ -19,12 +22,12 @@ use std::path::Path;
 /// was set to true.
 pub(crate) fn add_to_path(path: &Path) -> Result<()> {
     #[cfg(windows)]
-    windows::add_to_path(path)?;
+    let res = windows::add_to_path(path);
 
     #[cfg(unix)]
-    unix::add_to_path(path)?;
+    let res = unix::add_to_path(path);
 
-    Ok(())
+    res.with_kind(ErrorKind::EnvModification)
 }
 
 /// Remove a given path from OS's `PATH` variable.
@@ -34,10 +37,26 @@ pub(crate) fn add_to_path(path: &Path) -> Result<()> {
 /// was set to true, or if the path is not in the `PATH` variable.
 pub(crate) fn remove_from_path(path: &Path) -> Result<()> {
     #[cfg(windows)]
-    windows::remove_from_path(path)?;
+    let res = windows::remove_from_path(path);
 
     #[cfg(not(windows))]
-    unix::remove_from_path(path)?;
+    let res = unix::remove_from_path(path);
+
+    res.with_kind(ErrorKind::EnvModification)
+}
 
-    Ok(())
+/// Return the commands that set `vars` and add `paths` to `PATH` in current session of `shell`,
+/// such as `bash` or `pwsh`, the paths are expected in the order of being added.
+///
+/// This is useful when the installation was done without modifying the environment.
+pub(crate) fn env_script(
+    shell: &str,
+    vars: &HashMap<&'static str, String>,
+    paths: &[PathBuf],
+) -> Result<String> {
+    #[cfg(windows)]
+    return windows::env_script(shell, vars, paths);
+
+    #[cfg(unix)]
+    return unix::env_script(shell, vars, paths);
 }
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
-use std::{env, path::Path};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
 use crate::core::install::{EnvConfig, InstallConfiguration};
 use crate::core::uninstall::{UninstallConfiguration, Uninstallation};
@@ -19,27 +22,7 @@ impl EnvConfig for InstallConfiguration<'_> {
 
         if !GlobalOpts::get().no_modify_env {
             info!("{}", t!("install_env_config"));
-
-            let backup_dir = self.install_dir.join("backup");
-            utils::ensure_dir(&backup_dir)?;
-            for sh in shell::get_available_shells() {
-                // This string will be wrapped in a certain identifier comments.
-                for rc in sh.update_rcs() {
-                    // Do NOT fail installation if backup fails
-                    _ = create_backup_for_rc(&rc, &backup_dir);
-
-                    let old_content = utils::read_to_string("rc", &rc).unwrap_or_default();
-                    let new_content =
-                        rc_content_with_env_vars(sh.as_ref(), &old_content, &vars_raw);
-
-                    utils::write_file(&rc, &new_content, false).with_context(|| {
-                        format!(
-                            "failed to append environment vars to shell profile: '{}'",
-                            rc.display()
-                        )
-                    })?;
-                }
-            }
+            write_env_config(&self.install_dir, &vars_raw)?;
         }
 
         // Update vars for current process, this is a MUST to ensure this installation
@@ -52,6 +35,144 @@ impl EnvConfig for InstallConfiguration<'_> {
     }
 }
 
+/// Write env vars to the config section in the rc files of every available shell,
+/// the rc files will be backed up into the `backup` folder under `install_dir` first.
+pub(crate) fn write_env_config(
+    install_dir: &Path,
+    vars: &HashMap<&'static str, String>,
+) -> Result<()> {
+    let backup_dir = install_dir.join("backup");
+    utils::ensure_dir(&backup_dir)?;
+    for sh in shell::get_available_shells() {
+        // This string will be wrapped in a certain identifier comments.
+        for rc in sh.update_rcs() {
+            // Do NOT fail installation if backup fails
+            _ = create_backup_for_rc(&rc, &backup_dir);
+
+            let old_content = utils::read_to_string("rc", &rc).unwrap_or_default();
+            let new_content = rc_content_with_env_vars(sh.as_ref(), &old_content, vars);
+
+            utils::write_file(&rc, &new_content, false).with_context(|| {
+                format!(
+                    "failed to append environment vars to shell profile: '{}'",
+                    rc.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Return the rc files that the env vars would be written to, of every available shell.
+pub(crate) fn rc_files_to_update() -> Vec<PathBuf> {
+    shell::get_available_shells()
+        .flat_map(|sh| sh.update_rcs())
+        .collect()
+}
+
+/// The configuration section written by this program in one of the rc files.
+#[derive(Debug)]
+pub(crate) struct ConfigSection {
+    /// Name of the shell, such as `bash`, `zsh`.
+    pub(crate) shell: &'static str,
+    pub(crate) rc: PathBuf,
+    /// Env vars set in the section, or `None` if the rc file has no such section.
+    pub(crate) vars: Option<HashMap<String, String>>,
+    /// Paths added to `PATH` in the section.
+    pub(crate) paths: Vec<PathBuf>,
+}
+
+/// Read the configuration sections in the rc files that we write to,
+/// of every available shell.
+pub(crate) fn inspect_config_sections() -> Vec<ConfigSection> {
+    let mut sections = vec![];
+    for sh in shell::get_available_shells() {
+        for rc in sh.update_rcs() {
+            let content = utils::read_to_string("rc", &rc).unwrap_or_default();
+            let section = get_sub_string_between(
+                &content,
+                shell::RC_FILE_SECTION_START,
+                shell::RC_FILE_SECTION_END,
+            );
+            let mut paths = vec![];
+            let vars = section.map(|sec| {
+                let mut vars = HashMap::new();
+                for (key, val) in sec.lines().filter_map(|l| sh.parse_env_var_string(l)) {
+                    if key == "PATH" {
+                        paths.extend(
+                            val.split(sh.path_separator())
+                                .filter(|p| !p.is_empty() && *p != "$PATH")
+                                .map(PathBuf::from),
+                        );
+                    } else {
+                        vars.insert(key.to_string(), val.to_string());
+                    }
+                }
+                vars
+            });
+            sections.push(ConfigSection {
+                shell: sh.name(),
+                rc,
+                vars,
+                paths,
+            });
+        }
+    }
+    sections
+}
+
+/// Write the completion scripts of `bin` for every available shell that supports it,
+/// where `script` returns the content of the script by the name of a shell.
+///
+/// Return the paths of the written scripts.
+pub(crate) fn install_completions<F>(
+    install_dir: &Path,
+    bin: &str,
+    script: F,
+) -> Result<Vec<PathBuf>>
+where
+    F: Fn(&str) -> Result<String>,
+{
+    let mut written = vec![];
+    for sh in shell::get_available_shells() {
+        let Some(path) = sh.completion_script(install_dir, bin) else {
+            continue;
+        };
+        utils::ensure_parent_dir(&path)?;
+        utils::write_file(&path, &script(sh.name())?, false)?;
+
+        if let Some(line) = sh.completion_config(&path) {
+            for rc in sh.update_rcs() {
+                let old_content = utils::read_to_string("rc", &rc).unwrap_or_default();
+                let Some(new_content) = rc_content_with_line(sh.as_ref(), &old_content, &line)
+                else {
+                    continue;
+                };
+                utils::write_file(&rc, &new_content, false).with_context(|| {
+                    format!(
+                        "failed to write completion config to shell profile: '{}'",
+                        rc.display()
+                    )
+                })?;
+            }
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Remove the completion scripts written by [`install_completions`].
+pub(crate) fn remove_completions(install_dir: &Path, bin: &str) -> Result<()> {
+    for sh in shell::get_available_shells() {
+        if let Some(path) = sh.completion_script(install_dir, bin) {
+            if path.is_file() {
+                utils::remove(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 /// In case we mess up the user environment
 fn create_backup_for_rc(path: &Path, backup_dir: &Path) -> Result<()> {
     // Safe to unwrap as long as the path is one of the `sh.update_rcs()`
@@ -79,6 +200,10 @@ impl Uninstallation for UninstallConfiguration<'_> {
     }
 
     fn remove_self(&self) -> Result<()> {
+        let manager_name = format!("{}-manager", t!("vendor_en"));
+        if let Err(e) = remove_completions(&self.install_dir, &manager_name) {
+            warn!("{}: {e}", t!("remove_completions_failed"));
+        }
         // Remove the installer dir.
         std::fs::remove_dir_all(&self.install_dir)?;
         Ok(())
@@ -213,6 +338,32 @@ fn modify_path(path: &Path, remove: bool) -> Result<()> {
     Ok(())
 }
 
+/// Add a line to the config section, return `None` if the line already exists.
+fn rc_content_with_line(
+    sh: &dyn shell::UnixShell,
+    old_content: &str,
+    line: &str,
+) -> Option<String> {
+    let lines = old_content.lines().collect::<Vec<_>>();
+    let start = lines
+        .iter()
+        .position(|l| *l == shell::RC_FILE_SECTION_START);
+    let end = lines.iter().position(|l| *l == shell::RC_FILE_SECTION_END);
+    let (Some(start), Some(end)) = (start, end) else {
+        return Some(format!("{old_content}\n{}", sh.config_section(line)));
+    };
+    if lines[start..end].contains(&line) {
+        return None;
+    }
+    let mut new_lines = lines;
+    new_lines.insert(end, line);
+    let mut new_content = new_lines.join("\n");
+    if old_content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Some(new_content)
+}
+
 fn rc_content_with_env_vars(
     sh: &dyn shell::UnixShell,
     old_content: &str,
@@ -305,6 +456,35 @@ pub(crate) fn source_command() -> Option<String> {
     Some(format!("source \"{}\"", any_rc.display()))
 }
 
+/// Return the commands that set `vars` and add `paths` to `PATH` in current session of `shell`,
+/// which are the same as the ones written into its rc files.
+///
+/// Paths are expected in the order of being added, the last one will come first in `PATH`.
+pub(crate) fn env_script(
+    shell: &str,
+    vars: &HashMap<&'static str, String>,
+    paths: &[PathBuf],
+) -> Result<String> {
+    let sh =
+        shell::get_shell_by_name(shell).with_context(|| format!("unsupported shell '{shell}'"))?;
+
+    let mut vars = vars.iter().collect::<Vec<_>>();
+    vars.sort();
+    let mut lines = vars
+        .into_iter()
+        .map(|(key, val)| sh.to_env_var_string(key, val))
+        .collect::<Vec<_>>();
+
+    let mut path_cmd: Option<String> = None;
+    for path in paths.iter().collect::<IndexSet<_>>() {
+        let path_str = utils::path_to_str(path)?;
+        path_cmd = sh.command_to_update_path(path_cmd.as_deref(), path_str, false);
+    }
+    lines.extend(path_cmd);
+
+    Ok(lines.join("\n"))
+}
+
 /// Unix shell module, contains methods that are dedicated in configuring rustup env vars.
 // TODO?: Most code in this module are modified from rustup's `shell.rs`, this is not ideal for long term,
 // as the file in rustup could change drasically in the future and somehow we'll need to update
@@ -315,7 +495,10 @@ mod shell {
 
     use crate::utils;
     use anyhow::{bail, Result};
-    use std::{env, path::PathBuf};
+    use std::{
+        env,
+        path::{Path, PathBuf},
+    };
 
     type Shell = Box<dyn UnixShell>;
 
@@ -334,11 +517,37 @@ mod shell {
         // Gives rcs that should be written to.
         fn update_rcs(&self) -> Vec<PathBuf>;
 
+        /// Name of the shell.
+        fn name(&self) -> &'static str;
+
         /// Format a shell command to set env var.
         fn to_env_var_string(&self, key: &'static str, val: &str) -> String {
             format!("export {key}={val}")
         }
 
+        /// Parse a command written by [`to_env_var_string`](UnixShell::to_env_var_string)
+        /// back to a key-value pair, the quotes around the value are removed.
+        fn parse_env_var_string<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+            let (key, val) = line.trim().strip_prefix("export ")?.split_once('=')?;
+            Some((key.trim(), unquote(val.trim())))
+        }
+
+        /// The separator between paths in the value of `PATH` export command.
+        fn path_separator(&self) -> char {
+            ':'
+        }
+
+        /// Path to write the completion script of `bin`, or `None` if it is not supported.
+        fn completion_script(&self, _install_dir: &Path, _bin: &str) -> Option<PathBuf> {
+            None
+        }
+
+        /// A command to put in the config section, for the shell to find the completion script,
+        /// or `None` if the script is loaded automatically.
+        fn completion_config(&self, _script: &Path) -> Option<String> {
+            None
+        }
+
         /// Wraps given content between a pair of identifiers.
         ///
         /// Such identifiers are comments defined as [`RC_FILE_SECTION_START`] and [`RC_FILE_SECTION_END`].
@@ -380,12 +589,23 @@ mod shell {
         }
     }
 
+    fn unquote(val: &str) -> &str {
+        ['"', '\'']
+            .iter()
+            .find_map(|q| val.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+            .unwrap_or(val)
+    }
+
     pub(super) struct Posix;
     pub(super) struct Bash;
     pub(super) struct Zsh;
     pub(super) struct Fish;
 
     impl UnixShell for Posix {
+        fn name(&self) -> &'static str {
+            "sh"
+        }
+
         fn does_exist(&self) -> bool {
             true
         }
@@ -402,6 +622,10 @@ mod shell {
     }
 
     impl UnixShell for Bash {
+        fn name(&self) -> &'static str {
+            "bash"
+        }
+
         fn does_exist(&self) -> bool {
             !self.update_rcs().is_empty()
         }
@@ -421,6 +645,20 @@ mod shell {
                 .filter(|rc| rc.is_file())
                 .collect()
         }
+
+        // Scripts in this directory are loaded on demand by `bash-completion`.
+        fn completion_script(&self, _install_dir: &Path, bin: &str) -> Option<PathBuf> {
+            let data_dir = env::var_os("XDG_DATA_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| utils::home_dir().join(".local").join("share"));
+            Some(
+                data_dir
+                    .join("bash-completion")
+                    .join("completions")
+                    .join(bin),
+            )
+        }
     }
 
     impl Zsh {
@@ -448,6 +686,10 @@ mod shell {
     }
 
     impl UnixShell for Zsh {
+        fn name(&self) -> &'static str {
+            "zsh"
+        }
+
         fn does_exist(&self) -> bool {
             // zsh has to either be the shell or be callable for zsh setup.
             matches!(env::var("SHELL"), Ok(sh) if sh.contains("zsh")) || utils::cmd_exist("zsh")
@@ -474,9 +716,24 @@ mod shell {
                 .take(1)
                 .collect()
         }
+
+        // There's no user level directory that zsh looks for completions by default,
+        // so the script is put in the installation, which is then added to `fpath`.
+        fn completion_script(&self, install_dir: &Path, bin: &str) -> Option<PathBuf> {
+            Some(install_dir.join("completions").join(format!("_{bin}")))
+        }
+
+        fn completion_config(&self, script: &Path) -> Option<String> {
+            let dir = script.parent()?;
+            Some(format!("fpath=(\"{}\" $fpath)", dir.display()))
+        }
     }
 
     impl UnixShell for Fish {
+        fn name(&self) -> &'static str {
+            "fish"
+        }
+
         fn does_exist(&self) -> bool {
             // fish has to either be the shell or be callable for fish setup.
             matches!(env::var("SHELL"), Ok(sh) if sh.contains("fish")) || utils::cmd_exist("fish")
@@ -498,6 +755,15 @@ mod shell {
             format!("set -Ux {key} {val}")
         }
 
+        fn parse_env_var_string<'a>(&self, line: &'a str) -> Option<(&'a str, &'a str)> {
+            let (key, val) = line.trim().strip_prefix("set -Ux ")?.split_once(' ')?;
+            Some((key.trim(), unquote(val.trim())))
+        }
+
+        fn path_separator(&self) -> char {
+            ' '
+        }
+
         fn update_rcs(&self) -> Vec<PathBuf> {
             // The first rcfile takes precedence.
             match self.rcfiles().into_iter().next() {
@@ -506,6 +772,20 @@ mod shell {
             }
         }
 
+        // Scripts in this directory are loaded on demand by fish.
+        fn completion_script(&self, _install_dir: &Path, bin: &str) -> Option<PathBuf> {
+            let config_dir = env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| utils::home_dir().join(".config"));
+            Some(
+                config_dir
+                    .join("fish")
+                    .join("completions")
+                    .join(format!("{bin}.fish")),
+            )
+        }
+
         fn command_to_update_path(
             &self,
             old_command: Option<&str>,
@@ -530,6 +810,118 @@ mod shell {
         }
     }
 
+    /// Shells that no rc file will be written for,
+    /// they are only used to print the commands that set up a session.
+    pub(super) struct Nu;
+    pub(super) struct Pwsh;
+
+    impl UnixShell for Nu {
+        fn name(&self) -> &'static str {
+            "nu"
+        }
+
+        fn does_exist(&self) -> bool {
+            false
+        }
+
+        fn rcfiles(&self) -> Vec<PathBuf> {
+            vec![]
+        }
+
+        fn update_rcs(&self) -> Vec<PathBuf> {
+            vec![]
+        }
+
+        fn to_env_var_string(&self, key: &'static str, val: &str) -> String {
+            format!("$env.{key} = '{val}'")
+        }
+
+        fn command_to_update_path(
+            &self,
+            old_command: Option<&str>,
+            path_str: &str,
+            remove: bool,
+        ) -> Option<String> {
+            let quoted = format!("'{path_str}'");
+            if let Some(cmd) = old_command {
+                if remove {
+                    Some(cmd.replace(&format!("{quoted} "), "").replace(&quoted, ""))
+                } else {
+                    let where_to_insert = cmd.find('[')? + 1;
+                    let mut new_cmd = cmd.to_string();
+                    new_cmd.insert_str(where_to_insert, &format!("{quoted} "));
+                    Some(new_cmd)
+                }
+            } else {
+                if remove {
+                    None
+                } else {
+                    Some(format!("$env.PATH = ($env.PATH | prepend [{quoted}])"))
+                }
+            }
+        }
+    }
+
+    impl UnixShell for Pwsh {
+        fn name(&self) -> &'static str {
+            "pwsh"
+        }
+
+        fn does_exist(&self) -> bool {
+            false
+        }
+
+        fn rcfiles(&self) -> Vec<PathBuf> {
+            vec![]
+        }
+
+        fn update_rcs(&self) -> Vec<PathBuf> {
+            vec![]
+        }
+
+        fn to_env_var_string(&self, key: &'static str, val: &str) -> String {
+            format!("$env:{key} = '{val}'")
+        }
+
+        fn command_to_update_path(
+            &self,
+            old_command: Option<&str>,
+            path_str: &str,
+            remove: bool,
+        ) -> Option<String> {
+            let path_str_with_spliter = format!("{path_str}:");
+            if let Some(cmd) = old_command {
+                if remove {
+                    Some(cmd.replace(&path_str_with_spliter, ""))
+                } else {
+                    let where_to_insert = cmd.find('\"')? + 1;
+                    let mut new_cmd = cmd.to_string();
+                    new_cmd.insert_str(where_to_insert, &path_str_with_spliter);
+                    Some(new_cmd)
+                }
+            } else {
+                if remove {
+                    None
+                } else {
+                    Some(format!("$env:PATH = \"{path_str_with_spliter}$env:PATH\""))
+                }
+            }
+        }
+    }
+
+    /// Get a supported shell by its [`name`](UnixShell::name), whether it exists or not.
+    pub(super) fn get_shell_by_name(name: &str) -> Option<Shell> {
+        let all_shells: Vec<Shell> = vec![
+            Box::new(Posix),
+            Box::new(Bash),
+            Box::new(Zsh),
+            Box::new(Fish),
+            Box::new(Nu),
+            Box::new(Pwsh),
+        ];
+        all_shells.into_iter().find(|sh| sh.name() == name)
+    }
+
     pub(super) fn get_available_shells() -> impl Iterator<Item = Shell> {
         let supported_shells: Vec<Shell> = vec![
             Box::new(Posix),
@@ -771,6 +1163,59 @@ export PATH=/path/to/bin:$PATH
         assert_eq!(new_rc.unwrap(), existing_rc,);
     }
 
+    #[test]
+    fn parse_env_var_commands() {
+        let bash = shell::Bash;
+        assert_eq!(
+            bash.parse_env_var_string("export CARGO_HOME='/path/to/cargo'"),
+            Some(("CARGO_HOME", "/path/to/cargo"))
+        );
+        assert_eq!(
+            bash.parse_env_var_string(r#"export PATH="/path/to/bin:$PATH""#),
+            Some(("PATH", "/path/to/bin:$PATH"))
+        );
+        assert_eq!(bash.parse_env_var_string("alias ll='ls -l'"), None);
+
+        let fish = shell::Fish;
+        assert_eq!(
+            fish.parse_env_var_string("set -Ux PATH /path/to/bin $PATH"),
+            Some(("PATH", "/path/to/bin $PATH"))
+        );
+    }
+
+    #[test]
+    fn add_line_to_config_section() {
+        let shell = shell::Zsh;
+        let existing_rc = r#"export EDITOR=vim
+
+# ===== rustup config section START =====
+export CARGO_HOME='/path/to/cargo'
+# ===== rustup config section END =====
+"#;
+        let line = r#"fpath=("/path/to/completions" $fpath)"#;
+        let new_rc = super::rc_content_with_line(&shell, existing_rc, line).unwrap();
+        assert_eq!(
+            new_rc,
+            r#"export EDITOR=vim
+
+# ===== rustup config section START =====
+export CARGO_HOME='/path/to/cargo'
+fpath=("/path/to/completions" $fpath)
+# ===== rustup config section END =====
+"#
+        );
+        assert!(super::rc_content_with_line(&shell, &new_rc, line).is_none());
+
+        let new_rc = super::rc_content_with_line(&shell, "export EDITOR=vim", line).unwrap();
+        assert_eq!(
+            new_rc,
+            r#"export EDITOR=vim
+# ===== rustup config section START =====
+fpath=("/path/to/completions" $fpath)
+# ===== rustup config section END ====="#
+        );
+    }
+
     #[test]
     fn insert_path_fish() {
         let shell = shell::Fish;
@@ -850,6 +1295,50 @@ export PATH=/some/user/defined/bin:$PATH
         );
     }
 
+    #[test]
+    fn env_script_of_shells() {
+        use std::collections::HashMap;
+
+        let vars = HashMap::from([
+            ("RUSTUP_HOME", "/opt/rust/.rustup".to_string()),
+            ("CARGO_HOME", "/opt/rust/.cargo".to_string()),
+        ]);
+        let paths = [
+            PathBuf::from("/opt/rust"),
+            PathBuf::from("/opt/rust/.cargo/bin"),
+        ];
+
+        let script = super::env_script("bash", &vars, &paths).unwrap();
+        assert_eq!(
+            script,
+            "export CARGO_HOME=/opt/rust/.cargo\n\
+            export RUSTUP_HOME=/opt/rust/.rustup\n\
+            export PATH=\"/opt/rust/.cargo/bin:/opt/rust:$PATH\""
+        );
+        let script = super::env_script("fish", &vars, &paths).unwrap();
+        assert_eq!(
+            script,
+            "set -Ux CARGO_HOME /opt/rust/.cargo\n\
+            set -Ux RUSTUP_HOME /opt/rust/.rustup\n\
+            set -Ux PATH /opt/rust/.cargo/bin /opt/rust $PATH"
+        );
+        let script = super::env_script("nu", &vars, &paths).unwrap();
+        assert_eq!(
+            script,
+            "$env.CARGO_HOME = '/opt/rust/.cargo'\n\
+            $env.RUSTUP_HOME = '/opt/rust/.rustup'\n\
+            $env.PATH = ($env.PATH | prepend ['/opt/rust/.cargo/bin' '/opt/rust'])"
+        );
+        let script = super::env_script("pwsh", &vars, &paths).unwrap();
+        assert_eq!(
+            script,
+            "$env:CARGO_HOME = '/opt/rust/.cargo'\n\
+            $env:RUSTUP_HOME = '/opt/rust/.rustup'\n\
+            $env:PATH = \"/opt/rust/.cargo/bin:/opt/rust:$env:PATH\""
+        );
+        assert!(super::env_script("cmd", &vars, &paths).is_err());
+    }
+
     #[test]
     fn add_path_to_config_section_with_existing_path() {
         let existing_rc = r#"\
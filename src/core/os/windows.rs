@@ -38,7 +38,7 @@ impl Uninstallation for UninstallConfiguration<'_> {
     }
 
     fn remove_self(&self) -> Result<()> {
-        do_remove_from_programs(uninstall_entry())?;
+        remove_from_programs(&self.install_dir)?;
         remove_from_path(&self.install_dir)?;
 
         let current_exe = current_exe()?;
@@ -55,6 +55,13 @@ impl Uninstallation for UninstallConfiguration<'_> {
             }
         }
 
+        // Nothing left to do if this program is not part of the installation,
+        // which is the case when managing another installation with `--root`.
+        if !current_exe.starts_with(&self.install_dir) {
+            _ = utils::remove(&self.install_dir);
+            return Ok(());
+        }
+
         // remove current exe
         self_replace::self_delete()?;
         // remove parent dir, which should be empty by now, and should be very quick to remove.
@@ -64,37 +71,93 @@ impl Uninstallation for UninstallConfiguration<'_> {
     }
 }
 
+/// Return the commands that set `vars` and add `paths` to `PATH` in current session of `shell`.
+///
+/// Only PowerShell is supported on Windows, as env vars are written into the registry
+/// instead of the rc files of shells.
+pub(crate) fn env_script(
+    shell: &str,
+    vars: &std::collections::HashMap<&'static str, String>,
+    paths: &[std::path::PathBuf],
+) -> Result<String> {
+    if shell != "pwsh" {
+        anyhow::bail!("unsupported shell '{shell}', only 'pwsh' is supported on Windows");
+    }
+
+    let mut vars = vars.iter().collect::<Vec<_>>();
+    vars.sort();
+    let mut lines = vars
+        .into_iter()
+        .map(|(key, val)| format!("$env:{key} = '{val}'"))
+        .collect::<Vec<_>>();
+    if !paths.is_empty() {
+        let mut path_val = String::new();
+        for path in paths.iter().rev() {
+            path_val.push_str(utils::path_to_str(path)?);
+            path_val.push(';');
+        }
+        lines.push(format!("$env:PATH = \"{path_val}$env:PATH\""));
+    }
+
+    Ok(lines.join("\n"))
+}
+
 /// A module that contains functions that are modified from `rustup`:
 /// https://github.com/rust-lang/rustup/blob/master/src/cli/self_update/windows.rs
 pub(crate) mod rustup {
     use super::GlobalOpts;
     use anyhow::{anyhow, Context, Result};
+    use sha2::{Digest, Sha256};
     use std::env;
     use std::ffi::OsString;
     use std::os::windows::ffi::{OsStrExt, OsStringExt};
     use std::path::Path;
-    use std::sync::OnceLock;
     use winapi::shared::minwindef;
     use winapi::um::winuser;
     use winreg::enums::{RegType, HKEY_CURRENT_USER, KEY_READ, KEY_WRITE};
     use winreg::{RegKey, RegValue};
 
-    static UNINSTALL_ENTRY: OnceLock<String> = OnceLock::new();
+    const UNINSTALL_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Uninstall";
+
+    /// Get the registry key of the uninstall entry of the installation at `install_dir`.
+    ///
+    /// Each installation has its own entry, so that uninstalling one of them
+    /// does not remove the entry of the others.
+    pub(crate) fn uninstall_entry(install_dir: &Path) -> String {
+        let path = install_dir.to_string_lossy().to_lowercase();
+        let key = format!("{:x}", Sha256::digest(path.as_bytes()));
+        format!("{UNINSTALL_KEY}\\{}-{}", env!("CARGO_PKG_NAME"), &key[..12])
+    }
 
-    pub(super) fn uninstall_entry() -> &'static str {
-        UNINSTALL_ENTRY.get_or_init(|| {
-            format!(
-                "Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{}",
-                env!("CARGO_PKG_NAME")
-            )
-        })
+    /// Remove the uninstall entry of the installation at `install_dir`.
+    ///
+    /// The entry shared by every installation in older versions is removed as well,
+    /// but only if it was created for this installation.
+    pub(crate) fn remove_from_programs(install_dir: &Path) -> Result<()> {
+        do_remove_from_programs(&uninstall_entry(install_dir))?;
+
+        let legacy_entry = format!("{UNINSTALL_KEY}\\{}", env!("CARGO_PKG_NAME"));
+        let legacy_program = RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(&legacy_entry)
+            .and_then(|key| key.get_raw_value("UninstallString"))
+            .ok()
+            .and_then(|val| from_winreg_value(&val))
+            .map(|s| OsString::from_wide(&s).to_string_lossy().to_lowercase());
+        let install_dir = install_dir.to_string_lossy().to_lowercase();
+        if legacy_program.is_some_and(|cmd| cmd.trim_start_matches('"').starts_with(&install_dir)) {
+            do_remove_from_programs(&legacy_entry)?;
+        }
+        Ok(())
     }
 
     pub(crate) fn do_add_to_programs(program_bin: &Path) -> Result<()> {
         use std::path::PathBuf;
 
+        let install_dir = program_bin
+            .parent()
+            .context("manager binary is not in an installation directory")?;
         let key = RegKey::predef(HKEY_CURRENT_USER)
-            .create_subkey(uninstall_entry())
+            .create_subkey(uninstall_entry(install_dir))
             .context("Failed creating uninstall key")?
             .0;
 
@@ -129,6 +192,8 @@ pub(crate) mod rustup {
             &format!("{} Rust Installation Manager", t!("vendor")),
         )
         .context("Failed to set `DisplayName`")?;
+        key.set_value("InstallLocation", &install_dir.as_os_str())
+            .context("Failed to set `InstallLocation`")?;
 
         Ok(())
     }
@@ -1,14 +1,33 @@
 //! The major configuration file for this app, containing information about which version to skip,
-//! when the updates are checked, how long until next updates will be checked etc.
+//! when the updates are checked, how long until next updates will be checked,
+//! which components are pinned, how packages are downloaded etc.
 
 use super::{get_installed_dir, TomlParser};
-use anyhow::Result;
+use crate::components::Component;
+use crate::utils::RetryPolicy;
+use anyhow::{bail, Result};
 use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 
 /// Default update check timeout is 1440 minutes (1 day)
 const DEFAULT_UPDATE_CHECK_TIMEOUT_IN_MINUTES: u64 = 1440;
+/// Default number of packages to download at the same time
+const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
+/// Env var to override the number of packages to download at the same time
+const DOWNLOAD_CONCURRENCY_ENV: &str = "RIM_DOWNLOAD_CONCURRENCY";
+/// Default size limit of the download cache in MiB
+const DEFAULT_CACHE_SIZE_LIMIT_IN_MIB: u64 = 2048;
+/// Env var to override the number of retries after a download failed
+const DOWNLOAD_RETRIES_ENV: &str = "RIM_DOWNLOAD_RETRIES";
+/// Env var to override the delay in milliseconds before the first retry of a download
+const DOWNLOAD_RETRY_DELAY_ENV: &str = "RIM_DOWNLOAD_RETRY_DELAY";
 /// Default update check timeout in duration
 pub const DEFAULT_UPDATE_CHECK_DURATION: Duration =
     Duration::from_secs(60 * DEFAULT_UPDATE_CHECK_TIMEOUT_IN_MINUTES);
@@ -24,6 +43,10 @@ pub struct Configuration {
     #[serde(default = "default_autostart_policy")]
     pub autostart: bool,
     pub update: UpdateCheckerOpt,
+    #[serde(default, skip_serializing_if = "Pins::is_empty")]
+    pub pins: Pins,
+    #[serde(default, skip_serializing_if = "DownloadConf::is_default")]
+    pub download: DownloadConf,
 }
 
 impl Default for Configuration {
@@ -31,6 +54,8 @@ impl Default for Configuration {
         Self {
             autostart: default_autostart_policy(),
             update: UpdateCheckerOpt::default(),
+            pins: Pins::default(),
+            download: DownloadConf::default(),
         }
     }
 }
@@ -207,6 +232,167 @@ impl UpdateCheckerOpt {
     }
 }
 
+/// Components pinned by the user, which are skipped when updating toolkit.
+///
+/// Toolchain components always share the same version, therefore pinning any of them
+/// pins the whole toolchain.
+///
+/// # Configuration example
+///
+/// ```toml
+/// [pins]
+/// toolchain = "1.81.0" # the version of pinned Rust toolchain
+///
+/// [pins.tools]
+/// cargo-llvm-cov = "0.6.11" # tool names and the versions they were pinned at
+/// ```
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Pins {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toolchain: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tools: BTreeMap<String, String>,
+}
+
+impl Pins {
+    pub fn is_empty(&self) -> bool {
+        self.toolchain.is_none() && self.tools.is_empty()
+    }
+
+    /// Pin a component at its current version.
+    ///
+    /// Return `false` if it was already pinned.
+    ///
+    /// # Error
+    /// Return error if the installed version of the component is unknown.
+    pub fn pin(&mut self, comp: &Component) -> Result<bool> {
+        if self.is_pinned(comp) {
+            return Ok(false);
+        }
+        let Some(version) = comp.version.clone() else {
+            bail!("{}", t!("pin_version_unknown", name = comp.name));
+        };
+        if comp.kind.is_from_toolchain() {
+            self.toolchain = Some(version);
+        } else {
+            self.tools.insert(comp.name.clone(), version);
+        }
+        Ok(true)
+    }
+
+    /// Unpin a component, return `false` if it wasn't pinned.
+    pub fn unpin(&mut self, comp: &Component) -> bool {
+        if comp.kind.is_from_toolchain() {
+            self.toolchain.take().is_some()
+        } else {
+            self.tools.remove(&comp.name).is_some()
+        }
+    }
+
+    /// Return `true` if the given component should be skipped when updating toolkit.
+    pub fn is_pinned(&self, comp: &Component) -> bool {
+        if comp.kind.is_from_toolchain() {
+            self.toolchain.is_some()
+        } else {
+            self.tools.contains_key(&comp.name)
+        }
+    }
+
+    /// The version of pinned Rust toolchain.
+    pub fn toolchain(&self) -> Option<&str> {
+        self.toolchain.as_deref()
+    }
+
+    /// Iterate over the names of pinned tools and the versions they were pinned at.
+    pub fn tools(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tools.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Options of downloading packages.
+///
+/// # Configuration example
+///
+/// ```toml
+/// [download]
+/// concurrency = 4 # the maximum number of packages to download at the same time
+/// cache-size-limit = 2048 # the maximum size of the download cache in MiB
+/// retries = 3 # the number of retries after a download failed with transient errors
+/// retry-delay = 1000 # the delay in milliseconds before the first retry, doubled for each retry
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DownloadConf {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    concurrency: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_size_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_delay: Option<u64>,
+}
+
+impl DownloadConf {
+    /// Load the download options from the configuration file under `install_dir`,
+    /// the default is returned if the file does not exist, such as in a fresh installation.
+    pub fn load(install_dir: &Path) -> Self {
+        Configuration::load_from_dir(install_dir)
+            .map(|conf| conf.download)
+            .unwrap_or_default()
+    }
+
+    /// Load the download options of the installation being managed,
+    /// the default is returned if there isn't one, such as when running the installer.
+    pub fn current() -> Self {
+        super::INSTALL_DIR_ONCE
+            .get()
+            .map(|dir| Self::load(dir))
+            .unwrap_or_default()
+    }
+
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// The maximum number of packages to download at the same time,
+    /// which can be overridden by env var `RIM_DOWNLOAD_CONCURRENCY`.
+    pub fn concurrency(&self) -> usize {
+        env_or(DOWNLOAD_CONCURRENCY_ENV, self.concurrency)
+            .unwrap_or(DEFAULT_DOWNLOAD_CONCURRENCY)
+            .max(1)
+    }
+
+    /// How to retry the failed downloads, the number of retries and the initial delay
+    /// can be overridden by env var `RIM_DOWNLOAD_RETRIES` and `RIM_DOWNLOAD_RETRY_DELAY`.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            retries: env_or(DOWNLOAD_RETRIES_ENV, self.retries).unwrap_or(default.retries),
+            initial_delay: env_or(DOWNLOAD_RETRY_DELAY_ENV, self.retry_delay)
+                .map(Duration::from_millis)
+                .unwrap_or(default.initial_delay),
+            ..default
+        }
+    }
+
+    /// The maximum bytes that the download cache can take, the least recently used packages
+    /// are removed from the cache when exceeded.
+    pub fn cache_size_limit(&self) -> u64 {
+        self.cache_size_limit
+            .unwrap_or(DEFAULT_CACHE_SIZE_LIMIT_IN_MIB)
+            .saturating_mul(1024 * 1024)
+    }
+}
+
+/// Get the value of env var `key` if it's valid, otherwise the configured `value`.
+fn env_or<T: FromStr>(key: &str, value: Option<T>) -> Option<T> {
+    std::env::var(key)
+        .ok()
+        .and_then(|val| val.parse().ok())
+        .or(value)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +431,97 @@ manager = { last-run = "1970-01-01T00:00:00" }"#;
         expected = expected.remind_later(manager, 60);
         assert_eq!(expected.conf_mut(manager).timeout, Some(120));
     }
+
+    #[test]
+    fn pin_components() {
+        use crate::components::ComponentType;
+
+        let rustfmt = Component::new("rustfmt", "")
+            .set_kind(ComponentType::ToolchainComponent)
+            .with_version(Some("1.81.0"));
+        let clippy = Component::new("clippy", "").set_kind(ComponentType::ToolchainComponent);
+        let tool = Component::new("cargo-llvm-cov", "").with_version(Some("0.6.11"));
+        let other_tool = Component::new("mdbook", "");
+
+        let mut pins = Pins::default();
+        assert!(pins.pin(&rustfmt).unwrap());
+        assert!(!pins.pin(&clippy).unwrap());
+        assert!(pins.pin(&tool).unwrap());
+        // cannot be pinned without knowing its version
+        assert!(pins.pin(&other_tool).is_err());
+        assert!(pins.is_pinned(&clippy));
+        assert!(!pins.is_pinned(&other_tool));
+        assert_eq!(pins.toolchain(), Some("1.81.0"));
+        assert_eq!(
+            pins.tools().collect::<Vec<_>>(),
+            [("cargo-llvm-cov", "0.6.11")]
+        );
+
+        let conf = Configuration {
+            pins,
+            ..Default::default()
+        };
+        let conf = Configuration::from_str(&conf.to_toml().unwrap()).unwrap();
+        let mut pins = conf.pins;
+        assert!(pins.is_pinned(&tool));
+        // unpinning any toolchain component unpins the toolchain
+        assert!(pins.unpin(&clippy));
+        assert!(!pins.is_pinned(&rustfmt));
+        assert!(!pins.unpin(&other_tool));
+        assert!(pins.unpin(&tool));
+        assert!(pins.is_empty());
+    }
+
+    #[test]
+    fn download_concurrency() {
+        let input = r#"
+[update]
+
+[download]
+concurrency = 8"#;
+        let conf = Configuration::from_str(input).unwrap();
+        assert_eq!(conf.download.concurrency(), 8);
+        assert_eq!(DownloadConf::default().concurrency(), 4);
+        // at least one package should be downloaded at a time
+        let zero = DownloadConf {
+            concurrency: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(zero.concurrency(), 1);
+    }
+
+    #[test]
+    fn download_retry_policy() {
+        let input = r#"
+[update]
+
+[download]
+retries = 5
+retry-delay = 200"#;
+        let policy = Configuration::from_str(input)
+            .unwrap()
+            .download
+            .retry_policy();
+        assert_eq!(policy.retries, 5);
+        assert_eq!(policy.initial_delay, Duration::from_millis(200));
+        assert_eq!(
+            DownloadConf::default().retry_policy(),
+            RetryPolicy::default()
+        );
+    }
+
+    #[test]
+    fn download_cache_size_limit() {
+        let input = r#"
+[update]
+
+[download]
+cache-size-limit = 100"#;
+        let conf = Configuration::from_str(input).unwrap();
+        assert_eq!(conf.download.cache_size_limit(), 100 * 1024 * 1024);
+        assert_eq!(
+            DownloadConf::default().cache_size_limit(),
+            2048 * 1024 * 1024
+        );
+    }
 }
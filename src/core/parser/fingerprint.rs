@@ -2,11 +2,12 @@ use anyhow::{anyhow, Context, Result};
 use indexmap::IndexMap;
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
 use std::path::{Path, PathBuf};
+use url::Url;
 
 use crate::{core::tools::ToolKind, setter, utils};
 
 use super::{
-    toolset_manifest::{ToolchainComponent, ToolsetManifest},
+    toolset_manifest::{Proxy, ToolchainComponent, ToolsetManifest},
     TomlParser,
 };
 
@@ -28,6 +29,9 @@ pub struct InstallationRecord {
     pub rust: Option<RustRecord>,
     #[serde(default)]
     pub tools: IndexMap<String, ToolRecord>,
+    /// The servers and registry this installation was configured with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<InstallSettings>,
 }
 
 impl TomlParser for InstallationRecord {
@@ -63,7 +67,11 @@ impl InstallationRecord {
     /// This is useful when you want to know it without causing
     /// the program to panic using [`get_installed_dir`](super::get_installed_dir).
     pub fn exists() -> Result<bool> {
-        let parent_dir = utils::parent_dir_of_cur_exe()?;
+        // respect the installation directory that was explicitly selected, such as by `--root`
+        let parent_dir = match super::INSTALL_DIR_ONCE.get() {
+            Some(dir) => dir.clone(),
+            None => utils::parent_dir_of_cur_exe()?,
+        };
         Ok(parent_dir.join(Self::FILENAME).is_file())
     }
 
@@ -115,7 +123,17 @@ impl InstallationRecord {
         self.rust = None;
     }
 
-    #[allow(unused)]
+    /// Add a toolchain component to the installation record of Rust toolchain,
+    /// do nothing if the toolchain was not installed.
+    pub(crate) fn add_component_record(&mut self, component: &str) {
+        let Some(rust) = self.rust.as_mut() else {
+            return;
+        };
+        if !rust.components.iter().any(|c| c == component) {
+            rust.components.push(component.to_string());
+        }
+    }
+
     pub fn remove_component_record(&mut self, component: &str) {
         let Some(rust) = self.rust.as_mut() else {
             return;
@@ -160,6 +178,22 @@ impl InstallationRecord {
     }
 }
 
+/// Servers and registry used by an installation.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct InstallSettings {
+    pub rustup_dist_server: Url,
+    pub rustup_update_root: Url,
+    pub cargo_registry: Option<CargoRegistry>,
+    pub proxy: Option<Proxy>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CargoRegistry {
+    pub name: String,
+    pub url: String,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct RustRecord {
@@ -194,6 +228,14 @@ pub struct ToolRecord {
     version: Option<String>,
     #[serde(default)]
     pub(crate) paths: Vec<PathBuf>,
+    /// Every single file that was placed by the installation of this tool,
+    /// used to verify the integrity of an installation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) files: Vec<FileRecord>,
+    /// The checksum of the package this tool was installed from,
+    /// only available for tools installed from a single file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) source_sha256: Option<String>,
 }
 
 impl ToolRecord {
@@ -218,6 +260,48 @@ impl ToolRecord {
 
     setter!(with_paths(self.paths, Vec<PathBuf>));
     setter!(with_version(self.version, ver: Option<impl Into<String>>) { ver.map(Into::into) });
+    setter!(with_files(self.files, Vec<FileRecord>));
+    setter!(with_source_sha256(self.source_sha256, Option<String>));
+
+    /// Collect [`FileRecord`]s of every file under the recorded `paths`,
+    /// directories are walked recursively.
+    pub(crate) fn collect_files(&self) -> Result<Vec<FileRecord>> {
+        let mut files = vec![];
+        for path in &self.paths {
+            if path.is_file() {
+                files.push(FileRecord::from_path(path)?);
+            } else if path.is_dir() {
+                for entry in utils::walk_dir(path, true)? {
+                    if entry.is_file() {
+                        files.push(FileRecord::from_path(&entry)?);
+                    }
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// The size and checksum of a single installed file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FileRecord {
+    pub path: PathBuf,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl FileRecord {
+    pub(crate) fn from_path(path: &Path) -> Result<Self> {
+        let size = path
+            .metadata()
+            .with_context(|| format!("unable to read metadata of '{}'", path.display()))?
+            .len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            size,
+            sha256: utils::file_sha256(path)?,
+        })
+    }
 }
 
 // `use-cargo = true/false` was used during [0.2.0, 0.3.0], in order not to break
@@ -368,6 +452,36 @@ paths = []
         assert_eq!(ser, expected);
     }
 
+    #[test]
+    fn add_and_remove_component_records() {
+        let mut record = InstallationRecord::default();
+        // nothing to add to if toolchain is not installed
+        record.add_component_record("clippy");
+        assert!(record.installed_toolchain().is_none());
+
+        record.add_rust_record(
+            "stable",
+            &[ToolchainComponent::new("minimal").is_profile(true)],
+        );
+        record.add_component_record("llvm-tools");
+        record.add_component_record("llvm-tools");
+        record.add_component_record("clippy");
+        assert_eq!(
+            record.installed_toolchain(),
+            Some((
+                "stable",
+                ["minimal", "llvm-tools", "clippy"]
+                    .map(String::from)
+                    .as_slice()
+            ))
+        );
+
+        record.remove_component_record("llvm-tools");
+        let (_, comps) = record.installed_toolchain().unwrap();
+        assert!(!comps.contains(&"llvm-tools".to_string()));
+        assert_eq!(comps.len(), 2);
+    }
+
     #[test]
     fn with_tool_version() {
         let input = r#"
@@ -385,4 +499,55 @@ paths = ["/some/other/path"]"#;
         assert_eq!(tools.next(), Some(None));
         assert_eq!(tools.next(), None);
     }
+
+    #[test]
+    fn with_file_records() {
+        let input = r#"
+root = '/path/to/something'
+
+[tools.a]
+kind = "dir-with-bin"
+paths = ["/path/to/something/tools/a"]
+
+[[tools.a.files]]
+path = "/path/to/something/tools/a/bin/a"
+size = 4
+sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+"#;
+
+        let rec = InstallationRecord::from_str(input).unwrap();
+        let files = &rec.tools["a"].files;
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0].path,
+            PathBuf::from("/path/to/something/tools/a/bin/a")
+        );
+        assert_eq!(files[0].size, 4);
+    }
+
+    #[test]
+    fn collect_tool_files() {
+        let root = tempfile::tempdir().unwrap();
+        let tool_dir = root.path().join("tool");
+        utils::ensure_dir(tool_dir.join("bin")).unwrap();
+        utils::write_bytes(tool_dir.join("bin").join("a"), b"abc", false).unwrap();
+        utils::write_bytes(tool_dir.join("README"), b"", false).unwrap();
+
+        let record = ToolRecord::new(ToolKind::DirWithBin).with_paths(vec![tool_dir.clone()]);
+        let mut files = record.collect_files().unwrap();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, tool_dir.join("README"));
+        assert_eq!(files[0].size, 0);
+        assert_eq!(
+            files[0].sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(files[1].path, tool_dir.join("bin").join("a"));
+        assert_eq!(
+            files[1].sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
 }
@@ -0,0 +1,169 @@
+//! A user level index of known installations, which allows a single manager to manage
+//! multiple installation roots without copying itself around.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{set_installed_dir, validate_install_dir, TomlParser};
+use crate::utils;
+
+/// Get the directory where the index file is stored,
+/// which is a directory under user's config directory (or [`home_dir`](utils::home_dir)).
+pub(crate) fn index_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(utils::home_dir)
+        .join(env!("CARGO_PKG_NAME"))
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct InstallationIndex {
+    /// The installation to manage when no root was specified explicitly.
+    pub default: Option<PathBuf>,
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl TomlParser for InstallationIndex {
+    const FILENAME: &'static str = "installations.toml";
+}
+
+impl InstallationIndex {
+    /// Load the index of current user, return an empty one if it does not exist yet.
+    pub fn load() -> Result<Self> {
+        Self::load_or_default(&index_dir())
+    }
+
+    fn load_or_default(dir: &Path) -> Result<Self> {
+        let path = dir.join(Self::FILENAME);
+        let mut index = if path.is_file() {
+            <Self as TomlParser>::load(path)?
+        } else {
+            Self::default()
+        };
+        index.dir = dir.to_path_buf();
+        Ok(index)
+    }
+
+    pub fn write(&self) -> Result<()> {
+        // NB: not using `write_to_dir` because an empty index still need to be written
+        let content = self
+            .to_toml()
+            .context("unable to serialize installation index")?;
+        utils::ensure_dir(&self.dir)?;
+        utils::write_file(self.dir.join(Self::FILENAME), &content, false)
+    }
+
+    /// Add an installation root to the index, do nothing if it was already added.
+    pub fn add(&mut self, root: &Path) {
+        if !self.contains(root) {
+            self.roots.push(root.to_path_buf());
+        }
+    }
+
+    /// Remove an installation root from the index, return `false` if it wasn't there.
+    pub fn remove(&mut self, root: &Path) -> bool {
+        if self.default.as_deref() == Some(root) {
+            self.default = None;
+        }
+        let len = self.roots.len();
+        self.roots.retain(|r| r != root);
+        self.roots.len() != len
+    }
+
+    /// Add an installation root to the index and make it the default one.
+    pub fn set_default(&mut self, root: &Path) {
+        self.add(root);
+        self.default = Some(root.to_path_buf());
+    }
+
+    pub fn contains(&self, root: &Path) -> bool {
+        self.roots.iter().any(|r| r == root)
+    }
+
+    /// Add an installation root to the index of current user, and save the index.
+    pub fn register(root: &Path) -> Result<()> {
+        let mut index = Self::load()?;
+        if !index.contains(root) {
+            index.add(root);
+            index.write()?;
+        }
+        Ok(())
+    }
+
+    /// Remove an installation root from the index of current user, and save the index.
+    pub fn unregister(root: &Path) -> Result<()> {
+        let mut index = Self::load()?;
+        if index.remove(root) {
+            index.write()?;
+        }
+        Ok(())
+    }
+}
+
+/// Choose the installation root to manage, which is `root` if provided.
+///
+/// Otherwise, a manager that is part of an installation always manages that installation,
+/// and only a manager that lives outside of any installation uses the default one in
+/// [`InstallationIndex`], which is then shown to the user to avoid managing another
+/// installation by surprise.
+pub fn select_installed_dir(root: Option<&Path>) -> Result<()> {
+    if let Some(path) = root {
+        let abs_path = utils::to_nomalized_abspath(path, None)?;
+        return set_installed_dir(&abs_path);
+    }
+
+    if utils::parent_dir_of_cur_exe().is_ok_and(|dir| validate_install_dir(&dir).is_ok()) {
+        return Ok(());
+    }
+    let Some(default) = InstallationIndex::load()?.default else {
+        return Ok(());
+    };
+    if let Err(e) = validate_install_dir(&default) {
+        warn!(
+            "{}: {e}",
+            t!("default_installation_invalid", path = default.display())
+        );
+        return Ok(());
+    }
+    info!(
+        "{}",
+        t!("managing_default_installation", path = default.display())
+    );
+    set_installed_dir(&default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = InstallationIndex::load_or_default(dir.path()).unwrap();
+        assert!(index.roots.is_empty());
+
+        let a = Path::new("/path/to/a");
+        let b = Path::new("/path/to/b");
+        index.add(a);
+        index.add(a);
+        index.set_default(b);
+        assert_eq!(index.roots, vec![a.to_path_buf(), b.to_path_buf()]);
+        assert_eq!(index.default.as_deref(), Some(b));
+        index.write().unwrap();
+
+        let mut index = InstallationIndex::load_or_default(dir.path()).unwrap();
+        assert_eq!(index.roots, vec![a.to_path_buf(), b.to_path_buf()]);
+        assert!(index.remove(b));
+        assert!(!index.remove(b));
+        assert!(index.default.is_none());
+
+        assert!(index.remove(a));
+        index.write().unwrap();
+        let index = InstallationIndex::load_or_default(dir.path()).unwrap();
+        assert!(index.roots.is_empty());
+    }
+}
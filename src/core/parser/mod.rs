@@ -2,10 +2,11 @@ pub(crate) mod cargo_config;
 pub mod configuration;
 pub mod dist_manifest;
 pub mod fingerprint;
+pub mod installations;
 pub(crate) mod release_info;
 pub mod toolset_manifest;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use fingerprint::InstallationRecord;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
@@ -14,6 +15,7 @@ use std::{
 };
 use toml::{de, ser};
 
+use super::error::{ErrorKind, ResultExt};
 use crate::utils;
 
 static INSTALL_DIR_ONCE: OnceLock<PathBuf> = OnceLock::new();
@@ -26,7 +28,7 @@ pub(crate) trait TomlParser {
     where
         Self: Sized + DeserializeOwned,
     {
-        Ok(de::from_str(from)?)
+        de::from_str(from).with_kind(ErrorKind::ManifestParse)
     }
 
     /// Serialize data of a type into [`String`].
@@ -90,30 +92,51 @@ pub(crate) trait TomlParser {
 pub fn get_installed_dir() -> &'static Path {
     fn inner_() -> Result<PathBuf> {
         let maybe_install_dir = utils::parent_dir_of_cur_exe()?;
-
-        // the first check
-        if maybe_install_dir.parent().is_none() {
-            bail!("it appears that this program was mistakenly installed in root directory");
-        }
-        // the second check
-        if !maybe_install_dir
-            .join(InstallationRecord::FILENAME)
-            .is_file()
-        {
-            bail!("installation record cannot be found");
-        }
-        // the third check
-        let fp = InstallationRecord::load_from_dir(&maybe_install_dir)
-            .context("'.fingerprint' file exists but cannot be loaded")?;
-        if fp.root != maybe_install_dir {
-            bail!(
-                "`.fingerprint` file exists but the installation root in it \n\
-                does not match the one its in"
-            );
-        }
-
+        validate_install_dir(&maybe_install_dir)?;
         Ok(maybe_install_dir.to_path_buf())
     }
 
     INSTALL_DIR_ONCE.get_or_init(|| inner_().expect("unable to determine install dir"))
 }
+
+/// Use another installation directory instead of the one containing current executable,
+/// so that [`get_installed_dir`] will return it afterwards.
+///
+/// # Error
+/// Return error if `root` fails the checks in [`get_installed_dir`],
+/// or if the installation directory has already been determined.
+pub(crate) fn set_installed_dir(root: &Path) -> Result<()> {
+    validate_install_dir(root)?;
+    INSTALL_DIR_ONCE
+        .set(root.to_path_buf())
+        .map_err(|_| anyhow!("installation directory has already been determined"))
+}
+
+/// Check if a directory looks like an installation directory,
+/// see [`get_installed_dir`] for the checks.
+pub(crate) fn validate_install_dir(maybe_install_dir: &Path) -> Result<()> {
+    // the first check
+    if maybe_install_dir.parent().is_none() {
+        bail!("it appears that this program was mistakenly installed in root directory");
+    }
+    // the second check
+    if !maybe_install_dir
+        .join(InstallationRecord::FILENAME)
+        .is_file()
+    {
+        bail!(
+            "installation record cannot be found in '{}'",
+            maybe_install_dir.display()
+        );
+    }
+    // the third check
+    let fp = InstallationRecord::load_from_dir(maybe_install_dir)
+        .context("'.fingerprint' file exists but cannot be loaded")?;
+    if fp.root != maybe_install_dir {
+        bail!(
+            "`.fingerprint` file exists but the installation root in it \n\
+            does not match the one its in"
+        );
+    }
+    Ok(())
+}
@@ -404,7 +404,6 @@ pub(crate) struct RustToolchain {
 }
 
 impl RustToolchain {
-    #[allow(unused)]
     pub(crate) fn new(ver: &str) -> Self {
         Self {
             version: ver.to_string(),
@@ -454,7 +453,6 @@ pub(crate) struct Tools {
 }
 
 impl Tools {
-    #[allow(unused)]
     pub(crate) fn new<I>(targeted_tools: I) -> Tools
     where
         I: IntoIterator<Item = (String, ToolMap)>,
@@ -581,6 +579,11 @@ fn baked_in_manifest_raw() -> &'static str {
     }
 }
 
+/// Load the toolset manifest that was built into this program.
+pub(crate) fn baked_in_manifest() -> Result<ToolsetManifest> {
+    ToolsetManifest::from_str(baked_in_manifest_raw())
+}
+
 /// Get a [`ToolsetManifest`] by either:
 ///
 /// - Download from specific url, which could have file schema.
@@ -619,7 +622,7 @@ pub async fn get_toolset_manifest(url: Option<Url>, insecure: bool) -> Result<To
         ToolsetManifest::load(temp.path())
     } else {
         debug!("loading built-in toolset manifest");
-        ToolsetManifest::from_str(baked_in_manifest_raw())
+        baked_in_manifest()
     }?;
     debug!("caching toolset manifest in memory");
     guard.insert(url, manifest.clone());
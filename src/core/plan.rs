@@ -0,0 +1,175 @@
+//! The plan of an operation, which describes what would be changed by it,
+//! computed without changing anything, so it can be reviewed with `--dry-run`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::history::Action;
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ChangeKind {
+    Install,
+    Update,
+    Remove,
+}
+
+/// A component (or the toolchain itself) that would be changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Change {
+    pub(crate) kind: ChangeKind,
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) to: Option<String>,
+}
+
+/// The disk space needed by the packages that would be installed.
+///
+/// Only the size of local packages is known, the others are listed in
+/// [`unknown`](DiskSpace::unknown), as they are not downloaded in a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct DiskSpace {
+    pub(crate) bytes: u64,
+    pub(crate) unknown: Vec<String>,
+}
+
+impl DiskSpace {
+    /// Add the size of a local package `path` to the total, which could be a file or directory.
+    pub(crate) fn add_path(&mut self, name: &str, path: &Path) {
+        match size_of(path) {
+            Some(size) => self.bytes += size,
+            None => self.unknown.push(name.to_string()),
+        }
+    }
+
+    pub(crate) fn add_unknown(&mut self, name: &str) {
+        self.unknown.push(name.to_string());
+    }
+}
+
+fn size_of(path: &Path) -> Option<u64> {
+    let meta = path.metadata().ok()?;
+    if meta.is_file() {
+        return Some(meta.len());
+    }
+    let size = utils::walk_dir(path, true)
+        .ok()?
+        .iter()
+        .filter_map(|p| p.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum();
+    Some(size)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Plan {
+    pub(crate) action: Action,
+    pub(crate) install_dir: PathBuf,
+    pub(crate) changes: Vec<Change>,
+    /// Urls of the packages that would be downloaded.
+    pub(crate) downloads: Vec<String>,
+    /// Commands that would be run, such as `cargo install`.
+    pub(crate) commands: Vec<String>,
+    /// Env vars that would be set, or removed if the value is `None`.
+    pub(crate) env_vars: BTreeMap<String, Option<String>>,
+    pub(crate) path_added: Vec<PathBuf>,
+    pub(crate) path_removed: Vec<PathBuf>,
+    /// Shell rc files that would be modified.
+    pub(crate) rc_files: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) disk_space: Option<DiskSpace>,
+}
+
+impl Plan {
+    pub(crate) fn new(action: Action, install_dir: &Path) -> Self {
+        Self {
+            action,
+            install_dir: install_dir.to_path_buf(),
+            changes: vec![],
+            downloads: vec![],
+            commands: vec![],
+            env_vars: BTreeMap::new(),
+            path_added: vec![],
+            path_removed: vec![],
+            rc_files: vec![],
+            disk_space: None,
+        }
+    }
+
+    pub(crate) fn change<S: ToString>(
+        &mut self,
+        kind: ChangeKind,
+        name: S,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) {
+        self.changes.push(Change {
+            kind,
+            name: name.to_string(),
+            from: from.map(ToString::to_string),
+            to: to.map(ToString::to_string),
+        });
+    }
+
+    /// Return the changes of a certain kind.
+    pub(crate) fn changes_of(&self, kind: ChangeKind) -> impl Iterator<Item = &Change> {
+        self.changes.iter().filter(move |c| c.kind == kind)
+    }
+
+    /// Return `true` if nothing would be changed.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.env_vars.is_empty() && self.path_added.is_empty()
+    }
+}
+
+/// Format a size in bytes into a human readable string, such as `1.5 MiB`.
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_human_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn disk_space_of_local_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        utils::write_bytes(dir.path().join("a"), &[0; 10], false).unwrap();
+        utils::ensure_dir(dir.path().join("sub")).unwrap();
+        utils::write_bytes(dir.path().join("sub").join("b"), &[0; 20], false).unwrap();
+
+        let mut space = DiskSpace::default();
+        space.add_path("dir", dir.path());
+        space.add_path("file", &dir.path().join("a"));
+        space.add_path("missing", &dir.path().join("missing"));
+        space.add_unknown("remote");
+        assert_eq!(space.bytes, 40);
+        assert_eq!(space.unknown, ["missing", "remote"]);
+    }
+}
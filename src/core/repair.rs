@@ -0,0 +1,286 @@
+//! Detect broken components of an existing installation, so that they can be
+//! reinstalled using [`InstallConfiguration::repair`](crate::InstallConfiguration::repair).
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+
+use super::{
+    directories::RimDir,
+    parser::{
+        fingerprint::{InstallationRecord, ToolRecord},
+        toolset_manifest::ToolsetManifest,
+    },
+    rustup::RUSTUP,
+    tools::ToolKind,
+    verify::{self, ProblemKind},
+    CARGO_HOME, RUSTUP_HOME,
+};
+use crate::utils;
+
+/// The way to repair a broken Rust toolchain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolchainRepair {
+    /// The toolchain (or `rustup` itself) is missing or cannot be run, reinstall it entirely.
+    Reinstall,
+    /// Some of the toolchain components are missing, add them back.
+    Components(Vec<String>),
+}
+
+/// A list of broken components that need to be reinstalled.
+#[derive(Debug, Default)]
+pub struct RepairPlan {
+    /// Names of the broken tools.
+    pub tools: Vec<String>,
+    pub toolchain: Option<ToolchainRepair>,
+}
+
+impl RepairPlan {
+    pub(crate) fn detect(
+        record: &InstallationRecord,
+        manifest: &ToolsetManifest,
+        dirs: &impl RimDir,
+    ) -> Result<Self> {
+        let cargo_bins = if record
+            .tools
+            .values()
+            .any(|rec| rec.tool_kind() == ToolKind::CargoTool)
+        {
+            installed_cargo_bins(dirs)
+        } else {
+            Some(HashMap::new())
+        };
+
+        let mut tools = vec![];
+        for (name, tool) in &record.tools {
+            if tool_is_broken(name, tool, cargo_bins.as_ref(), dirs.cargo_bin())? {
+                tools.push(name.clone());
+            }
+        }
+
+        let mut toolchain = detect_broken_toolchain(record, manifest, dirs)?;
+        // `rustc` might still work when `cargo` does not, the toolchain is broken either way
+        if cargo_bins.is_none() && record.installed_toolchain().is_some() {
+            toolchain = Some(ToolchainRepair::Reinstall);
+        }
+
+        Ok(Self { tools, toolchain })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty() && self.toolchain.is_none()
+    }
+
+    /// Return a printable list of the components to repair.
+    pub fn to_list(&self) -> String {
+        let mut list = String::new();
+        match &self.toolchain {
+            Some(ToolchainRepair::Reinstall) => {
+                list.push_str(&format!(
+                    "{} ({})\n",
+                    t!("rust_toolchain"),
+                    t!("reinstall_all")
+                ));
+            }
+            Some(ToolchainRepair::Components(comps)) => {
+                for comp in comps {
+                    list.push_str(&format!("{comp}\n"));
+                }
+            }
+            None => (),
+        }
+        for tool in &self.tools {
+            list.push_str(&format!("{tool}\n"));
+        }
+        list
+    }
+}
+
+/// Check whether a tool needs to be reinstalled.
+///
+/// Tools installed by `cargo` are checked by looking for their binaries,
+/// and they are broken if `cargo` does not know about them at all,
+/// which cannot be checked when `cargo_bins` is `None` because `cargo` cannot be run.
+/// Other tools are checked using their [file records](ToolRecord::files),
+/// note that unknown files does not make a tool broken.
+fn tool_is_broken(
+    name: &str,
+    record: &ToolRecord,
+    cargo_bins: Option<&HashMap<String, Vec<String>>>,
+    cargo_bin_dir: &Path,
+) -> Result<bool> {
+    if record.tool_kind() == ToolKind::CargoTool {
+        let broken = cargo_bins.is_some_and(|all_bins| match all_bins.get(name) {
+            Some(bins) => bins.iter().any(|bin| !cargo_bin_dir.join(bin).is_file()),
+            None => true,
+        });
+        return Ok(broken);
+    }
+
+    let report = verify::verify_tool(name, record)?;
+    Ok(report.problems.iter().any(|p| p.kind != ProblemKind::Extra))
+}
+
+/// Get the binaries of each crate installed by `cargo install`.
+///
+/// Returns `None` if `cargo` cannot be run.
+fn installed_cargo_bins(dirs: &impl RimDir) -> Option<HashMap<String, Vec<String>>> {
+    let cargo = dirs.cargo_bin().join(utils::exe!("cargo"));
+    let output = utils::cmd!([CARGO_HOME=dirs.cargo_home(), RUSTUP_HOME=dirs.rustup_home()] cargo, "install", "--list")
+        .output();
+    match output {
+        Ok(out) if out.status.success() => Some(parse_cargo_install_list(
+            &String::from_utf8_lossy(&out.stdout),
+        )),
+        _ => None,
+    }
+}
+
+/// Parse the output of `cargo install --list`, which looks like:
+///
+/// ```text
+/// cargo-nextest v0.9.72:
+///     cargo-nextest
+/// mdbook v0.4.40:
+///     mdbook
+/// ```
+fn parse_cargo_install_list(output: &str) -> HashMap<String, Vec<String>> {
+    let mut res: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if let Some(name) = &current {
+                res.entry(name.clone())
+                    .or_default()
+                    .push(line.trim().to_string());
+            }
+        } else if let Some((name, _)) = line.split_once(' ') {
+            res.entry(name.to_string()).or_default();
+            current = Some(name.to_string());
+        }
+    }
+    res
+}
+
+fn detect_broken_toolchain(
+    record: &InstallationRecord,
+    manifest: &ToolsetManifest,
+    dirs: &impl RimDir,
+) -> Result<Option<ToolchainRepair>> {
+    let Some((version, recorded_comps)) = record.installed_toolchain() else {
+        return Ok(None);
+    };
+
+    let rustup = dirs.cargo_bin().join(RUSTUP);
+    if !rustup.is_file() {
+        return Ok(Some(ToolchainRepair::Reinstall));
+    }
+    let rustup_cmd = |args: &[&str]| {
+        let mut cmd = utils::cmd!(
+            [
+                CARGO_HOME = dirs.cargo_home(),
+                RUSTUP_HOME = dirs.rustup_home()
+            ] & rustup
+        );
+        cmd.args(args).output()
+    };
+
+    let rustc_works =
+        rustup_cmd(&["run", version, "rustc", "--version"]).is_ok_and(|out| out.status.success());
+    if !rustc_works {
+        return Ok(Some(ToolchainRepair::Reinstall));
+    }
+
+    let output = rustup_cmd(&["component", "list", "--installed", "--toolchain", version])?;
+    if !output.status.success() {
+        return Ok(Some(ToolchainRepair::Reinstall));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let installed = stdout.lines().map(str::trim).collect::<Vec<_>>();
+
+    // the base profile is also recorded as a component, but it's not a real component.
+    let profile = manifest.toolchain_profile();
+    let is_profile = |name: &str| {
+        profile.is_some_and(|p| p.name == name || p.verbose_name.as_deref() == Some(name))
+    };
+    let mut missing: Vec<String> = vec![];
+    for comp in manifest.rust.components.iter().chain(recorded_comps) {
+        if is_profile(comp)
+            || missing.contains(comp)
+            || installed.iter().any(|ic| component_matches(ic, comp))
+        {
+            continue;
+        }
+        missing.push(comp.clone());
+    }
+
+    Ok((!missing.is_empty()).then_some(ToolchainRepair::Components(missing)))
+}
+
+/// Check if an item from `rustup component list` is the given component,
+/// the listed item might have target triple suffix, such as `cargo-x86_64-unknown-linux-gnu`.
+fn component_matches(listed: &str, component: &str) -> bool {
+    let name = component.trim_end_matches("-preview");
+    [component, name].iter().any(|name| {
+        listed == *name
+            || listed
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('-'))
+                == Some(env!("TARGET"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cargo_install_list_output() {
+        let output = "\
+cargo-nextest v0.9.72:
+    cargo-nextest
+mdbook v0.4.40 (https://github.com/rust-lang/mdBook#d3ef1a4f):
+    mdbook
+    mdbook-extra
+empty v0.1.0:
+";
+        let bins = parse_cargo_install_list(output);
+        assert_eq!(bins["cargo-nextest"], vec!["cargo-nextest"]);
+        assert_eq!(bins["mdbook"], vec!["mdbook", "mdbook-extra"]);
+        assert!(bins["empty"].is_empty());
+        assert_eq!(bins.len(), 3);
+    }
+
+    #[test]
+    fn detect_broken_cargo_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        utils::write_bytes(dir.path().join("mdbook"), b"", false).unwrap();
+        let bins = parse_cargo_install_list("mdbook v0.4.40:\n    mdbook\nfoo v0.1.0:\n    foo\n");
+        let record = ToolRecord::cargo_tool();
+        let is_broken = |name, bins| tool_is_broken(name, &record, bins, dir.path()).unwrap();
+
+        assert!(!is_broken("mdbook", Some(&bins)));
+        // binary removed
+        assert!(is_broken("foo", Some(&bins)));
+        // crate uninstalled
+        assert!(is_broken("bar", Some(&bins)));
+        // unknown when `cargo` cannot be run
+        assert!(!is_broken("bar", None));
+    }
+
+    #[test]
+    fn match_component_names() {
+        let target = env!("TARGET");
+        assert!(component_matches("rust-src", "rust-src"));
+        assert!(component_matches(&format!("cargo-{target}"), "cargo"));
+        assert!(component_matches(
+            &format!("llvm-tools-{target}"),
+            "llvm-tools-preview"
+        ));
+        assert!(!component_matches(
+            &format!("rust-std-{target}"),
+            "rust-src"
+        ));
+        assert!(!component_matches(&format!("rust-docs-{target}"), "rust"));
+    }
+}
@@ -0,0 +1,369 @@
+//! Snapshots of the previous toolkit, which are saved when switching to another toolkit,
+//! so that the installation can be rolled back to it using
+//! [`InstallConfiguration::rollback`](crate::InstallConfiguration::rollback).
+//!
+//! A snapshot is staged before a toolkit update starts, and it only replaces the previous
+//! snapshot after the update succeeds, so that an interrupted update (or rollback) can be
+//! resumed without losing the toolkit to roll back to.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::{
+    components::{Component, ComponentType},
+    parser::{fingerprint::InstallationRecord, toolset_manifest::ToolsetManifest, TomlParser},
+};
+use crate::utils;
+
+/// The manifest and fingerprint of the toolkit installed before the last toolkit update.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub manifest: ToolsetManifest,
+    pub record: InstallationRecord,
+}
+
+impl Snapshot {
+    fn dir(root: &Path) -> PathBuf {
+        root.join("backup").join("snapshot")
+    }
+
+    fn staged_dir(root: &Path) -> PathBuf {
+        root.join("backup").join("snapshot-staged")
+    }
+
+    /// Load the snapshot of the previous toolkit in `root`, return `None` if there isn't one.
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        Self::load_from(&Self::dir(root))
+    }
+
+    /// Load the snapshot staged by an unfinished toolkit update.
+    pub(crate) fn load_staged(root: &Path) -> Result<Option<Self>> {
+        Self::load_from(&Self::staged_dir(root))
+    }
+
+    /// Load a snapshot from the manifest and fingerprint in `dir`, return `None` if missing.
+    pub(crate) fn load_from(dir: &Path) -> Result<Option<Self>> {
+        let manifest_path = dir.join(ToolsetManifest::FILENAME);
+        let record_path = dir.join(InstallationRecord::FILENAME);
+        if !manifest_path.is_file() || !record_path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            manifest: ToolsetManifest::load(manifest_path)?,
+            record: InstallationRecord::load(record_path)?,
+        }))
+    }
+
+    /// Copy the manifest and fingerprint of current toolkit in `root` as a staged snapshot,
+    /// which will become the snapshot of previous toolkit once [`commit`](Self::commit)ted.
+    pub(crate) fn stage(root: &Path) -> Result<()> {
+        let staged_dir = Self::staged_dir(root);
+        if staged_dir.exists() {
+            utils::remove(&staged_dir)?;
+        }
+        let files = [
+            root.join(ToolsetManifest::FILENAME),
+            root.join(InstallationRecord::FILENAME),
+        ];
+        if files.iter().any(|f| !f.is_file()) {
+            return Ok(());
+        }
+        utils::ensure_dir(&staged_dir)?;
+        for file in files {
+            utils::copy_into(file, &staged_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the snapshot of previous toolkit with the staged one, if there is one.
+    pub(crate) fn commit(root: &Path) -> Result<()> {
+        let staged_dir = Self::staged_dir(root);
+        if staged_dir.is_dir() {
+            utils::move_to(&staged_dir, &Self::dir(root), true)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the snapshot staged by an unfinished toolkit update, if there is one.
+    pub(crate) fn discard_staged(root: &Path) -> Result<()> {
+        let staged_dir = Self::staged_dir(root);
+        if staged_dir.exists() {
+            utils::remove(&staged_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Compare this snapshot with the current installation in `root`.
+    pub fn rollback_plan(&self, root: &Path) -> Result<RollbackPlan> {
+        RollbackPlan::new(self, &InstallationRecord::load_from_dir(root)?)
+    }
+
+    /// The toolchain version of this snapshot.
+    pub(crate) fn toolchain(&self) -> Option<&str> {
+        self.record.installed_toolchain().map(|(ver, _)| ver)
+    }
+}
+
+/// Return the toolchains that are no longer needed after updating the toolchain in `root`
+/// from `installed` to `new`.
+///
+/// The toolchain of previous toolkit is kept, so that it can be rolled back to without
+/// downloading it again, the others are obsolete.
+pub(crate) fn obsolete_toolchains(
+    root: &Path,
+    installed: Option<&str>,
+    new: &str,
+) -> Result<Vec<String>> {
+    let committed = Snapshot::load(root)?;
+    let committed = committed.as_ref().and_then(Snapshot::toolchain);
+    // the staged snapshot will replace the committed one once the update finished
+    let (kept, candidates) = match Snapshot::load_staged(root)? {
+        Some(staged) => (
+            staged.toolchain().map(ToString::to_string),
+            [installed, committed],
+        ),
+        None => (committed.map(ToString::to_string), [installed, None]),
+    };
+
+    let mut obsolete: Vec<String> = vec![];
+    for ver in candidates.into_iter().flatten() {
+        if ver != new && kept.as_deref() != Some(ver) && !obsolete.iter().any(|v| v == ver) {
+            obsolete.push(ver.to_string());
+        }
+    }
+    Ok(obsolete)
+}
+
+/// What would be changed to roll back to the previous toolkit.
+#[derive(Debug, Default)]
+pub struct RollbackPlan {
+    /// Components to install with the versions of the previous toolkit.
+    pub components: Vec<Component>,
+    /// The installed versions of [`components`](Self::components).
+    pub installed_versions: Vec<Option<String>>,
+    /// Tools that were not installed in the previous toolkit, which will be uninstalled.
+    pub tools_to_remove: Vec<String>,
+}
+
+impl RollbackPlan {
+    /// Compare the `snapshot` of previous toolkit with the `current` installation.
+    pub(crate) fn new(snapshot: &Snapshot, current: &InstallationRecord) -> Result<Self> {
+        let mut plan = Self::default();
+        let previous = &snapshot.record;
+        let prev_toolchain = previous.installed_toolchain();
+        let cur_toolchain = current.installed_toolchain();
+        // The toolchain components are recorded all at once, so they must be installed together.
+        let toolchain_changed = match (prev_toolchain, cur_toolchain) {
+            (Some((prev_ver, prev_comps)), Some((cur_ver, cur_comps))) => {
+                prev_ver != cur_ver || prev_comps.iter().any(|c| !cur_comps.contains(c))
+            }
+            (prev, _) => prev.is_some(),
+        };
+
+        for comp in snapshot.manifest.current_target_components(false)? {
+            let installed_version = if comp.kind.is_from_toolchain() {
+                let Some((_, prev_comps)) = prev_toolchain else {
+                    continue;
+                };
+                // the profile represents the toolchain itself, so it's needed to install the toolchain
+                let is_needed =
+                    comp.kind == ComponentType::ToolchainProfile || prev_comps.contains(&comp.name);
+                if !toolchain_changed || !is_needed {
+                    continue;
+                }
+                cur_toolchain.map(|(ver, _)| ver)
+            } else {
+                if !previous.tools.contains_key(&comp.name) {
+                    continue;
+                }
+                let installed_version = current.get_tool_version(&comp.name);
+                if current.tools.contains_key(&comp.name)
+                    && installed_version == previous.get_tool_version(&comp.name)
+                {
+                    continue;
+                }
+                installed_version
+            };
+            plan.installed_versions
+                .push(installed_version.map(ToString::to_string));
+            plan.components.push(comp);
+        }
+
+        plan.tools_to_remove = current
+            .tools
+            .keys()
+            .filter(|name| !previous.tools.contains_key(*name))
+            .cloned()
+            .collect();
+        Ok(plan)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.tools_to_remove.is_empty()
+    }
+
+    /// Return a printable list of the changes.
+    pub fn to_list(&self) -> String {
+        let mut list = String::new();
+        for (comp, from) in self.components.iter().zip(&self.installed_versions) {
+            list.push_str(&format!(
+                "{} ({} -> {})\n",
+                comp.name,
+                from.as_deref().unwrap_or("N/A"),
+                comp.version.as_deref().unwrap_or("N/A")
+            ));
+        }
+        for name in &self.tools_to_remove {
+            list.push_str(&format!("{name} ({})\n", t!("version_change_remove")));
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[rust]
+version = "1.79.0"
+profile = { name = "minimal" }
+optional-components = ["clippy"]
+
+[tools.target.x86_64-pc-windows-msvc]
+cargo-expand = "1.0.88"
+flamegraph = "0.6.4"
+
+[tools.target.x86_64-pc-windows-gnu]
+cargo-expand = "1.0.88"
+flamegraph = "0.6.4"
+
+[tools.target.x86_64-unknown-linux-gnu]
+cargo-expand = "1.0.88"
+flamegraph = "0.6.4"
+
+[tools.target.aarch64-apple-darwin]
+cargo-expand = "1.0.88"
+flamegraph = "0.6.4"
+"#;
+
+    fn record(toml: &str) -> InstallationRecord {
+        InstallationRecord::from_str(&format!("root = '/path/to/something'\n{toml}")).unwrap()
+    }
+
+    #[test]
+    fn stage_and_commit_snapshot() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        assert!(Snapshot::load(root).unwrap().is_none());
+
+        utils::write_file(root.join(ToolsetManifest::FILENAME), MANIFEST, false).unwrap();
+        record("version = '0.1.0'").write_to_dir(root).unwrap();
+        Snapshot::stage(root).unwrap();
+        assert!(Snapshot::load(root).unwrap().is_none());
+        assert!(Snapshot::load_staged(root).unwrap().is_some());
+
+        Snapshot::commit(root).unwrap();
+        let snapshot = Snapshot::load(root).unwrap().unwrap();
+        assert_eq!(snapshot.record.version.as_deref(), Some("0.1.0"));
+        assert!(Snapshot::load_staged(root).unwrap().is_none());
+
+        // staging again should not touch the committed snapshot
+        record("version = '0.2.0'").write_to_dir(root).unwrap();
+        Snapshot::stage(root).unwrap();
+        let snapshot = Snapshot::load(root).unwrap().unwrap();
+        assert_eq!(snapshot.record.version.as_deref(), Some("0.1.0"));
+    }
+
+    #[test]
+    fn keep_toolchain_of_previous_toolkit() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        utils::write_file(root.join(ToolsetManifest::FILENAME), MANIFEST, false).unwrap();
+        let with_toolchain = |ver: &str| record(&format!("[rust]\nversion = '{ver}'"));
+
+        // no snapshot yet, switching to another toolkit for the first time
+        with_toolchain("1.79.0").write_to_dir(root).unwrap();
+        Snapshot::stage(root).unwrap();
+        assert!(obsolete_toolchains(root, Some("1.79.0"), "1.80.0")
+            .unwrap()
+            .is_empty());
+        Snapshot::commit(root).unwrap();
+
+        // switching again, the toolchain of the oldest toolkit is no longer needed
+        with_toolchain("1.80.0").write_to_dir(root).unwrap();
+        Snapshot::stage(root).unwrap();
+        assert_eq!(
+            obsolete_toolchains(root, Some("1.80.0"), "1.81.0").unwrap(),
+            ["1.79.0"]
+        );
+        Snapshot::commit(root).unwrap();
+
+        // updating without switching toolkit keeps the snapshot
+        with_toolchain("1.81.0").write_to_dir(root).unwrap();
+        assert_eq!(
+            obsolete_toolchains(root, Some("1.81.0"), "1.81.1").unwrap(),
+            ["1.81.0"]
+        );
+
+        // rolling back to the previous toolkit, which can be undone by rolling back again
+        Snapshot::stage(root).unwrap();
+        assert!(obsolete_toolchains(root, Some("1.81.0"), "1.80.0")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn plan_rollback() {
+        let snapshot = Snapshot {
+            manifest: ToolsetManifest::from_str(MANIFEST).unwrap(),
+            record: record(
+                r#"
+[rust]
+version = '1.79.0'
+components = ['minimal', 'clippy']
+
+[tools]
+cargo-expand = { kind = 'cargo-tool', version = '1.0.88', paths = [] }
+flamegraph = { kind = 'cargo-tool', version = '0.6.4', paths = [] }
+"#,
+            ),
+        };
+        let current = record(
+            r#"
+[rust]
+version = '1.81.0'
+components = ['minimal', 'clippy']
+
+[tools]
+cargo-expand = { kind = 'cargo-tool', version = '1.0.88', paths = [] }
+flamegraph = { kind = 'cargo-tool', version = '0.6.5', paths = [] }
+mdbook = { kind = 'cargo-tool', version = '0.4.40', paths = [] }
+"#,
+        );
+
+        let plan = RollbackPlan::new(&snapshot, &current).unwrap();
+        let changes = plan
+            .components
+            .iter()
+            .zip(&plan.installed_versions)
+            .map(|(c, from)| (c.name.as_str(), from.as_deref(), c.version.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                ("minimal", Some("1.81.0"), Some("1.79.0")),
+                ("clippy", Some("1.81.0"), Some("1.79.0")),
+                ("flamegraph", Some("0.6.5"), Some("0.6.4")),
+            ]
+        );
+        assert_eq!(plan.tools_to_remove, ["mdbook"]);
+
+        assert!(RollbackPlan::new(&snapshot, &snapshot.record)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,11 +1,13 @@
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
 use std::thread;
 
 use anyhow::{Context, Result};
 use url::Url;
 
 use super::directories::RimDir;
+use super::error::{ErrorKind, ResultExt};
 use super::install::InstallConfiguration;
 use super::parser::toolset_manifest::ToolsetManifest;
 use super::uninstall::UninstallConfiguration;
@@ -24,9 +26,9 @@ pub(crate) const RUSTUP_INIT: &str = "rustup-init.exe";
 pub(crate) const RUSTUP_INIT: &str = "rustup-init";
 
 #[cfg(windows)]
-const RUSTUP: &str = "rustup.exe";
+pub(crate) const RUSTUP: &str = "rustup.exe";
 #[cfg(not(windows))]
-const RUSTUP: &str = "rustup";
+pub(crate) const RUSTUP: &str = "rustup";
 
 pub struct ToolchainInstaller {
     insecure: bool,
@@ -56,7 +58,14 @@ impl ToolchainInstaller {
             args.push("--component");
             args.extend(components);
         }
-        let mut cmd = if let Some(local_server) = manifest.offline_dist_server()? {
+        let mut cmd = self.rustup_cmd(rustup, manifest)?;
+        cmd.args(args);
+        run_rustup(cmd)
+    }
+
+    /// Create a `rustup` command that respects the dist server configuration.
+    fn rustup_cmd(&self, rustup: &Path, manifest: &ToolsetManifest) -> Result<Command> {
+        let cmd = if let Some(local_server) = manifest.offline_dist_server()? {
             utils::cmd!([RUSTUP_DIST_SERVER=local_server.as_str()] rustup)
         } else if let Ok(dist_server) = std::env::var(RUSTUP_DIST_SERVER) {
             let mut server: Url = dist_server.parse()?;
@@ -70,8 +79,7 @@ impl ToolchainInstaller {
         } else {
             utils::cmd!(rustup)
         };
-        cmd.args(args);
-        utils::execute(cmd)
+        Ok(cmd)
     }
 
     /// Install rust toolchain & components via rustup.
@@ -82,18 +90,7 @@ impl ToolchainInstaller {
         components: &[ToolchainComponent],
     ) -> Result<()> {
         let rustup = ensure_rustup(config, manifest, self.insecure)?;
-
-        let extra_comps = components
-            .iter()
-            .filter_map(|c| (!c.is_profile).then_some(&c.name));
-        let all_components = manifest
-            .rust
-            .components
-            .iter()
-            .chain(extra_comps)
-            .map(|s| s.as_str())
-            .collect();
-        self.install_toolchain_via_rustup(&rustup, manifest, all_components)?;
+        self.install_toolchain_via_rustup(&rustup, manifest, all_components(manifest, components))?;
 
         // Remove the `rustup` uninstall entry on windows, because we don't want users to
         // accidently uninstall `rustup` thus removing the tools installed by this program.
@@ -105,16 +102,95 @@ impl ToolchainInstaller {
         Ok(())
     }
 
-    /// Update rust toolchain by invoking `rustup toolchain add`, then `rustup default`
+    /// Update rust toolchain by installing the one specified in `manifest` with `components`,
+    /// then `rustup default` to it and remove the `obsolete` ones.
+    ///
+    /// A toolchain of specific version (such as `1.80.1`) never changes once installed,
+    /// so it will not be installed again if exists, which allows rolling back to a
+    /// previous toolchain without network connection.
     pub(crate) fn update(
         &self,
         config: &InstallConfiguration,
         manifest: &ToolsetManifest,
+        components: &[ToolchainComponent],
+        obsolete: &[String],
     ) -> Result<()> {
         let rustup = ensure_rustup(config, manifest, self.insecure)?;
         let tc_ver = manifest.rust_version();
+        let components = all_components(manifest, components);
+
+        if is_pinned_version(tc_ver) && is_toolchain_installed(config, tc_ver) {
+            if !components.is_empty() {
+                let mut cmd = self.rustup_cmd(&rustup, manifest)?;
+                cmd.args(["component", "add", "--toolchain", tc_ver]);
+                cmd.args(components);
+                run_rustup(cmd)?;
+            }
+        } else {
+            self.install_toolchain_via_rustup(&rustup, manifest, components)?;
+        }
+        run_rustup(utils::cmd!(&rustup, "default", tc_ver))?;
+        for ver in obsolete {
+            // the new toolchain is already usable, don't fail if the old one cannot be removed.
+            if let Err(e) = utils::run!(&rustup, "toolchain", "uninstall", ver) {
+                warn!("{e}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the toolchain specified in `manifest` then install it again,
+    /// `rustup` will be installed as well if it's missing.
+    pub(crate) fn reinstall(
+        &self,
+        config: &InstallConfiguration,
+        manifest: &ToolsetManifest,
+        components: &[ToolchainComponent],
+    ) -> Result<()> {
+        let rustup = config.cargo_bin().join(RUSTUP);
+        if rustup.is_file() {
+            // the toolchain might be partially removed, so don't fail if it cannot be uninstalled.
+            if let Err(e) = utils::run!(&rustup, "toolchain", "uninstall", manifest.rust_version())
+            {
+                warn!("{e}");
+            }
+        }
+        self.install(config, manifest, components)
+    }
 
-        utils::run!(&rustup, "toolchain", "add", tc_ver, "--no-self-update")
+    /// Add components to the toolchain specified in `manifest`, using `rustup component add`.
+    pub(crate) fn add_components(
+        &self,
+        config: &InstallConfiguration,
+        manifest: &ToolsetManifest,
+        components: &[&str],
+    ) -> Result<()> {
+        let rustup = ensure_rustup(config, manifest, self.insecure)?;
+        let mut cmd = self.rustup_cmd(&rustup, manifest)?;
+        cmd.args(["component", "add", "--toolchain", manifest.rust_version()]);
+        cmd.args(components);
+        run_rustup(cmd)
+    }
+
+    /// Remove components from the installed toolchain, using `rustup component remove`.
+    pub(crate) fn remove_components(
+        &self,
+        config: &UninstallConfiguration,
+        components: &[&str],
+    ) -> Result<()> {
+        let rustup = config.cargo_bin().join(RUSTUP);
+        let Some((version, _)) = config.install_record.installed_toolchain() else {
+            return Ok(());
+        };
+        let mut cmd = utils::cmd!(
+            [CARGO_HOME = config.cargo_home(), RUSTUP_HOME = config.rustup_home()] rustup,
+            "component",
+            "remove",
+            "--toolchain",
+            version
+        );
+        cmd.args(components);
+        run_rustup(cmd)
     }
 
     // Rustup self uninstall all the components and toolchains.
@@ -137,12 +213,42 @@ impl ToolchainInstaller {
             (progress.update)(&spinner, None);
         }
 
-        handle.join().unwrap()?;
+        handle.join().unwrap().with_kind(ErrorKind::Rustup)?;
         (progress.stop)(&spinner, t!("rust_toolchain_uninstalled").to_string());
         Ok(())
     }
 }
 
+/// Return the components required by `manifest` along with the extra `components`,
+/// excluding the profile, which is not a component of `rustup`.
+fn all_components<'a>(
+    manifest: &'a ToolsetManifest,
+    components: &'a [ToolchainComponent],
+) -> Vec<&'a str> {
+    let extra_comps = components.iter().filter(|c| !c.is_profile).map(|c| &c.name);
+    manifest
+        .rust
+        .components
+        .iter()
+        .chain(extra_comps)
+        .map(|s| s.as_str())
+        .collect()
+}
+
+/// Return `true` if `version` is a specific version such as `1.80.1`,
+/// rather than a channel like `stable` or `nightly-2024-08-01`.
+fn is_pinned_version(version: &str) -> bool {
+    version.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn is_toolchain_installed(config: &InstallConfiguration, version: &str) -> bool {
+    config
+        .rustup_home()
+        .join("toolchains")
+        .join(format!("{version}-{}", env!("TARGET")))
+        .is_dir()
+}
+
 fn ensure_rustup(
     config: &InstallConfiguration,
     manifest: &ToolsetManifest,
@@ -181,6 +287,12 @@ fn ensure_rustup(
     Ok(rustup_bin)
 }
 
+/// Return the url to download `rustup-init` from the rustup update `server`.
+pub(crate) fn rustup_init_url(server: &Url) -> Result<Url> {
+    url_join(server, &format!("dist/{}/{RUSTUP_INIT}", env!("TARGET")))
+        .context("Failed to init rustup download url.")
+}
+
 fn download_rustup_init(
     dest: &Path,
     server: &Url,
@@ -189,8 +301,7 @@ fn download_rustup_init(
 ) -> Result<()> {
     info!("{}", t!("downloading_rustup_init"));
 
-    let download_url = url_join(server, &format!("dist/{}/{RUSTUP_INIT}", env!("TARGET")))
-        .context("Failed to init rustup download url.")?;
+    let download_url = rustup_init_url(server)?;
     utils::DownloadOpt::new(RUSTUP_INIT)
         .insecure(insecure)
         .with_proxy(proxy.cloned())
@@ -218,5 +329,10 @@ fn install_rustup(rustup_init: &PathBuf) -> Result<()> {
     }
     let mut cmd = utils::cmd!(rustup_init);
     cmd.args(args);
-    utils::execute(cmd)
+    run_rustup(cmd)
+}
+
+/// Run a `rustup` (or `rustup-init`) command, the error of which is categorized as [`ErrorKind::Rustup`].
+fn run_rustup(cmd: Command) -> Result<()> {
+    utils::execute(cmd).with_kind(ErrorKind::Rustup)
 }
@@ -1,9 +1,11 @@
+use std::cmp::Ordering;
+
 use crate::core::parser::dist_manifest::DistManifest;
 use crate::core::parser::TomlParser;
 use crate::fingerprint::InstallationRecord;
 use crate::toolset_manifest::ToolsetManifest;
 use crate::{components, utils};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use semver::Version;
 use serde::Serialize;
 use tokio::sync::{Mutex, OnceCell};
@@ -20,9 +22,9 @@ static INSTALLED_KIT: OnceCell<Mutex<Toolkit>> = OnceCell::const_new();
 pub struct Toolkit {
     pub name: String,
     pub version: String,
-    desc: Option<String>,
+    pub desc: Option<String>,
     #[serde(alias = "notes")]
-    info: Option<String>,
+    pub info: Option<String>,
     #[serde(rename = "manifestURL")]
     pub manifest_url: Option<String>,
     pub components: Vec<components::Component>,
@@ -210,3 +212,185 @@ pub async fn latest_installable_toolkit(
         Ok(None)
     }
 }
+
+/// Find the toolkit `name` of a specific `version` from the ones provided by the server.
+///
+/// The version matches if it is the same as the toolkit's version, either literally or
+/// after trimming the prefixes such as `stable ` in `stable 1.80.1`.
+pub(crate) async fn find_toolkit(name: &str, version: &str, insecure: bool) -> Result<Toolkit> {
+    let same_name = toolkits_from_server(insecure)
+        .await?
+        .into_iter()
+        .filter(|tk| tk.name == name)
+        .collect::<Vec<_>>();
+    if same_name.is_empty() {
+        bail!("{}", t!("toolkit_not_on_server", name = name));
+    }
+
+    let wanted = trim_version(version);
+    if let Some(found) = same_name
+        .iter()
+        .find(|tk| tk.version == version || trim_version(&tk.version) == wanted)
+    {
+        return Ok(found.clone());
+    }
+    let available = same_name
+        .iter()
+        .map(|tk| tk.version.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    bail!(
+        "{}",
+        t!(
+            "toolkit_version_not_found",
+            name = name,
+            version = version,
+            available = available
+        )
+    );
+}
+
+fn trim_version(version: &str) -> &str {
+    version.trim_start_matches(|c: char| !c.is_ascii_digit())
+}
+
+/// How a component would be changed by switching to another toolkit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VersionChange {
+    /// Not installed yet, but required by the new toolkit.
+    Install,
+    Upgrade,
+    Downgrade,
+    /// The versions are different, but cannot be compared as semver.
+    Replace,
+    Unchanged,
+}
+
+/// Compare the `target` components of another toolkit with the ones installed in `record`,
+/// returning every component that is either installed or required, along with its installed
+/// version and how it would be changed.
+///
+/// Components that are neither installed nor required are not included, and so are the
+/// installed ones that are not provided by the target toolkit, which will be kept as is.
+pub(crate) fn version_changes<'a>(
+    record: &'a InstallationRecord,
+    target: &'a [components::Component],
+) -> Vec<(&'a components::Component, Option<&'a str>, VersionChange)> {
+    let installed_toolchain = record.installed_toolchain();
+    target
+        .iter()
+        .filter_map(|comp| {
+            let installed_version = if comp.kind.is_from_toolchain() {
+                installed_toolchain
+                    .filter(|(_, comps)| comps.contains(&comp.name))
+                    .map(|(ver, _)| Some(ver))
+            } else {
+                record
+                    .tools
+                    .contains_key(&comp.name)
+                    .then(|| record.get_tool_version(&comp.name))
+            };
+            let change = match installed_version {
+                None if comp.required => VersionChange::Install,
+                None => return None,
+                Some(from) => compare_versions(from, comp.version.as_deref()),
+            };
+            Some((comp, installed_version.flatten(), change))
+        })
+        .collect()
+}
+
+fn compare_versions(from: Option<&str>, to: Option<&str>) -> VersionChange {
+    if from == to {
+        return VersionChange::Unchanged;
+    }
+    let parse = |v: Option<&str>| v.and_then(|v| trim_version(v).parse::<Version>().ok());
+    match (parse(from), parse(to)) {
+        (Some(from), Some(to)) => match to.cmp(&from) {
+            Ordering::Greater => VersionChange::Upgrade,
+            Ordering::Less => VersionChange::Downgrade,
+            Ordering::Equal => VersionChange::Unchanged,
+        },
+        _ => VersionChange::Replace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::components::{Component, ComponentType};
+
+    #[test]
+    fn compare_component_versions() {
+        assert_eq!(
+            compare_versions(Some("1.80.0"), Some("1.81.0")),
+            VersionChange::Upgrade
+        );
+        assert_eq!(
+            compare_versions(Some("v0.6.5"), Some("0.6.4")),
+            VersionChange::Downgrade
+        );
+        assert_eq!(
+            compare_versions(Some("stable 1.80.0"), Some("1.80.0")),
+            VersionChange::Unchanged
+        );
+        assert_eq!(
+            compare_versions(Some("nightly"), Some("1.80.0")),
+            VersionChange::Replace
+        );
+        assert_eq!(compare_versions(None, None), VersionChange::Unchanged);
+    }
+
+    #[test]
+    fn changes_of_switching_toolkit() {
+        let record = InstallationRecord::from_str(
+            r#"
+root = '/path/to/something'
+
+[rust]
+version = '1.81.0'
+components = ['minimal', 'clippy']
+
+[tools]
+cargo-expand = { kind = 'cargo-tool', version = '1.0.90', paths = [] }
+flamegraph = { kind = 'cargo-tool', version = '0.6.5', paths = [] }
+"#,
+        )
+        .unwrap();
+        let target = [
+            Component::new("minimal", "")
+                .set_kind(ComponentType::ToolchainProfile)
+                .required(true)
+                .with_version(Some("1.79.0")),
+            Component::new("clippy", "")
+                .set_kind(ComponentType::ToolchainComponent)
+                .with_version(Some("1.79.0")),
+            Component::new("rustfmt", "")
+                .set_kind(ComponentType::ToolchainComponent)
+                .with_version(Some("1.79.0")),
+            Component::new("cargo-expand", "").with_version(Some("1.0.95")),
+            Component::new("flamegraph", "").with_version(Some("0.6.5")),
+            Component::new("mdbook", "")
+                .required(true)
+                .with_version(Some("0.4.40")),
+            Component::new("cargo-nextest", "").with_version(Some("0.9.0")),
+        ];
+
+        let changes = version_changes(&record, &target)
+            .into_iter()
+            .map(|(c, from, change)| (c.name.as_str(), from, change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                ("minimal", Some("1.81.0"), VersionChange::Downgrade),
+                ("clippy", Some("1.81.0"), VersionChange::Downgrade),
+                ("cargo-expand", Some("1.0.90"), VersionChange::Upgrade),
+                ("flamegraph", Some("0.6.5"), VersionChange::Unchanged),
+                ("mdbook", None, VersionChange::Install),
+            ]
+        );
+    }
+}
@@ -8,7 +8,10 @@ use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
 
 use super::{
-    directories::RimDir, parser::fingerprint::ToolRecord, uninstall::UninstallConfiguration,
+    directories::RimDir,
+    error::{ErrorKind, ResultExt},
+    parser::fingerprint::ToolRecord,
+    uninstall::UninstallConfiguration,
     GlobalOpts, PathExt, CARGO_HOME,
 };
 use crate::{core::custom_instructions, setter, utils, InstallConfiguration};
@@ -119,6 +122,42 @@ impl<'a> Tool<'a> {
         Ok(Self::new(name, ToolKind::Unknown).with_path(path))
     }
 
+    /// Create a tool from its installation record, which is then used for uninstallation.
+    ///
+    /// Return `None` if the tool cannot be identified, in which case it will be skipped.
+    pub(crate) fn from_record(name: &str, record: &'a ToolRecord) -> Option<Self> {
+        let kind = record.tool_kind();
+        let tool = match kind {
+            ToolKind::CargoTool => Tool::cargo_tool(name, None),
+            // TODO: (>1.0) We didn't have a proper way to track tool's type,
+            // so we uses a `use-cargo`, then we have to guess it by looking at the content
+            // of the paths if `use-cargo = false`.
+            // After 1.0 release, remove this branch.
+            ToolKind::Unknown => {
+                if let [path] = record.paths.as_slice() {
+                    // don't interrupt uninstallation if the path of some tools cannot be found,
+                    // as the user might have manually remove them
+                    let Ok(tool) = Tool::from_path(name, path) else {
+                        warn!(
+                            "{}: {}",
+                            t!("uninstall_tool_skipped", tool = name),
+                            t!("path_to_installation_not_found", path = path.display())
+                        );
+                        return None;
+                    };
+                    tool
+                } else if !record.paths.is_empty() {
+                    Tool::new(name.into(), ToolKind::Executables).with_path(record.paths.clone())
+                } else {
+                    info!("{}", t!("uninstall_unknown_tool_warn", tool = name));
+                    return None;
+                }
+            }
+            _ => Tool::new(name.into(), kind).with_path(record.paths.clone()),
+        };
+        Some(tool)
+    }
+
     /// Specify as a tool that managed by `cargo`.
     ///
     /// Note: `extra_args` should not contains "install" and `name`.
@@ -175,9 +214,12 @@ impl<'a> Tool<'a> {
             }
         };
 
-        Ok(ToolRecord::new(self.kind)
+        let record = ToolRecord::new(self.kind)
             .with_paths(paths)
-            .with_version(version))
+            .with_version(version);
+        // keep a manifest of every installed file, so that we can verify them later.
+        let files = record.collect_files()?;
+        Ok(record.with_files(files))
     }
 
     pub(crate) fn uninstall(&self, config: &UninstallConfiguration) -> Result<()> {
@@ -218,7 +260,7 @@ fn cargo_install_or_uninstall(op: &str, args: &[&str], cargo_home: &Path) -> Res
     }
     full_args.extend_from_slice(args);
     cmd.args(full_args);
-    utils::execute(cmd)
+    utils::execute(cmd).with_kind(ErrorKind::CargoInstall)
 }
 
 /// Move one path (file/dir) to a new folder with `name` under tools dir.
@@ -1,14 +1,25 @@
 use anyhow::Result;
 use indexmap::IndexMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::{
+    components::{Component, ComponentType},
+    dir_lock::InstallDirLock,
     directories::RimDir,
-    parser::fingerprint::{installed_tools_fresh, InstallationRecord, ToolRecord},
+    error::{ErrorKind, ResultExt},
+    history::{self, Action, HistoryEntry},
+    journal::{Journal, Operation, Step},
+    parser::{
+        fingerprint::{installed_tools_fresh, InstallationRecord, ToolRecord},
+        installations::InstallationIndex,
+        validate_install_dir, TomlParser,
+    },
+    plan::{ChangeKind, Plan},
     rustup::ToolchainInstaller,
-    tools::ToolKind,
+    tools::{Tool, ToolKind},
+    GlobalOpts, ALL_VARS,
 };
-use crate::{core::tools::Tool, utils::Progress};
+use crate::utils::Progress;
 
 /// Contains definition of uninstallation steps.
 pub(crate) trait Uninstallation {
@@ -47,6 +58,15 @@ impl<'a> UninstallConfiguration<'a> {
         })
     }
 
+    /// Create a configuration to uninstall things from the given installation `root`.
+    pub(crate) fn at(root: &Path) -> Result<Self> {
+        Ok(Self {
+            install_dir: root.to_path_buf(),
+            install_record: InstallationRecord::load_from_dir(root)?,
+            progress_indicator: None,
+        })
+    }
+
     pub(crate) fn inc_progress(&self, val: f32) -> Result<()> {
         if let Some(prog) = &self.progress_indicator {
             prog.inc(Some(val))?;
@@ -55,13 +75,50 @@ impl<'a> UninstallConfiguration<'a> {
     }
 
     pub fn uninstall(mut self, remove_self: bool) -> Result<()> {
+        let lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry =
+            HistoryEntry::new(Action::Uninstall).from_version(self.install_record.version.clone());
+        let result = self.uninstall_();
+        // the history is removed along with the installation directory after a full
+        // uninstallation, so there is no point to record it, unless it failed.
+        if !remove_self || result.is_err() {
+            history::record(&self.install_dir, entry.with_result(&result));
+        }
+        result?;
+
+        // remove the manager binary itself or update install record
+        if remove_self {
+            if let Err(e) = InstallationIndex::unregister(&self.install_dir) {
+                warn!("{}: {e}", t!("unregister_installation_failed"));
+            }
+            // make sure what's about to be removed is still an installation directory
+            validate_install_dir(&self.install_dir)?;
+            info!("{}", t!("uninstall_self"));
+            // the lock file is about to be removed along with the installation directory
+            drop(lock);
+            self.remove_self()?;
+        } else {
+            self.install_record.remove_toolkit_meta();
+            self.install_record.write()?;
+        }
+        self.inc_progress(10.0)?;
+
+        Ok(())
+    }
+
+    fn uninstall_(&mut self) -> Result<()> {
+        let mut journal = Journal::begin(&self.install_dir, Operation::Uninstall, None)?;
+
         // remove all tools.
-        info!("{}", t!("uninstalling_third_party_tools"));
-        self.remove_tools(installed_tools_fresh(&self.install_dir)?, 40.0)?;
+        if !journal.is_done(&Step::UninstallTools) {
+            info!("{}", t!("uninstalling_third_party_tools"));
+            self.remove_tools(installed_tools_fresh(&self.install_dir)?, 40.0)?;
+            journal.record(Step::UninstallTools)?;
+        }
 
         // Remove rust toolchain via rustup.
-        if self.install_record.rust.is_some() {
-            if let Err(e) = ToolchainInstaller::init().remove_self(&self) {
+        if self.install_record.rust.is_some() && !journal.is_done(&Step::UninstallRust) {
+            if let Err(e) = ToolchainInstaller::init().remove_self(self) {
                 // if user has manually uninstall rustup, this will fails,
                 // then we can assume it has been removed.
                 // TODO: add an error type to indicate `rustup` cannot be found
@@ -69,62 +126,132 @@ impl<'a> UninstallConfiguration<'a> {
             }
             self.install_record.remove_rust_record();
             self.install_record.write()?;
+            journal.record(Step::UninstallRust)?;
         }
         self.inc_progress(40.0)?;
 
         // remove all env configuration.
-        info!("{}", t!("uninstall_env_config"));
-        self.remove_rustup_env_vars()?;
+        if !journal.is_done(&Step::RemoveEnvVars) {
+            info!("{}", t!("uninstall_env_config"));
+            self.remove_rustup_env_vars()
+                .with_kind(ErrorKind::EnvModification)?;
+            journal.record(Step::RemoveEnvVars)?;
+        }
         self.inc_progress(10.0)?;
 
-        // remove the manager binary itself or update install record
-        if remove_self {
-            info!("{}", t!("uninstall_self"));
-            self.remove_self()?;
-        } else {
-            self.install_record.remove_toolkit_meta();
+        // Everything that might fail has been done, the journal is no longer needed.
+        journal.finish()
+    }
+
+    /// Uninstall some of the installed tools and toolchain components,
+    /// while keeping the rest of the installation.
+    pub fn remove_components(mut self, components: &[Component]) -> Result<()> {
+        let _lock = InstallDirLock::acquire(&self.install_dir)?;
+        let entry = HistoryEntry::new(Action::ComponentRemove)
+            .from_version(self.install_record.version.clone())
+            .components(components.iter().map(|c| c.name.clone()).collect());
+        let result = self.remove_components_(components);
+        history::record(&self.install_dir, entry.with_result(&result));
+        result
+    }
+
+    fn remove_components_(&mut self, components: &[Component]) -> Result<()> {
+        let toolchain = components
+            .iter()
+            .filter(|c| c.kind == ComponentType::ToolchainComponent)
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        if !toolchain.is_empty() {
+            info!("{}", t!("uninstall_toolchain_components"));
+            ToolchainInstaller::init().remove_components(self, &toolchain)?;
+            for name in toolchain {
+                self.install_record.remove_component_record(name);
+            }
             self.install_record.write()?;
         }
-        self.inc_progress(10.0)?;
+        self.inc_progress(30.0)?;
 
-        Ok(())
+        let tools = installed_tools_fresh(&self.install_dir)?
+            .into_iter()
+            .filter(|(name, _)| {
+                components
+                    .iter()
+                    .any(|c| c.kind == ComponentType::Tool && &c.name == name)
+            })
+            .collect();
+        self.remove_tools(tools, 70.0)
+    }
+
+    /// Compute what would be done to uninstall everything,
+    /// or only the given `components` if it's `Some`.
+    pub(crate) fn plan(&self, components: Option<&[Component]>, remove_self: bool) -> Plan {
+        let action = if components.is_some() {
+            Action::ComponentRemove
+        } else {
+            Action::Uninstall
+        };
+        let mut plan = Plan::new(action, &self.install_dir);
+        let record = &self.install_record;
+        let should_remove = |name: &str, kind: ComponentType| {
+            components.map_or(true, |comps| {
+                comps.iter().any(|c| c.kind == kind && c.name == name)
+            })
+        };
+
+        for (name, tool) in &record.tools {
+            if !should_remove(name, ComponentType::Tool) {
+                continue;
+            }
+            plan.change(
+                ChangeKind::Remove,
+                name,
+                record.get_tool_version(name),
+                None,
+            );
+            if tool.tool_kind() == ToolKind::DirWithBin {
+                plan.path_removed
+                    .extend(tool.paths.first().map(|dir| dir.join("bin")));
+            }
+        }
+        if let Some((version, installed_comps)) = record.installed_toolchain() {
+            if components.is_none() {
+                plan.change(ChangeKind::Remove, "rust", Some(version), None);
+            } else {
+                for comp in installed_comps
+                    .iter()
+                    .filter(|c| should_remove(c, ComponentType::ToolchainComponent))
+                {
+                    plan.change(ChangeKind::Remove, comp, Some(version), None);
+                }
+            }
+        }
+
+        if components.is_none() {
+            plan.path_removed.push(self.cargo_bin().to_path_buf());
+            if remove_self {
+                plan.path_removed.push(self.install_dir.clone());
+            }
+            if !GlobalOpts::get().no_modify_env() {
+                plan.env_vars = ALL_VARS.iter().map(|key| (key.to_string(), None)).collect();
+                #[cfg(unix)]
+                {
+                    plan.rc_files = super::os::unix::inspect_config_sections()
+                        .into_iter()
+                        .filter_map(|sec| sec.vars.is_some().then_some(sec.rc))
+                        .collect();
+                }
+            }
+        }
+        plan
     }
 
     /// Uninstall all tools
     fn remove_tools(&mut self, tools: IndexMap<String, ToolRecord>, weight: f32) -> Result<()> {
         let mut tools_to_uninstall = vec![];
         for (name, tool_detail) in &tools {
-            let kind = tool_detail.tool_kind();
-            let tool = match kind {
-                ToolKind::CargoTool => Tool::cargo_tool(name, None),
-                // TODO: (>1.0) We didn't have a proper way to track tool's type,
-                // so we uses a `use-cargo`, then we have to guess it by looking at the content
-                // of the paths if `use-cargo = false`.
-                // After 1.0 release, remove this branch.
-                ToolKind::Unknown => {
-                    if let [path] = tool_detail.paths.as_slice() {
-                        // don't interrupt uninstallation if the path of some tools cannot be found,
-                        // as the user might have manually remove them
-                        let Ok(tool) = Tool::from_path(name, path) else {
-                            warn!(
-                                "{}: {}",
-                                t!("uninstall_tool_skipped", tool = name),
-                                t!("path_to_installation_not_found", path = path.display())
-                            );
-                            continue;
-                        };
-                        tool
-                    } else if !tool_detail.paths.is_empty() {
-                        Tool::new(name.into(), ToolKind::Executables)
-                            .with_path(tool_detail.paths.clone())
-                    } else {
-                        info!("{}", t!("uninstall_unknown_tool_warn", tool = name));
-                        continue;
-                    }
-                }
-                _ => Tool::new(name.into(), kind).with_path(tool_detail.paths.clone()),
-            };
-            tools_to_uninstall.push(tool);
+            if let Some(tool) = Tool::from_record(name, tool_detail) {
+                tools_to_uninstall.push(tool);
+            }
         }
 
         if tools_to_uninstall.is_empty() {
@@ -5,10 +5,13 @@ use std::{env, sync::atomic::AtomicBool};
 
 use anyhow::{Context, Result};
 use semver::Version;
+use serde::Serialize;
 use tokio::sync::Notify;
 use url::Url;
 
+use super::dir_lock::InstallDirLock;
 use super::directories::RimDir;
+use super::history::{self, Action, HistoryEntry};
 use super::parser::release_info::ReleaseInfo;
 use super::parser::TomlParser;
 use crate::configuration::{Configuration, UpdateTarget};
@@ -45,8 +48,9 @@ impl UpdateOpt {
     where
         F: FnOnce(&Path) -> Result<()>,
     {
-        let dir = self.install_dir();
-        callback(dir).context("unable to update toolkit")
+        // NB: the lock of installation directory is acquired by the update operation itself,
+        // after the user has confirmed what to update.
+        callback(self.install_dir()).context("unable to update toolkit")
     }
 
     /// Update self when applicable.
@@ -66,13 +70,28 @@ impl UpdateOpt {
             return Ok(false);
         }
 
+        let latest_version = &latest_manager_release(self.insecure).await?.version;
+        let _lock = InstallDirLock::acquire(self.install_dir())?;
+        let result = self.replace_self(latest_version).await;
+        let entry = HistoryEntry::new(Action::SelfUpdate)
+            .from_version(Some(env!("CARGO_PKG_VERSION").to_string()))
+            .to_version(Some(latest_version.to_string()))
+            .with_result(&result);
+        history::record(self.install_dir(), entry);
+        result?;
+
+        info!("{}", t!("self_update_complete"));
+        Ok(true)
+    }
+
+    /// Download the given version of manager, then replace the current running one with it.
+    async fn replace_self(&self, latest_version: &Version) -> Result<()> {
         #[cfg(not(feature = "gui"))]
         let cli = "-cli";
         #[cfg(feature = "gui")]
         let cli = "";
 
         let src_name = utils::exe!(format!("{}-manager{cli}", t!("vendor_en")));
-        let latest_version = &latest_manager_release(self.insecure).await?.version;
         let download_url = parse_download_url(&format!(
             "manager/archive/{latest_version}/{}/{src_name}",
             env!("TARGET"),
@@ -96,9 +115,7 @@ impl UpdateOpt {
 
         // replace the current executable
         self_replace::self_replace(newer_manager)?;
-
-        info!("{}", t!("self_update_complete"));
-        Ok(true)
+        Ok(())
     }
 }
 
@@ -121,14 +138,19 @@ async fn latest_manager_release(insecure: bool) -> Result<&'static ReleaseInfo>
     Ok(LATEST_RELEASE.get_or_init(|| release_info))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
 pub enum UpdateKind<T: Sized> {
-    Newer { current: T, latest: T },
+    Newer {
+        current: T,
+        latest: T,
+    },
     Uncertain,
+    #[serde(rename = "up-to-date")]
     UnNeeded,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct UpdatePayload {
     pub version: String,
     pub url: Option<String>,
@@ -0,0 +1,194 @@
+//! Verify the integrity of installed tools, using the list of files
+//! recorded in [`InstallationRecord`] at installation time.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use super::parser::fingerprint::{FileRecord, InstallationRecord, ToolRecord};
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProblemKind {
+    /// A recorded file no longer exists.
+    Missing,
+    /// A recorded file exists, but its size or checksum has changed.
+    Modified,
+    /// A file that was not placed by the installation exists in a tool's directory.
+    Extra,
+}
+
+impl Display for ProblemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Missing => t!("missing"),
+            Self::Modified => t!("modified"),
+            Self::Extra => t!("extra"),
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct Problem {
+    pub(crate) kind: ProblemKind,
+    pub(crate) path: PathBuf,
+}
+
+impl Problem {
+    fn new(kind: ProblemKind, path: &Path) -> Self {
+        Self {
+            kind,
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+/// Verification result of a single tool.
+#[derive(Debug, Serialize)]
+pub(crate) struct ToolReport {
+    pub(crate) name: String,
+    /// `false` if there are no file records of this tool, which is the case
+    /// for tools installed by `cargo`, or tools installed by an older version of this program,
+    /// the integrity of such tools can only be roughly checked by their paths.
+    pub(crate) tracked: bool,
+    pub(crate) problems: Vec<Problem>,
+}
+
+impl ToolReport {
+    pub(crate) fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Verify every installed tool, or just the one named `component` if provided.
+///
+/// # Error
+/// Return error if `component` was given but it is not an installed tool.
+pub(crate) fn verify_installation(
+    record: &InstallationRecord,
+    component: Option<&str>,
+) -> Result<Vec<ToolReport>> {
+    if let Some(name) = component {
+        let Some(tool) = record.tools.get(name) else {
+            bail!("{}", t!("component_not_installed", name = name));
+        };
+        return Ok(vec![verify_tool(name, tool)?]);
+    }
+
+    record
+        .tools
+        .iter()
+        .map(|(name, tool)| verify_tool(name, tool))
+        .collect()
+}
+
+/// Compare the files on disk with the records of a tool.
+pub(crate) fn verify_tool(name: &str, record: &ToolRecord) -> Result<ToolReport> {
+    let mut problems = vec![];
+
+    if record.files.is_empty() {
+        for path in &record.paths {
+            if !path.exists() {
+                problems.push(Problem::new(ProblemKind::Missing, path));
+            }
+        }
+        return Ok(ToolReport {
+            name: name.to_string(),
+            tracked: false,
+            problems,
+        });
+    }
+
+    let recorded: HashMap<&Path, &FileRecord> = record
+        .files
+        .iter()
+        .map(|file| (file.path.as_path(), file))
+        .collect();
+
+    for file in &record.files {
+        if !file.path.is_file() {
+            problems.push(Problem::new(ProblemKind::Missing, &file.path));
+        } else if file.path.metadata()?.len() != file.size
+            || utils::file_sha256(&file.path)? != file.sha256
+        {
+            problems.push(Problem::new(ProblemKind::Modified, &file.path));
+        }
+    }
+
+    // Look for unknown files, only directories are checked, because other locations,
+    // such as cargo's `bin` folder, are shared with other programs.
+    for dir in record.paths.iter().filter(|p| p.is_dir()) {
+        for entry in utils::walk_dir(dir, true)? {
+            if entry.is_file() && !recorded.contains_key(entry.as_path()) {
+                problems.push(Problem::new(ProblemKind::Extra, &entry));
+            }
+        }
+    }
+
+    Ok(ToolReport {
+        name: name.to_string(),
+        tracked: true,
+        problems,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::tools::ToolKind;
+
+    #[test]
+    fn verify_tool_files() {
+        let root = tempfile::tempdir().unwrap();
+        let tool_dir = root.path().join("tool");
+        let bin_dir = tool_dir.join("bin");
+        utils::ensure_dir(&bin_dir).unwrap();
+        utils::write_bytes(bin_dir.join("a"), b"a", false).unwrap();
+        utils::write_bytes(bin_dir.join("b"), b"b", false).unwrap();
+        utils::write_bytes(bin_dir.join("c"), b"c", false).unwrap();
+
+        let record = ToolRecord::new(ToolKind::DirWithBin).with_paths(vec![tool_dir.clone()]);
+        let files = record.collect_files().unwrap();
+        let record = record.with_files(files);
+
+        let report = verify_tool("tool", &record).unwrap();
+        assert!(report.tracked);
+        assert!(report.is_ok());
+
+        std::fs::remove_file(bin_dir.join("a")).unwrap();
+        utils::write_bytes(bin_dir.join("b"), b"modified", false).unwrap();
+        utils::write_bytes(bin_dir.join("d"), b"d", false).unwrap();
+
+        let mut problems = verify_tool("tool", &record).unwrap().problems;
+        problems.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            problems,
+            vec![
+                Problem::new(ProblemKind::Missing, &bin_dir.join("a")),
+                Problem::new(ProblemKind::Modified, &bin_dir.join("b")),
+                Problem::new(ProblemKind::Extra, &bin_dir.join("d")),
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_untracked_tool() {
+        let root = tempfile::tempdir().unwrap();
+        let missing = root.path().join("missing");
+        let record = ToolRecord::new(ToolKind::Unknown).with_paths(vec![missing.clone()]);
+
+        let report = verify_tool("tool", &record).unwrap();
+        assert!(!report.tracked);
+        assert_eq!(
+            report.problems,
+            vec![Problem::new(ProblemKind::Missing, &missing)]
+        );
+    }
+}
@@ -12,9 +12,14 @@ pub mod utils;
 
 // Exports
 pub use core::install::{default_install_dir, EnvConfig, InstallConfiguration};
-pub use core::parser::{configuration, fingerprint, get_installed_dir, toolset_manifest};
+pub use core::parser::{
+    configuration, fingerprint, get_installed_dir, installations, toolset_manifest,
+};
 pub use core::try_it::try_it;
 pub use core::uninstall::UninstallConfiguration;
-pub use core::{components, toolkit, update, AppInfo, Language, Mode};
+pub use core::{
+    components, error, history, journal, lock, repair, rollback, toolkit, update, AppInfo,
+    Language, Mode,
+};
 
 i18n!("locales", fallback = "en");
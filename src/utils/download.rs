@@ -1,15 +1,22 @@
 use std::cmp::min;
-use std::path::Path;
-use std::time::Duration;
+use std::fmt::Display;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context, Result};
-use indicatif::ProgressBar;
-use reqwest::{header, Client};
+use indicatif::{MultiProgress, ProgressBar};
+use reqwest::{header, Client, StatusCode};
+use serde::Serialize;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
+use tokio::task::JoinSet;
 use url::Url;
 
 use super::progress_bar::{CliProgress, Style};
+use crate::configuration::DownloadConf;
+use crate::core::error::{ErrorKind, ResultExt};
 use crate::core::GlobalOpts;
 use crate::setter;
 use crate::toolset_manifest::Proxy as CrateProxy;
@@ -31,6 +38,12 @@ pub struct DownloadOpt<T: Sized> {
     pub proxy: Option<CrateProxy>,
     /// Whether or not to resuming previous download.
     resume: bool,
+    /// The expected SHA-256 checksum of the downloaded file, if known.
+    sha256: Option<String>,
+    /// Show the progress bar in a group, when downloading multiple files at the same time.
+    multi_progress: Option<MultiProgress>,
+    /// How to retry after failed with transient errors.
+    retry: RetryPolicy,
 }
 
 impl DownloadOpt<ProgressBar> {
@@ -42,6 +55,9 @@ impl DownloadOpt<ProgressBar> {
             insecure: false,
             proxy: None,
             resume: false,
+            sha256: None,
+            multi_progress: None,
+            retry: DownloadConf::current().retry_policy(),
         }
     }
 
@@ -49,6 +65,9 @@ impl DownloadOpt<ProgressBar> {
     setter!(with_handler(self.handler, Option<CliProgress<ProgressBar>>));
     setter!(insecure(self.insecure, bool));
     setter!(resume(self.resume, bool));
+    setter!(with_sha256(self.sha256, Option<String>));
+    setter!(with_multi_progress(self.multi_progress, Option<MultiProgress>));
+    setter!(with_retry(self.retry, RetryPolicy));
 
     /// Build and return a client for download
     fn client(&self) -> Result<Client> {
@@ -72,6 +91,45 @@ impl DownloadOpt<ProgressBar> {
     /// If the `url` is a local file, this will use [`read_to_string`](fs::read_to_string) to
     /// get the text instead.
     pub async fn read(self, url: &Url) -> Result<String> {
+        self.with_retry_(|_| self.read_(url))
+            .await
+            .with_kind(ErrorKind::Download)
+    }
+
+    /// Run `op` until it succeeded, or failed with an error that is not [retryable](is_retryable),
+    /// or there is no more retries left according to the [`RetryPolicy`].
+    ///
+    /// `op` is given the number of retries that have been made, which is `0` at the first attempt.
+    async fn with_retry_<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retried = 0;
+        loop {
+            match op(retried).await {
+                Err(e) if retried < self.retry.retries && is_retryable(&e) => {
+                    retried += 1;
+                    let delay = self.retry.delay(retried);
+                    warn!(
+                        "{}",
+                        t!(
+                            "download_retry",
+                            name = self.name,
+                            reason = format!("{e:#}"),
+                            seconds = format!("{:.1}", delay.as_secs_f32()),
+                            attempt = retried,
+                            max = self.retry.retries
+                        )
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn read_(&self, url: &Url) -> Result<String> {
         if url.scheme() == "file" {
             let file_url = url
                 .to_file_path()
@@ -98,14 +156,27 @@ impl DownloadOpt<ProgressBar> {
         if resp.status().is_success() {
             Ok(resp.text().await?)
         } else {
-            bail!(
-                "unable to get text content of url '{url}': server responded with error {}",
-                resp.status()
-            );
+            Err(StatusError(resp.status()))
+                .with_context(|| format!("unable to get text content of url '{url}'"))
         }
     }
     /// Consume self, and download from given `Url` to `Path`.
+    ///
+    /// If resuming is enabled, the download continues from the end of the existing file,
+    /// as long as the server confirms that the file has not changed since then,
+    /// otherwise it starts over. The length of the downloaded file is then verified
+    /// if the server told it, along with its [checksum](Self::with_sha256) if there is one,
+    /// which is the only way to tell if a file of unknown length is complete.
+    ///
+    /// The download is retried according to the [`RetryPolicy`] if failed with transient errors,
+    /// which continues with the bytes that were already downloaded.
     pub async fn download(self, url: &Url, path: &Path) -> Result<()> {
+        self.with_retry_(|retried| self.download_(url, path, self.resume || retried > 0))
+            .await
+            .with_kind(ErrorKind::Download)
+    }
+
+    async fn download_(&self, url: &Url, path: &Path, resume: bool) -> Result<()> {
         if url.scheme() == "file" {
             fs::copy(
                 url.to_file_path()
@@ -113,38 +184,56 @@ impl DownloadOpt<ProgressBar> {
                 path,
             )
             .await?;
-            return Ok(());
+            return self.verify_checksum(path).await;
         }
 
         if self.insecure {
             warn!("{}", t!("insecure_download"));
         }
 
-        let helper = DownloadHelper::new(&self.client()?, url, path, self.resume).await?;
+        let helper = DownloadHelper::new(&self.client()?, url, path, resume).await?;
         let (mut resp, mut file, mut downloaded_bytes) =
             (helper.response, helper.file, helper.downloaded_bytes);
 
-        let total_size = resp
-            .content_length()
-            .ok_or_else(|| anyhow!("unable to get file length of '{url}'"))?;
-
-        let maybe_indicator = self.handler.as_ref().and_then(|h| {
-            (h.start)(
-                format!("downloading '{}'", &self.name),
-                Style::Bytes(total_size),
-            )
-            .ok()
-        });
+        // the length is unknown if the server uses chunked transfer encoding
+        let total_size = helper.total_size;
+        let style = match total_size {
+            Some(total) => Style::Bytes(total),
+            None => Style::UnknownBytes,
+        };
+        let maybe_indicator = self
+            .handler
+            .as_ref()
+            .and_then(|h| (h.start)(format!("downloading '{}'", &self.name), style).ok());
+        if let (Some(indicator), Some(multi)) = (&maybe_indicator, &self.multi_progress) {
+            multi.add(indicator.clone());
+        }
 
+        let mut reporter = ProgressReporter::new(&self.name, total_size);
+        reporter.report(downloaded_bytes, false);
         while let Some(chunk) = resp.chunk().await? {
             file.write_all(&chunk).await?;
 
-            downloaded_bytes = min(downloaded_bytes + chunk.len() as u64, total_size);
+            downloaded_bytes += chunk.len() as u64;
+            let pos = total_size.map_or(downloaded_bytes, |total| min(downloaded_bytes, total));
             if let Some(indicator) = &maybe_indicator {
                 // safe to unwrap, because indicator won't exist if self.handler is none
-                (self.handler.as_ref().unwrap().update)(indicator, Some(downloaded_bytes));
+                (self.handler.as_ref().unwrap().update)(indicator, Some(pos));
             }
+            reporter.report(pos, false);
         }
+        file.flush().await?;
+
+        if let Some(total_size) = total_size.filter(|total| *total != downloaded_bytes) {
+            return Err(LengthMismatch {
+                expected: total_size,
+                actual: downloaded_bytes,
+            })
+            .with_context(|| format!("incomplete download from '{url}'"));
+        }
+        // the file is complete, there is nothing to resume anymore
+        remove_validator(path).await?;
+        self.verify_checksum(path).await?;
 
         if let Some(indicator) = &maybe_indicator {
             // safe to unwrap, because indicator won't exist if self.handler is none
@@ -153,10 +242,34 @@ impl DownloadOpt<ProgressBar> {
                 format!("'{}' successfully downloaded.", &self.name),
             );
         }
+        reporter.report(downloaded_bytes, true);
 
         Ok(())
     }
 
+    /// Check the downloaded file at `path` against the expected checksum if there is one,
+    /// the file is removed if it does not match, so it will not be resumed next time.
+    async fn verify_checksum(&self, path: &Path) -> Result<()> {
+        let Some(expected) = &self.sha256 else {
+            return Ok(());
+        };
+        let file = path.to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || super::file_sha256(file)).await??;
+        if !actual.eq_ignore_ascii_case(expected) {
+            fs::remove_file(path).await?;
+            bail!(
+                "{}",
+                t!(
+                    "download_checksum_mismatch",
+                    name = self.name,
+                    expected = expected,
+                    actual = actual
+                )
+            );
+        }
+        Ok(())
+    }
+
     /// Consume self, and download from given `Url` to `Path`.
     ///
     /// Note: This will block the current thread until the download is finished.
@@ -165,19 +278,325 @@ impl DownloadOpt<ProgressBar> {
     }
 }
 
+/// The progress of a download, which is reported to the listener set by
+/// [`set_progress_listener`], such as to be displayed in GUI.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    /// The verbose name of the file.
+    pub name: String,
+    /// The number of bytes that have been downloaded.
+    pub downloaded: u64,
+    /// The length of the whole file, `None` if the server does not tell.
+    pub total: Option<u64>,
+    pub finished: bool,
+}
+
+type ProgressListener = Box<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+static PROGRESS_LISTENER: OnceLock<ProgressListener> = OnceLock::new();
+
+/// Report the progress of every download to `listener`, in addition to the progress bars.
+///
+/// The listener can only be set once, the later ones are ignored.
+pub fn set_progress_listener<F>(listener: F)
+where
+    F: Fn(&DownloadProgress) + Send + Sync + 'static,
+{
+    if PROGRESS_LISTENER.set(Box::new(listener)).is_err() {
+        debug!("download progress listener has already been set");
+    }
+}
+
+/// Report the progress of a download to the [listener](set_progress_listener) if there is one,
+/// at most once in every [`REPORT_INTERVAL`](Self::REPORT_INTERVAL) until it's finished.
+struct ProgressReporter {
+    progress: DownloadProgress,
+    last_report: Option<Instant>,
+}
+
+impl ProgressReporter {
+    const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+    fn new(name: &str, total: Option<u64>) -> Self {
+        Self {
+            progress: DownloadProgress {
+                name: name.to_string(),
+                downloaded: 0,
+                total,
+                finished: false,
+            },
+            last_report: None,
+        }
+    }
+
+    fn report(&mut self, downloaded: u64, finished: bool) {
+        let Some(listener) = PROGRESS_LISTENER.get() else {
+            return;
+        };
+        if !finished
+            && self
+                .last_report
+                .is_some_and(|last| last.elapsed() < Self::REPORT_INTERVAL)
+        {
+            return;
+        }
+        self.progress.downloaded = downloaded;
+        self.progress.finished = finished;
+        self.last_report = Some(Instant::now());
+        listener(&self.progress);
+    }
+}
+
+/// How to retry a request after it failed with errors that are likely transient,
+/// such as connection resets, timeouts and `5xx` responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of times to retry after the first attempt failed, `0` disables retrying.
+    pub retries: u32,
+    /// The delay before the first retry, which is doubled for each following retry.
+    pub initial_delay: Duration,
+    /// The maximum delay before a retry.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Return the delay before the `n`th retry, counting from `1`.
+    ///
+    /// The delay grows exponentially, and a random half of it is jittered,
+    /// so that the clients that failed at the same time don't retry at the same time.
+    pub fn delay(&self, n: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(n.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay.max(self.initial_delay));
+        delay / 2 + (delay / 2).mul_f64(random_fraction())
+    }
+}
+
+/// Return a random number in `[0, 1)`.
+fn random_fraction() -> f64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    // the keys of `RandomState` are random, which is enough for jitter
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// The server responded with an error status.
+#[derive(Debug)]
+struct StatusError(StatusCode);
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server responded with error {}", self.0)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// The length of the downloaded file is not what the server said,
+/// such as when the connection was closed early.
+#[derive(Debug)]
+struct LengthMismatch {
+    expected: u64,
+    actual: u64,
+}
+
+impl Display for LengthMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expecting {} bytes, got {} bytes",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for LengthMismatch {}
+
+/// Return `true` if `error` is likely transient, so that the request could succeed if retried,
+/// such as a timeout, a connection reset, or a `5xx` response.
+fn is_retryable(error: &anyhow::Error) -> bool {
+    let is_retryable_status = |status: StatusCode| {
+        (status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED)
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+    };
+    error.chain().any(|e| {
+        if let Some(StatusError(status)) = e.downcast_ref::<StatusError>() {
+            is_retryable_status(*status)
+        } else if e.is::<LengthMismatch>() {
+            // the rest of it can be downloaded by resuming, or it will start over if it's too long
+            true
+        } else if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+            match e.status() {
+                Some(status) => is_retryable_status(status),
+                None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            }
+        } else {
+            false
+        }
+    })
+}
+
+/// A file to download with [`download_all`].
+#[derive(Debug, Clone)]
+pub struct DownloadTask {
+    /// The verbose name of the file.
+    pub name: String,
+    pub url: Url,
+    pub dest: PathBuf,
+}
+
+/// Download multiple files concurrently, with at most `limit` of them at the same time.
+///
+/// `opt` creates the [`DownloadOpt`] of each task, the progress bars of them are grouped
+/// along with an overall one. `on_finished` is called once a file is downloaded,
+/// in the order of completion.
+///
+/// The remaining downloads are cancelled once any of them failed.
+pub async fn download_all<O, F>(
+    tasks: Vec<DownloadTask>,
+    limit: usize,
+    opt: O,
+    mut on_finished: F,
+) -> Result<()>
+where
+    O: Fn(&DownloadTask) -> DownloadOpt<ProgressBar>,
+    F: FnMut(&DownloadTask) -> Result<()>,
+{
+    let total = tasks.len();
+    let multi = (!GlobalOpts::get().quiet).then(MultiProgress::new);
+    let overall = match &multi {
+        Some(multi) => {
+            let bar = (CliProgress::new().start)(
+                t!("downloading_packages", count = total).to_string(),
+                Style::Len(total as u64),
+            )?;
+            Some(multi.add(bar))
+        }
+        None => None,
+    };
+
+    let mut pending = tasks.into_iter();
+    let mut running = JoinSet::new();
+    let mut spawn_next = |running: &mut JoinSet<_>| {
+        if let Some(task) = pending.next() {
+            let dl = opt(&task).with_multi_progress(multi.clone());
+            running.spawn(async move { dl.download(&task.url, &task.dest).await.map(|_| task) });
+        }
+    };
+    for _ in 0..limit.max(1) {
+        spawn_next(&mut running);
+    }
+
+    // dropping the join set aborts the remaining downloads when returning early with error
+    while let Some(res) = running.join_next().await {
+        let task = res??;
+        on_finished(&task)?;
+        if let Some(bar) = &overall {
+            bar.inc(1);
+        }
+        spawn_next(&mut running);
+    }
+
+    if let Some(bar) = &overall {
+        bar.finish_with_message(t!("packages_downloaded", count = total).to_string());
+    }
+    Ok(())
+}
+
+/// The suffix of the file that stores the validator of a partially downloaded file,
+/// which is the `ETag` or `Last-Modified` of the resource, so that when resuming,
+/// the server only returns the rest of the file if the resource has not changed since then.
+const VALIDATOR_SUFFIX: &str = ".validator";
+
+fn validator_path(path: &Path) -> PathBuf {
+    let mut validator = path.as_os_str().to_owned();
+    validator.push(VALIDATOR_SUFFIX);
+    PathBuf::from(validator)
+}
+
+async fn remove_validator(path: &Path) -> Result<()> {
+    match fs::remove_file(validator_path(path)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Get the validator that can be used in `If-Range` of a response, weak `ETag`s are not allowed.
+fn validator_of(response: &reqwest::Response) -> Option<&str> {
+    let headers = response.headers();
+    headers
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| {
+            headers
+                .get(header::LAST_MODIFIED)
+                .and_then(|date| date.to_str().ok())
+        })
+}
+
+/// Parse the value of a `Content-Range` header such as `bytes 100-199/200`,
+/// return the first byte position and the complete length, which might be unknown.
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let start = start.trim().parse::<u64>().ok()?;
+    let end = end.trim().parse::<u64>().ok()?;
+    if end < start {
+        return None;
+    }
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse::<u64>().ok().filter(|t| *t > end)?),
+    };
+    Some((start, total))
+}
+
 struct DownloadHelper {
     response: reqwest::Response,
     file: fs::File,
     /// The length of bytes that already got downloaded.
     downloaded_bytes: u64,
+    /// The length of the whole file, if known.
+    total_size: Option<u64>,
 }
 
 impl DownloadHelper {
     async fn new_without_resume(client: &Client, url: &Url, path: &Path) -> Result<Self> {
         let response = get_response_(client, url, None).await?;
-        let file = open_file_(path, true).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(StatusError(status))
+                .with_context(|| format!("unable to download from '{url}'"));
+        }
+        Self::start_over(response, path).await
+    }
 
+    /// Write the whole file from `response` to `path`, and keep the validator of it
+    /// in case it needs to be resumed.
+    async fn start_over(response: reqwest::Response, path: &Path) -> Result<Self> {
+        match validator_of(&response) {
+            Some(validator) => fs::write(validator_path(path), validator).await?,
+            None => remove_validator(path).await?,
+        }
+        let file = open_file_(path, true).await?;
         Ok(Self {
+            total_size: response.content_length(),
             response,
             file,
             downloaded_bytes: 0,
@@ -185,56 +604,288 @@ impl DownloadHelper {
     }
 
     async fn new(client: &Client, url: &Url, path: &Path, resume: bool) -> Result<Self> {
-        let (downloaded_bytes, file) = if resume {
-            let file = open_file_(path, false).await?;
-            let downloaded = file.metadata().await?.len();
-            (downloaded, file)
-        } else {
-            (0, open_file_(path, true).await?)
+        let downloaded_bytes = match fs::metadata(path).await {
+            Ok(meta) if resume => meta.len(),
+            _ => 0,
+        };
+        // a partially downloaded file can only be resumed if we know which version of
+        // the resource it was from, otherwise it might get mixed with another one
+        let validator = match downloaded_bytes {
+            0 => None,
+            _ => fs::read_to_string(validator_path(path)).await.ok(),
+        };
+        let Some(validator) = validator else {
+            return Self::new_without_resume(client, url, path).await;
         };
 
-        // resume from the next of downloaded byte
-        let resume_from = (downloaded_bytes != 0).then_some(downloaded_bytes + 1);
-        let response = get_response_(client, url, resume_from).await?;
-
+        // ranges are zero-based, so the offset is the number of bytes downloaded
+        let range = (downloaded_bytes, validator.trim());
+        let response = get_response_(client, url, Some(range)).await?;
         let status = response.status();
-        if status == 416 {
-            // 416: server does not support download range, retry without resuming
-            info!("download range not satisfiable, retrying without ranges header");
-
-            return Self::new_without_resume(client, url, path).await;
-        } else if !status.is_success() {
-            bail!("server returns error when attempting download from '{url}': {status}");
+        if status == StatusCode::PARTIAL_CONTENT {
+            let content_range = response
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|val| val.to_str().ok())
+                .and_then(parse_content_range);
+            let Some((start, total)) =
+                content_range.filter(|(start, _)| *start == downloaded_bytes)
+            else {
+                debug!(
+                    "unexpected content range when resuming download from '{url}', starting over"
+                );
+                return Self::new_without_resume(client, url, path).await;
+            };
+            let total_size = total.or_else(|| response.content_length().map(|len| start + len));
+            let file = open_file_(path, false).await?;
+            Ok(Self {
+                response,
+                file,
+                downloaded_bytes,
+                total_size,
+            })
+        } else if status == StatusCode::RANGE_NOT_SATISFIABLE {
+            // the existing file is not a part of the resource, such as being larger than it
+            debug!("download range of '{url}' not satisfiable, starting over");
+            Self::new_without_resume(client, url, path).await
+        } else if status.is_success() {
+            // the resource has changed, or the server does not support ranges
+            info!("{}", t!("download_restarted", url = url));
+            Self::start_over(response, path).await
+        } else {
+            Err(StatusError(status)).with_context(|| format!("unable to download from '{url}'"))
         }
-
-        Ok(Self {
-            response,
-            file,
-            downloaded_bytes,
-        })
     }
 }
 
+/// Open the file at `path` to write, the content is either truncated,
+/// or kept so that new content is appended to it.
 async fn open_file_(path: &Path, truncate: bool) -> Result<fs::File> {
     Ok(fs::OpenOptions::new()
         .create(true)
         .write(true)
         .truncate(truncate)
+        .append(!truncate)
         .open(path)
         .await?)
 }
 
+/// Send a request to download from `url`, or only the part starting from an offset
+/// if `range` is given, along with the validator of the part that was downloaded.
 async fn get_response_(
     client: &Client,
     url: &Url,
-    resume_from: Option<u64>,
+    range: Option<(u64, &str)>,
 ) -> Result<reqwest::Response> {
     let mut builder = client.get(url.as_ref());
-    if let Some(bytes) = resume_from {
-        builder = builder.header(header::RANGE, format!("bytes={bytes}-"));
+    if let Some((offset, validator)) = range {
+        builder = builder
+            .header(header::RANGE, format!("bytes={offset}-"))
+            .header(header::IF_RANGE, validator);
     }
     let resp = builder.send().await.with_context(|| {
         format!("failed to receive surver response when downloading from '{url}'")
     })?;
     Ok(resp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Serve `content` with `etag` over HTTP on a local port, supporting `Range` and `If-Range`,
+    /// the `Range` headers of the requests are recorded.
+    fn serve(content: &'static [u8], etag: &'static str) -> (Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/pkg.tar.gz", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(vec![]));
+        let recorded = ranges.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut headers = HashMap::new();
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((key, val)) = line.split_once(": ") {
+                        headers.insert(key.to_lowercase(), val.to_string());
+                    }
+                }
+
+                let range = headers.get("range").cloned();
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(range.clone().unwrap_or_default());
+                let offset = range
+                    .filter(|_| headers.get("if-range").map(String::as_str) == Some(etag))
+                    .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok());
+                let head = match offset {
+                    Some(offset) if offset >= content.len() => {
+                        "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0".to_string()
+                    }
+                    Some(offset) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {offset}-{}/{}\r\nContent-Length: {}",
+                        content.len() - 1,
+                        content.len(),
+                        content.len() - offset
+                    ),
+                    None => format!("HTTP/1.1 200 OK\r\nContent-Length: {}", content.len()),
+                };
+                let body = match (offset, head.contains("416")) {
+                    (_, true) => &[][..],
+                    (Some(offset), _) => &content[offset..],
+                    (None, _) => content,
+                };
+                write!(
+                    stream,
+                    "{head}\r\nETag: {etag}\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        (url.parse().unwrap(), ranges)
+    }
+
+    /// Serve `content` over HTTP on a local port with chunked transfer encoding,
+    /// so the length of it is unknown to clients.
+    fn serve_chunked(content: &'static [u8]) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/pkg.tar.gz", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let request = BufReader::new(&stream).lines().map_while(Result::ok);
+                request.take_while(|line| !line.is_empty()).for_each(drop);
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+                for chunk in content.chunks(4) {
+                    write!(stream, "{:x}\r\n", chunk.len()).unwrap();
+                    stream.write_all(chunk).unwrap();
+                    write!(stream, "\r\n").unwrap();
+                }
+                write!(stream, "0\r\n\r\n").unwrap();
+            }
+        });
+        url.parse().unwrap()
+    }
+
+    fn download(url: &Url, path: &Path, sha256: Option<&str>) -> Result<()> {
+        let opt = DownloadOpt::new("pkg")
+            .with_handler(None)
+            .with_sha256(sha256.map(ToString::to_string))
+            .resume(true);
+        crate::utils::blocking!(opt.download(url, path))
+    }
+
+    #[test]
+    fn resume_from_downloaded_bytes() {
+        let (url, ranges) = serve(b"0123456789", "\"v1\"");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pkg.tar.gz");
+        std::fs::write(&path, "0123").unwrap();
+        std::fs::write(validator_path(&path), "\"v1\"").unwrap();
+
+        download(&url, &path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0123456789");
+        assert_eq!(*ranges.lock().unwrap(), ["bytes=4-"]);
+        // nothing to resume after finished
+        assert!(!validator_path(&path).exists());
+    }
+
+    #[test]
+    fn start_over_if_cannot_resume() {
+        let (url, ranges) = serve(b"new content", "\"v2\"");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pkg.tar.gz");
+
+        // the resource has changed
+        std::fs::write(&path, "old").unwrap();
+        std::fs::write(validator_path(&path), "\"v1\"").unwrap();
+        download(&url, &path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new content");
+
+        // the partial file is larger than the resource
+        std::fs::write(&path, "new content, but longer").unwrap();
+        std::fs::write(validator_path(&path), "\"v2\"").unwrap();
+        download(&url, &path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new content");
+
+        // unknown where the partial file is from
+        std::fs::write(&path, "new").unwrap();
+        download(&url, &path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new content");
+
+        assert_eq!(*ranges.lock().unwrap(), ["bytes=3-", "bytes=23-", "", ""]);
+    }
+
+    #[test]
+    fn verify_downloaded_checksum() {
+        let (url, _) = serve(b"content", "\"v1\"");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pkg.tar.gz");
+        let checksum = "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73";
+
+        download(&url, &path, Some(checksum)).unwrap();
+        assert!(download(&url, &path, Some("abcd")).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn download_unknown_length() {
+        let url = serve_chunked(b"chunked content");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pkg.tar.gz");
+
+        download(&url, &path, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "chunked content");
+        let actual = crate::utils::file_sha256(&path).unwrap();
+        // completeness can only be told by checksum
+        download(&url, &path, Some(&actual)).unwrap();
+        assert!(download(&url, &path, Some("abcd")).is_err());
+    }
+
+    #[test]
+    fn content_range() {
+        assert_eq!(parse_content_range("bytes 4-9/10"), Some((4, Some(10))));
+        assert_eq!(parse_content_range("bytes 4-9/*"), Some((4, None)));
+        assert_eq!(parse_content_range("bytes 4-9/9"), None);
+        assert_eq!(parse_content_range("bytes 9-4/10"), None);
+        assert_eq!(parse_content_range("bytes */10"), None);
+    }
+
+    #[test]
+    fn retry_delay_grows_with_jitter() {
+        let policy = RetryPolicy {
+            retries: 10,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+        };
+        for (n, full) in [(1, 1), (2, 2), (3, 4), (4, 5), (10, 5)] {
+            let delay = policy.delay(n);
+            let full = Duration::from_secs(full);
+            assert!(delay >= full / 2 && delay <= full, "{delay:?} of retry {n}");
+        }
+    }
+
+    #[test]
+    fn retry_transient_errors_only() {
+        let status_err = |status| anyhow::Error::new(StatusError(status)).context("download");
+        assert!(is_retryable(&status_err(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(is_retryable(&status_err(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!is_retryable(&status_err(StatusCode::NOT_FOUND)));
+        assert!(!is_retryable(&status_err(StatusCode::NOT_IMPLEMENTED)));
+        assert!(!is_retryable(&anyhow!("invalid url")));
+    }
+}
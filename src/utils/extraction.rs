@@ -8,6 +8,7 @@ use std::path::{Path, PathBuf};
 use xz2::read::XzDecoder;
 use zip::ZipArchive;
 
+use crate::core::error::{ErrorKind, ResultExt};
 use crate::utils::progress_bar::Style;
 
 use super::progress_bar::CliProgress;
@@ -27,6 +28,10 @@ pub struct Extractable<'a> {
 
 impl<'a> Extractable<'a> {
     pub fn load(path: &'a Path, custom_kind: Option<&str>) -> Result<Self> {
+        Self::load_(path, custom_kind).with_kind(ErrorKind::Extraction)
+    }
+
+    fn load_(path: &'a Path, custom_kind: Option<&str>) -> Result<Self> {
         let ext = if let Some(custom) = custom_kind {
             custom
         } else {
@@ -93,12 +98,13 @@ impl<'a> Extractable<'a> {
             indicator: CliProgress::new(),
         };
 
-        match &mut self.kind {
+        let res = match &mut self.kind {
             ExtractableKind::Zip(archive) => helper.extract_zip(archive),
             ExtractableKind::SevenZ(archive) => helper.extract_7z(archive),
             ExtractableKind::Gz(archive) => helper.extract_tar(archive),
             ExtractableKind::Xz(archive) => helper.extract_tar(archive),
-        }
+        };
+        res.with_kind(ErrorKind::Extraction)
     }
 
     /// Extract file into a specific root like [`extract_to`](Extractable::extract_to),
@@ -329,3 +329,18 @@ pub fn make_temp_file(prefix: &str, root: Option<&Path>) -> Result<NamedTempFile
 pub fn extension_str(path: &Path) -> Option<&str> {
     path.extension().and_then(|ext| ext.to_str())
 }
+
+/// Calculate the SHA-256 checksum of a file, returned as lowercase hex string.
+pub fn file_sha256<P: AsRef<Path>>(path: P) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = fs::File::open(path.as_ref()).with_context(|| {
+        format!(
+            "unable to open '{}' to calculate checksum",
+            path.as_ref().display()
+        )
+    })?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
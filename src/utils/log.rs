@@ -13,6 +13,8 @@ pub struct Logger {
     /// This level only effects displayed log,
     /// the file logger will still be using max log level.
     level: LevelFilter,
+    /// Print output on `stderr` instead of `stdout`.
+    use_stderr: bool,
 }
 
 impl Default for Logger {
@@ -31,6 +33,7 @@ impl Logger {
         Self {
             output_sender: None,
             level,
+            use_stderr: false,
         }
     }
     /// Set verbose output, this will print `trace!` messages as well.
@@ -47,6 +50,12 @@ impl Logger {
         }
         self
     }
+    /// Print output on `stderr` rather than `stdout`, which keeps `stdout` clean
+    /// for the output that might be parsed by other programs.
+    pub fn stderr(mut self, yes: bool) -> Self {
+        self.use_stderr = yes;
+        self
+    }
     /// Send output using a specific sender rather than printing on `stdout`.
     pub fn sender(mut self, sender: Sender<String>) -> Self {
         self.output_sender = Some(sender);
@@ -66,7 +75,7 @@ impl Logger {
             md.level() <= self.level && md.level() != LevelFilter::Trace
         };
 
-        // decide if `Sender` or `Stdout` should be used as message medium.
+        // decide if `Sender` or console (`stdout`/`stderr`) should be used as message medium.
         let output = if let Some(sender) = self.output_sender {
             fern::Dispatch::new()
                 .filter(filter_log_for_output)
@@ -78,20 +87,25 @@ impl Logger {
                 })
                 .chain(sender)
         } else {
-            fern::Dispatch::new()
-                .filter(filter_log_for_output)
-                .format(|out, msg, rec| {
-                    out.finish(format_args!(
-                        "{}: {msg}",
-                        ColoredLevelConfig::new()
-                            .info(Color::BrightBlue)
-                            .debug(Color::Magenta)
-                            .color(rec.level())
-                            .to_string()
-                            .to_lowercase(),
-                    ));
-                })
-                .chain(io::stdout())
+            let console =
+                fern::Dispatch::new()
+                    .filter(filter_log_for_output)
+                    .format(|out, msg, rec| {
+                        out.finish(format_args!(
+                            "{}: {msg}",
+                            ColoredLevelConfig::new()
+                                .info(Color::BrightBlue)
+                                .debug(Color::Magenta)
+                                .color(rec.level())
+                                .to_string()
+                                .to_lowercase(),
+                        ));
+                    });
+            if self.use_stderr {
+                console.chain(io::stderr())
+            } else {
+                console.chain(io::stdout())
+            }
         };
 
         let file_config = fern::Dispatch::new()
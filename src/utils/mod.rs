@@ -16,7 +16,9 @@ use std::{
     time::Duration,
 };
 
-pub use download::DownloadOpt;
+pub use download::{
+    download_all, set_progress_listener, DownloadOpt, DownloadProgress, DownloadTask, RetryPolicy,
+};
 pub use extraction::Extractable;
 pub use file_system::*;
 pub use log::{log_file_path, Logger};
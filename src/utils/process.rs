@@ -1,4 +1,5 @@
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 use std::process::{Command, ExitStatus};
 use std::{env, io};
 
@@ -138,3 +139,16 @@ pub fn cmd_exist<S: AsRef<str>>(cmd: S) -> bool {
         .map(|p| p.join(cmd.as_ref()))
         .any(|p| p.exists())
 }
+
+/// Find the path of a command/program that would be run from the `PATH`,
+/// which is the first match in the order of `PATH` entries.
+///
+/// The executable extension is appended automatically, i.e. `cargo` will be
+/// searched as `cargo.exe` on Windows.
+pub fn find_in_path<S: AsRef<str>>(cmd: S) -> Option<PathBuf> {
+    let path = env::var_os("PATH").unwrap_or_default();
+    let filename = format!("{}{}", cmd.as_ref(), env::consts::EXE_SUFFIX);
+    env::split_paths(&path)
+        .map(|p| p.join(&filename))
+        .find(|p| p.is_file())
+}
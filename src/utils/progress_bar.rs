@@ -78,6 +78,9 @@ pub struct CliProgress<T: Sized> {
 pub enum Style {
     /// Display the progress base on number of bytes.
     Bytes(u64),
+    /// Display the number of bytes with a spinner, when the total length is unknown,
+    /// such as downloading a file without `Content-Length`.
+    UnknownBytes,
     /// Display the progress base on position & length parameters.
     Len(u64),
     /// A spinner that spins as the progress goes, this does not require
@@ -93,6 +96,7 @@ impl Style {
         match self {
             Style::Bytes(_) => "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})",
             Style::Len(_) => "{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len} ({eta})",
+            Style::UnknownBytes => "{msg}\n{spinner:.green} [{elapsed_precise}] {bytes} ({binary_bytes_per_sec})",
             Style::Spinner{..} => "{spinner:.green} [{elapsed_precise}] {msg}"
         }
     }
@@ -122,6 +126,12 @@ impl CliProgress<CliProgressBar> {
             };
             let pb = match style {
                 Style::Bytes(total) | Style::Len(total) => CliProgressBar::new(total),
+                Style::UnknownBytes => {
+                    // keep spinning even if no data is received for a while
+                    let spinner = CliProgressBar::new_spinner();
+                    spinner.enable_steady_tick(Duration::from_millis(100));
+                    spinner
+                }
                 Style::Spinner { auto_tick_duration } => {
                     let spinner = CliProgressBar::new_spinner();
                     if let Some(dur) = auto_tick_duration {